@@ -0,0 +1,114 @@
+use blockchainApi::blockchain::{
+    block_chain::BlockChain,
+    transaction::{Metadata, TransactionInfo},
+    wallet::WalletInfo,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const READERS: usize = 4;
+const RUN_TIME: Duration = Duration::from_secs(2);
+
+//CREATE A BLOCKCHAIN WITH TWO WALLETS AND A FEW MINED BLOCKS
+fn setup() -> Arc<BlockChain> {
+    let mut blockchain = BlockChain::default();
+    blockchain.difficulty = 4;
+
+    for address in ["miner", "reader"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: i32::MAX,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+
+    Arc::new(blockchain)
+}
+
+//CREATE A TRANSACTION AND MINE IT UNTIL STOPPED, RETURN THE NUMBER OF MINED BLOCKS
+fn mine(blockchain: &BlockChain, stop: &AtomicBool) -> usize {
+    let mut blocks = 0;
+    while !stop.load(Ordering::Relaxed) {
+        blockchain
+            .create_transaction(TransactionInfo {
+                from_address: "miner".to_string(),
+                from_password: "pass".to_string(),
+                to_address: "reader".to_string(),
+                amount: 1,
+                fee: 0,
+                expiry: None,
+                nonce: None,
+                memo: None,
+                metadata: Metadata::new(),
+                lock: None,
+                hash_lock: None,
+            })
+            .unwrap();
+        blockchain.mine_pending_transactions("miner").unwrap();
+        blocks += 1;
+    }
+    blocks
+}
+
+//READ BALANCES AND THE CHAIN TIP FROM SEVERAL THREADS AND RETURN THE NUMBER OF READS PER SECOND
+fn run(with_mining: bool) -> (f64, usize) {
+    let blockchain = setup();
+    let stop = Arc::new(AtomicBool::new(false));
+    let reads = Arc::new(AtomicUsize::new(0));
+
+    let miner = with_mining.then(|| {
+        let (blockchain, stop) = (Arc::clone(&blockchain), Arc::clone(&stop));
+        thread::spawn(move || mine(&blockchain, &stop))
+    });
+
+    let readers: Vec<_> = (0..READERS)
+        .map(|_| {
+            let (blockchain, stop, reads) = (
+                Arc::clone(&blockchain),
+                Arc::clone(&stop),
+                Arc::clone(&reads),
+            );
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    blockchain.get_balance_of_wallet("reader", "pass").unwrap();
+                    let chain = blockchain.chain.read().unwrap();
+                    assert!(!chain[chain.len() - 1].hash.is_empty());
+                    reads.fetch_add(2, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+
+    let start = Instant::now();
+    thread::sleep(RUN_TIME);
+    stop.store(true, Ordering::Relaxed);
+
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    let blocks = miner.map_or(0, |miner| miner.join().unwrap());
+
+    (
+        reads.load(Ordering::Relaxed) as f64 / start.elapsed().as_secs_f64(),
+        blocks,
+    )
+}
+
+fn main() {
+    let (idle, _) = run(false);
+    println!("{} readers, no mining:   {:>12.0} reads/s", READERS, idle);
+
+    let (mining, blocks) = run(true);
+    println!(
+        "{} readers, while mining: {:>12.0} reads/s ({} blocks mined)",
+        READERS, mining, blocks
+    );
+}
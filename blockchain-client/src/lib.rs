@@ -0,0 +1,443 @@
+use blockchainApi::{
+    api::{
+        admin::{ExportQuery, ImportSummary},
+        idempotency::IDEMPOTENCY_KEY_HEADER,
+        node::{Health, NodeInfo, Readiness},
+        v1::{BatchRequest, BatchResponse, CoinsDeposit, WalletPassword, V1_PREFIX},
+    },
+    blockchain::{
+        block::BlockDetails,
+        escrow::ClaimInfo,
+        multisig::{
+            MultisigWalletDetails, MultisigWalletInfo, OwnerCredentials, Proposal, ProposalInfo,
+        },
+        query::{BlockQuery, HistoryEntry, HistoryQuery, Page},
+        snapshot::SnapshotFormat,
+        standing_order::{StandingOrder, StandingOrderInfo},
+        transaction::{TransactionDetails, TransactionInfo, TransactionStatus},
+        wallet::{MineRewardAddress, WalletBalance, WalletInfo},
+    },
+    logging::REDACTED,
+};
+use derive_more::Display;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+//THE ERRORS RETURNED BY THE CLIENT
+#[derive(Debug, Display)]
+pub enum ClientError {
+    //THE REQUEST COULD NOT BE SENT OR THE RESPONSE COULD NOT BE READ
+    #[display(fmt = "{}", _0)]
+    Http(reqwest::Error),
+    //THE NODE ANSWERED WITH AN ERROR, THE MESSAGE IS THE ONE SHOWN BY THE NODE
+    #[display(fmt = "{}: {}", status, message)]
+    Api { status: StatusCode, message: String },
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(error: reqwest::Error) -> Self {
+        ClientError::Http(error)
+    }
+}
+
+//A CLIENT FOR THE '/v1' API AND THE ENDPOINTS OF A NODE
+#[derive(Clone)]
+pub struct BlockchainClient {
+    base_url: String,
+    http: reqwest::Client,
+    admin_token: Option<String>,
+    idempotency_key: Option<String>,
+}
+
+impl BlockchainClient {
+    //CREATE A CLIENT FOR THE NODE AT THE GIVEN URL, FOR EXAMPLE 'http://127.0.0.1:8080'
+    pub fn new(base_url: &str) -> Self {
+        BlockchainClient::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    //CREATE A CLIENT THAT SENDS THE REQUESTS WITH AN ALREADY CONFIGURED HTTP CLIENT
+    pub fn with_http_client(base_url: &str, http: reqwest::Client) -> Self {
+        BlockchainClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http,
+            admin_token: None,
+            idempotency_key: None,
+        }
+    }
+
+    //SEND THE GIVEN TOKEN TO THE ADMIN ENDPOINTS
+    pub fn with_admin_token(mut self, admin_token: &str) -> Self {
+        self.admin_token = Some(admin_token.to_string());
+        self
+    }
+
+    //SEND THE GIVEN KEY IN THE 'Idempotency-Key' HEADER, THE NODE ONLY USES IT FOR THE REQUESTS THAT CHANGE SOMETHING
+    //A REQUEST SENT AGAIN WITH THE SAME KEY IS NOT EXECUTED TWICE, THE NODE RETURNS THE FIRST RESPONSE
+    pub fn with_idempotency_key(mut self, idempotency_key: &str) -> Self {
+        self.idempotency_key = Some(idempotency_key.to_string());
+        self
+    }
+
+    //CREATE A NEW WALLET
+    pub async fn create_wallet(&self, wallet: &WalletInfo) -> Result<WalletBalance, ClientError> {
+        self.send(self.http.post(self.v1("/wallets")).json(wallet))
+            .await
+    }
+
+    //ADD COINS TO A WALLET AND RETURN ITS NEW BALANCE
+    pub async fn add_coins(
+        &self,
+        address: &str,
+        password: &str,
+        coins: u32,
+    ) -> Result<WalletBalance, ClientError> {
+        let deposit = CoinsDeposit {
+            password: password.to_string(),
+            coins,
+        };
+        let url = self.v1(&format!("/wallets/{}/coins", address));
+
+        self.send(self.http.post(url).json(&deposit)).await
+    }
+
+    //SHOW THE BALANCE OF A WALLET
+    pub async fn balance(
+        &self,
+        address: &str,
+        password: &str,
+    ) -> Result<WalletBalance, ClientError> {
+        let url = self.v1(&format!("/wallets/{}/balance", address));
+
+        self.send(self.http.get(url).query(&password_query(password)))
+            .await
+    }
+
+    //SHOW A PAGE OF THE TRANSACTIONS OF A WALLET
+    pub async fn history(
+        &self,
+        address: &str,
+        password: &str,
+        query: &HistoryQuery,
+    ) -> Result<Page<HistoryEntry>, ClientError> {
+        let url = self.v1(&format!("/wallets/{}/history", address));
+
+        self.send(
+            self.http
+                .get(url)
+                .query(&password_query(password))
+                .query(query),
+        )
+        .await
+    }
+
+    //SEND COINS FROM A WALLET TO ANOTHER, THE TRANSACTION STAYS PENDING UNTIL IT IS MINED
+    pub async fn transfer(
+        &self,
+        transaction: &TransactionInfo,
+    ) -> Result<TransactionDetails, ClientError> {
+        self.send(self.http.post(self.v1("/transactions")).json(transaction))
+            .await
+    }
+
+    //SEND SEVERAL TRANSACTIONS TOGETHER AND RETURN WHAT HAPPENED TO EACH OF THEM
+    pub async fn transfer_batch(&self, batch: &BatchRequest) -> Result<BatchResponse, ClientError> {
+        self.send(self.http.post(self.v1("/transactions/batch")).json(batch))
+            .await
+    }
+
+    //SHOW A TRANSACTION AND ITS STATUS
+    pub async fn transaction(&self, id: &str) -> Result<TransactionStatus, ClientError> {
+        self.send(self.http.get(self.v1(&format!("/transactions/{}", id))))
+            .await
+    }
+
+    //CANCEL A PENDING TRANSACTION WITH THE PASSWORD OF ITS SENDER, THE COINS TAKEN FOR IT GO BACK TO THE SENDER
+    pub async fn cancel_transaction(
+        &self,
+        id: &str,
+        password: &str,
+    ) -> Result<TransactionStatus, ClientError> {
+        let url = self.v1(&format!("/transactions/{}/cancel", id));
+
+        self.send(self.http.post(url).json(&password_query(password)))
+            .await
+    }
+
+    //CLAIM THE COINS OF AN ESCROW AS ITS RECIPIENT WITH THE PREIMAGE OF ITS HASH, RETURN THE CLAIM TRANSACTION
+    pub async fn claim_escrow(
+        &self,
+        id: &str,
+        password: &str,
+        preimage: &str,
+    ) -> Result<TransactionDetails, ClientError> {
+        let url = self.v1(&format!("/escrows/{}/claim", id));
+        let claim = ClaimInfo {
+            password: password.to_string(),
+            preimage: preimage.to_string(),
+        };
+
+        self.send(self.http.post(url).json(&claim)).await
+    }
+
+    //GIVE THE COINS OF AN ESCROW BACK TO ITS SENDER AFTER THE TIMEOUT, RETURN THE REFUND TRANSACTION
+    pub async fn refund_escrow(
+        &self,
+        id: &str,
+        password: &str,
+    ) -> Result<TransactionDetails, ClientError> {
+        let url = self.v1(&format!("/escrows/{}/refund", id));
+
+        self.send(self.http.post(url).json(&password_query(password)))
+            .await
+    }
+
+    //CREATE A MULTISIGNATURE WALLET, TRANSFERS FROM IT MUST BE APPROVED BY 'threshold' OF ITS OWNERS
+    pub async fn create_multisig_wallet(
+        &self,
+        wallet: &MultisigWalletInfo,
+    ) -> Result<MultisigWalletDetails, ClientError> {
+        self.send(self.http.post(self.v1("/wallets/multisig")).json(wallet))
+            .await
+    }
+
+    //PROPOSE A TRANSFER FROM A MULTISIGNATURE WALLET AS ONE OF ITS OWNERS
+    pub async fn propose_transfer(
+        &self,
+        address: &str,
+        proposal: &ProposalInfo,
+    ) -> Result<Proposal, ClientError> {
+        let url = self.v1(&format!("/wallets/{}/proposals", address));
+
+        self.send(self.http.post(url).json(proposal)).await
+    }
+
+    //APPROVE A PROPOSAL AS ONE OF THE OWNERS OF ITS WALLET
+    pub async fn approve_proposal(
+        &self,
+        id: &str,
+        owner: &str,
+        password: &str,
+    ) -> Result<Proposal, ClientError> {
+        let url = self.v1(&format!("/proposals/{}/approve", id));
+
+        self.send(
+            self.http
+                .post(url)
+                .json(&owner_credentials(owner, password)),
+        )
+        .await
+    }
+
+    //SHOW A PROPOSAL AND WHO APPROVED IT
+    pub async fn proposal(
+        &self,
+        id: &str,
+        owner: &str,
+        password: &str,
+    ) -> Result<Proposal, ClientError> {
+        let url = self.v1(&format!("/proposals/{}", id));
+
+        self.send(
+            self.http
+                .get(url)
+                .query(&owner_credentials(owner, password)),
+        )
+        .await
+    }
+
+    //SHOW THE PROPOSALS OF A MULTISIGNATURE WALLET, THE OLDEST FIRST
+    pub async fn proposals(
+        &self,
+        address: &str,
+        owner: &str,
+        password: &str,
+    ) -> Result<Vec<Proposal>, ClientError> {
+        let url = self.v1(&format!("/wallets/{}/proposals", address));
+
+        self.send(
+            self.http
+                .get(url)
+                .query(&owner_credentials(owner, password)),
+        )
+        .await
+    }
+
+    //CREATE A STANDING ORDER, THE NODE PAYS IT FROM THE WALLET OF THE PAYER AT EVERY INTERVAL
+    pub async fn create_standing_order(
+        &self,
+        order: &StandingOrderInfo,
+    ) -> Result<StandingOrder, ClientError> {
+        self.send(self.http.post(self.v1("/standing-orders")).json(order))
+            .await
+    }
+
+    //SHOW THE STANDING ORDERS PAID FROM A WALLET
+    pub async fn standing_orders(
+        &self,
+        address: &str,
+        password: &str,
+    ) -> Result<Vec<StandingOrder>, ClientError> {
+        let url = self.v1(&format!("/wallets/{}/standing-orders", address));
+
+        self.send(self.http.get(url).query(&password_query(password)))
+            .await
+    }
+
+    //REVOKE A STANDING ORDER WITH THE PASSWORD OF THE PAYER
+    pub async fn revoke_standing_order(
+        &self,
+        id: &str,
+        password: &str,
+    ) -> Result<StandingOrder, ClientError> {
+        let url = self.v1(&format!("/standing-orders/{}/revoke", id));
+
+        self.send(self.http.post(url).json(&password_query(password)))
+            .await
+    }
+
+    //MINE THE PENDING TRANSACTIONS INTO A NEW BLOCK AND REWARD THE GIVEN ADDRESS
+    pub async fn mine(&self, mining_reward_address: &str) -> Result<BlockDetails, ClientError> {
+        let reward_address = MineRewardAddress {
+            mining_reward_address: mining_reward_address.to_string(),
+        };
+
+        self.send(self.http.post(self.v1("/blocks")).json(&reward_address))
+            .await
+    }
+
+    //SHOW THE LAST BLOCK OF THE CHAIN
+    pub async fn latest_block(&self) -> Result<BlockDetails, ClientError> {
+        self.send(self.http.get(self.v1("/blocks/latest"))).await
+    }
+
+    //SHOW THE BLOCK AT THE GIVEN HEIGHT
+    pub async fn block(&self, height: usize) -> Result<BlockDetails, ClientError> {
+        self.send(self.http.get(self.v1(&format!("/blocks/{}", height))))
+            .await
+    }
+
+    //SHOW THE BLOCK WITH THE GIVEN HASH
+    pub async fn block_by_hash(&self, hash: &str) -> Result<BlockDetails, ClientError> {
+        self.send(self.http.get(self.v1(&format!("/blocks/hash/{}", hash))))
+            .await
+    }
+
+    //SHOW A PAGE OF BLOCKS
+    pub async fn blocks(&self, query: &BlockQuery) -> Result<Page<BlockDetails>, ClientError> {
+        self.send(self.http.get(self.v1("/blocks")).query(query))
+            .await
+    }
+
+    //CHECK IF THE NODE IS ALIVE
+    pub async fn health(&self) -> Result<Health, ClientError> {
+        self.send(self.http.get(self.url("/health"))).await
+    }
+
+    //CHECK IF THE NODE IS READY, A NODE THAT IS NOT READY IS NOT AN ERROR
+    pub async fn ready(&self) -> Result<Readiness, ClientError> {
+        let response = self.http.get(self.url("/ready")).send().await?;
+
+        match response.status() {
+            StatusCode::OK | StatusCode::SERVICE_UNAVAILABLE => Ok(response.json().await?),
+            status => Err(ClientError::Api {
+                status,
+                message: response.text().await?,
+            }),
+        }
+    }
+
+    //SHOW INFORMATION ABOUT THE NODE AND ITS CHAIN
+    pub async fn info(&self) -> Result<NodeInfo, ClientError> {
+        self.send(self.http.get(self.url("/info"))).await
+    }
+
+    //DOWNLOAD A SNAPSHOT OF THE CHAIN, THE WALLETS AND THE PENDING TRANSACTIONS. NEEDS THE ADMIN TOKEN
+    pub async fn export_chain(&self, format: SnapshotFormat) -> Result<Vec<u8>, ClientError> {
+        let query = ExportQuery {
+            format: Some(format),
+        };
+        let request = self.admin(self.http.get(self.v1("/admin/export")).query(&query));
+        let response = check_status(request.send().await?).await?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    //REPLACE THE CHAIN OF THE NODE WITH A SNAPSHOT, THE NODE VALIDATES IT FIRST. NEEDS THE ADMIN TOKEN
+    pub async fn import_chain(&self, snapshot: Vec<u8>) -> Result<ImportSummary, ClientError> {
+        self.send(self.admin(self.http.post(self.v1("/admin/import")).body(snapshot)))
+            .await
+    }
+
+    //RETURN THE URL OF AN ENDPOINT OF THE NODE
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    //RETURN THE URL OF A '/v1' ENDPOINT
+    fn v1(&self, path: &str) -> String {
+        self.url(&format!("{}{}", V1_PREFIX, path))
+    }
+
+    //ADD THE ADMIN TOKEN TO THE REQUEST, IF THE CLIENT HAS ONE
+    fn admin(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.admin_token {
+            Some(admin_token) => request.bearer_auth(admin_token),
+            None => request,
+        }
+    }
+
+    //SEND THE REQUEST AND READ THE JSON RESPONSE, THE ERRORS OF THE NODE ARE RETURNED AS 'ClientError::Api'
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        let request = match &self.idempotency_key {
+            Some(idempotency_key) => request.header(IDEMPOTENCY_KEY_HEADER, idempotency_key),
+            None => request,
+        };
+        let response = check_status(request.send().await?).await?;
+
+        Ok(response.json().await?)
+    }
+}
+
+//THE ADMIN TOKEN IS NEVER SHOWN WHEN THE CLIENT IS PRINTED OR LOGGED
+impl fmt::Debug for BlockchainClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockchainClient")
+            .field("base_url", &self.base_url)
+            .field("http", &self.http)
+            .field("admin_token", &self.admin_token.as_ref().map(|_| REDACTED))
+            .field("idempotency_key", &self.idempotency_key)
+            .finish()
+    }
+}
+
+//RETURN THE ERROR OF THE NODE AS 'ClientError::Api' IF THE REQUEST FAILED
+async fn check_status(response: Response) -> Result<Response, ClientError> {
+    let status = response.status();
+
+    if !status.is_success() {
+        return Err(ClientError::Api {
+            status,
+            message: response.text().await?,
+        });
+    }
+
+    Ok(response)
+}
+
+//THE QUERY THAT CONTAINS THE PASSWORD OF A WALLET
+fn password_query(password: &str) -> WalletPassword {
+    WalletPassword {
+        password: password.to_string(),
+    }
+}
+
+//THE OWNER OF A MULTISIGNATURE WALLET AND THE PASSWORD OF ITS OWN WALLET
+fn owner_credentials(owner: &str, password: &str) -> OwnerCredentials {
+    OwnerCredentials {
+        owner: owner.to_string(),
+        password: password.to_string(),
+    }
+}
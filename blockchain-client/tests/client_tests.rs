@@ -0,0 +1,395 @@
+use actix_web::{App, HttpServer};
+use blockchainApi::api::{config::ApiConfig, v1::BatchRequest};
+use blockchainApi::blockchain::{
+    batch::{BatchItemStatus, BatchMode},
+    escrow::{hash_of, HashLock},
+    mempool::EvictionReason,
+    multisig::{MultisigWalletInfo, ProposalInfo, ProposalStatus},
+    query::{BlockQuery, Direction, HistoryQuery, TransactionState},
+    snapshot::{ChainSnapshot, SnapshotFormat},
+    standing_order::{StandingOrderInfo, StandingOrderStatus},
+    transaction::{LockTime, Metadata, TransactionInfo},
+    wallet::WalletInfo,
+};
+use blockchain_client::{BlockchainClient, ClientError};
+use reqwest::StatusCode;
+
+//START A NODE ON A FREE PORT AND RETURN A CLIENT FOR IT
+//EVERY TEST FILE IS A SEPARATE PROCESS, SO THE NODE STARTS WITH A NEW BLOCKCHAIN
+fn start_node() -> BlockchainClient {
+    let server = HttpServer::new(|| {
+        App::new().configure(blockchainApi::configure(ApiConfig {
+            legacy_routes: false,
+            admin_token: Some("secret".to_string()),
+            ..ApiConfig::default()
+        }))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let address = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    BlockchainClient::new(&format!("http://{}", address))
+}
+
+#[actix_web::test]
+async fn test_client() {
+    let client = start_node();
+
+    //CHECK THE NODE
+    assert_eq!(client.health().await.unwrap().status, "ok");
+    assert!(client.ready().await.unwrap().ready);
+    let info = client.info().await.unwrap();
+    assert_eq!(info.tip_height, 0);
+
+    //CREATE WALLETS AND ADD COINS
+    for address in ["alice", "bob"] {
+        let wallet = client
+            .create_wallet(&WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(wallet.balance, 100);
+    }
+    let wallet = client.add_coins("alice", "pass", 50).await.unwrap();
+    assert_eq!(wallet.balance, 150);
+
+    //SEND COINS AND MINE THEM
+    let transaction = client
+        .transfer(&TransactionInfo {
+            from_address: "alice".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "bob".to_string(),
+            amount: 30,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        })
+        .await
+        .unwrap();
+    let status = client.transaction(&transaction.id).await.unwrap();
+    assert_eq!(status.status, "pending");
+
+    let block = client.mine("alice").await.unwrap();
+    assert_eq!(block.index, 1);
+    assert_eq!(block.transactions, vec![transaction.clone()]);
+    assert_eq!(client.balance("bob", "pass").await.unwrap().balance, 130);
+    assert_eq!(client.balance("alice", "pass").await.unwrap().balance, 120);
+
+    //LOOK UP THE BLOCKS
+    assert_eq!(client.latest_block().await.unwrap(), block);
+    assert_eq!(client.block(1).await.unwrap(), block);
+    assert_eq!(client.block_by_hash(&block.hash).await.unwrap(), block);
+    let page = client
+        .blocks(&BlockQuery {
+            limit: Some(1),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.items[0].index, 0);
+    let next_page = client
+        .blocks(&BlockQuery {
+            limit: Some(1),
+            cursor: page.next_cursor,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(next_page.items, vec![block]);
+
+    //READ THE HISTORY
+    let history = client
+        .history(
+            "bob",
+            "pass",
+            &HistoryQuery {
+                direction: Some(Direction::In),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(history.items.len(), 1);
+    assert_eq!(history.items[0].transaction, transaction);
+    assert_eq!(history.items[0].status, TransactionState::Confirmed);
+
+    //THE SENDER CAN CANCEL A PENDING TRANSACTION AND GETS THE COINS BACK
+    let pending = client
+        .transfer(&TransactionInfo {
+            from_address: "alice".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "bob".to_string(),
+            amount: 20,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(client.balance("alice", "pass").await.unwrap().balance, 100);
+    let cancelled = client
+        .cancel_transaction(&pending.id, "pass")
+        .await
+        .unwrap();
+    assert_eq!(cancelled.eviction_reason, Some(EvictionReason::Cancelled));
+    assert_eq!(client.balance("alice", "pass").await.unwrap().balance, 120);
+
+    //A BATCH RETURNS WHAT HAPPENED TO EACH TRANSACTION, THE VALID ONES ARE ADDED IN BEST-EFFORT MODE
+    let batch = client
+        .transfer_batch(&BatchRequest {
+            transactions: ["bob", "nobody"]
+                .map(|to_address| TransactionInfo {
+                    from_address: "alice".to_string(),
+                    from_password: "pass".to_string(),
+                    to_address: to_address.to_string(),
+                    amount: 10,
+                    fee: 0,
+                    expiry: None,
+                    nonce: None,
+                    memo: None,
+                    metadata: Metadata::new(),
+                    lock: None,
+                    hash_lock: None,
+                })
+                .to_vec(),
+            mode: BatchMode::BestEffort,
+        })
+        .await
+        .unwrap();
+    assert_eq!(batch.accepted, 1);
+    assert_eq!(batch.items[0].status, BatchItemStatus::Accepted);
+    assert_eq!(batch.items[1].status, BatchItemStatus::Rejected);
+    assert_eq!(client.balance("alice", "pass").await.unwrap().balance, 110);
+
+    //A TRANSFER RETRIED WITH THE SAME IDEMPOTENCY KEY IS ONLY EXECUTED ONCE
+    let retrying = client.clone().with_idempotency_key("payroll-1");
+    let transfer = TransactionInfo {
+        from_address: "alice".to_string(),
+        from_password: "pass".to_string(),
+        to_address: "bob".to_string(),
+        amount: 10,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
+    };
+    let first = retrying.transfer(&transfer).await.unwrap();
+    assert_eq!(retrying.transfer(&transfer).await.unwrap(), first);
+    assert_eq!(client.balance("alice", "pass").await.unwrap().balance, 100);
+    assert!(matches!(
+        retrying
+            .transfer(&TransactionInfo {
+                amount: 20,
+                ..transfer
+            })
+            .await,
+        Err(ClientError::Api {
+            status: StatusCode::CONFLICT,
+            ..
+        })
+    ));
+
+    //THE ERRORS OF THE NODE ARE RETURNED WITH THEIR STATUS AND MESSAGE
+    match client.balance("bob", "wrong").await {
+        Err(ClientError::Api { status, message }) => {
+            assert_eq!(status, StatusCode::FAILED_DEPENDENCY);
+            assert_eq!(message, "Wrong password!");
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(matches!(
+        client.block(10).await,
+        Err(ClientError::Api {
+            status: StatusCode::NOT_FOUND,
+            ..
+        })
+    ));
+
+    //THE CHAIN CAN BE EXPORTED AND IMPORTED BACK WITH THE ADMIN TOKEN
+    assert!(matches!(
+        client.export_chain(SnapshotFormat::Jsonl).await,
+        Err(ClientError::Api {
+            status: StatusCode::UNAUTHORIZED,
+            ..
+        })
+    ));
+    let admin = client.clone().with_admin_token("secret");
+    let latest = client.latest_block().await.unwrap();
+    for format in [SnapshotFormat::Jsonl, SnapshotFormat::Binary] {
+        let bytes = admin.export_chain(format).await.unwrap();
+        assert_eq!(ChainSnapshot::decode(&bytes).unwrap().header.blocks, 2);
+
+        let summary = admin.import_chain(bytes).await.unwrap();
+        assert_eq!(summary.height, 1);
+        assert_eq!(summary.wallets, 2);
+        assert_eq!(client.latest_block().await.unwrap(), latest);
+    }
+    assert!(!format!("{:?}", admin).contains("secret"));
+
+    //A TRANSFER FROM A MULTISIGNATURE WALLET IS SENT WHEN ENOUGH OWNERS APPROVED IT
+    let vault = client
+        .create_multisig_wallet(&MultisigWalletInfo {
+            address: "vault".to_string(),
+            password: "vault".to_string(),
+            owners: vec!["alice".to_string(), "bob".to_string()],
+            threshold: 2,
+        })
+        .await
+        .unwrap();
+    assert_eq!(vault.owners, ["alice", "bob"]);
+    client.add_coins("vault", "vault", 40).await.unwrap();
+    let proposal = client
+        .propose_transfer(
+            "vault",
+            &ProposalInfo {
+                owner: "alice".to_string(),
+                password: "pass".to_string(),
+                to_address: "bob".to_string(),
+                amount: 25,
+                fee: 0,
+                memo: None,
+                metadata: Metadata::new(),
+                expires_in: Some(60),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Pending);
+    let proposal = client
+        .approve_proposal(&proposal.id, "bob", "pass")
+        .await
+        .unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Executed);
+    assert_eq!(client.balance("vault", "vault").await.unwrap().balance, 15);
+    assert_eq!(
+        client.proposals("vault", "alice", "pass").await.unwrap(),
+        vec![proposal.clone()]
+    );
+    assert_eq!(
+        client.proposal(&proposal.id, "bob", "pass").await.unwrap(),
+        proposal
+    );
+    assert!(matches!(
+        client.approve_proposal(&proposal.id, "alice", "pass").await,
+        Err(ClientError::Api {
+            status: StatusCode::FAILED_DEPENDENCY,
+            ..
+        })
+    ));
+
+    //THE COINS OF A LOCKED TRANSFER ARE SHOWN APART FROM THE BALANCE THAT CAN BE SPENT
+    let payout = client
+        .transfer(&TransactionInfo {
+            from_address: "bob".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "alice".to_string(),
+            amount: 5,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: Some(LockTime::Timestamp(u64::MAX)),
+            hash_lock: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(payout.lock, Some(LockTime::Timestamp(u64::MAX)));
+    client.mine("bob").await.unwrap();
+    assert_eq!(client.balance("alice", "pass").await.unwrap().locked, 5);
+
+    //THE RECIPIENT OF AN ESCROW CLAIMS IT WITH THE PREIMAGE, THE SENDER CAN'T REFUND IT BEFORE THE TIMEOUT
+    let escrow = client
+        .transfer(&TransactionInfo {
+            from_address: "bob".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "alice".to_string(),
+            amount: 5,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: Some(HashLock {
+                hash: hash_of("secret"),
+                timeout: LockTime::Timestamp(u64::MAX),
+            }),
+        })
+        .await
+        .unwrap();
+    client.mine("bob").await.unwrap();
+    assert!(matches!(
+        client.refund_escrow(&escrow.id, "pass").await,
+        Err(ClientError::Api {
+            status: StatusCode::FAILED_DEPENDENCY,
+            ..
+        })
+    ));
+    let claim = client
+        .claim_escrow(&escrow.id, "pass", "secret")
+        .await
+        .unwrap();
+    assert_eq!((claim.to_address.as_str(), claim.amount), ("alice", 5));
+    assert_eq!(
+        client.transaction(&claim.id).await.unwrap().status,
+        "pending"
+    );
+
+    //THE PAYER LISTS AND REVOKES ITS STANDING ORDERS
+    let order = client
+        .create_standing_order(&StandingOrderInfo {
+            from_address: "bob".to_string(),
+            password: "pass".to_string(),
+            to_address: "alice".to_string(),
+            amount: 1,
+            fee: 0,
+            interval: 3600,
+            start_at: Some(u64::MAX / 2),
+            end_at: None,
+            memo: Some("subscription".to_string()),
+            metadata: Metadata::new(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(order.status, StandingOrderStatus::Active);
+    assert_eq!(
+        client.standing_orders("bob", "pass").await.unwrap()[0],
+        order
+    );
+    let revoked = client
+        .revoke_standing_order(&order.id, "pass")
+        .await
+        .unwrap();
+    assert_eq!(revoked.status, StandingOrderStatus::Revoked);
+    assert!(matches!(
+        client.revoke_standing_order(&order.id, "pass").await,
+        Err(ClientError::Api {
+            status: StatusCode::FAILED_DEPENDENCY,
+            ..
+        })
+    ));
+    assert!(matches!(
+        client.revoke_standing_order("unknown", "pass").await,
+        Err(ClientError::Api {
+            status: StatusCode::NOT_FOUND,
+            ..
+        })
+    ));
+}
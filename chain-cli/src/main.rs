@@ -0,0 +1,1076 @@
+use blockchainApi::api::{
+    admin::ImportSummary,
+    v1::{BatchRequest, BatchResponse},
+};
+use blockchainApi::blockchain::{
+    batch::BatchMode,
+    block::{Block, BlockDetails},
+    block_chain::{validate_blocks, BlockChain},
+    escrow::HashLock,
+    multisig::{MultisigWalletDetails, MultisigWalletInfo, Proposal, ProposalInfo},
+    query::{
+        BlockQuery, Direction, HistoryEntry, HistoryQuery, Page, SortOrder, TransactionState,
+        MAX_PAGE_SIZE,
+    },
+    snapshot::SnapshotFormat,
+    standing_order::{StandingOrder, StandingOrderInfo},
+    transaction::{Expiry, LockTime, TransactionDetails, TransactionInfo, TransactionStatus},
+    wallet::{WalletBalance, WalletInfo},
+    write_ahead_log::read_entries,
+};
+use blockchain_client::{BlockchainClient, ClientError};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+/// A command-line client for a blockchain node
+#[derive(Parser)]
+#[command(name = "chain-cli", version, about)]
+struct Cli {
+    /// The URL of the node
+    #[arg(
+        long,
+        env = "CHAIN_NODE",
+        default_value = "http://127.0.0.1:8080",
+        global = true
+    )]
+    node: String,
+
+    /// Print the results as JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Send this key with the request, a command run again with the same key is not executed twice
+    #[arg(long, global = true)]
+    idempotency_key: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage wallets
+    #[command(subcommand)]
+    Wallet(WalletCommand),
+    /// Manage multisignature wallets and the transfers proposed from them
+    #[command(subcommand)]
+    Multisig(MultisigCommand),
+    /// Claim or refund the coins of an escrow
+    #[command(subcommand)]
+    Escrow(EscrowCommand),
+    /// Pay a wallet at every interval from another, until the end date or until revoked
+    #[command(subcommand)]
+    StandingOrder(StandingOrderCommand),
+    /// Send coins from a wallet to another
+    Send {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: u32,
+        /// Paid to the miner, the transactions with the lowest fees are evicted first when the mempool is full
+        #[arg(long, default_value_t = 0)]
+        fee: u32,
+        /// The unix timestamp at which the transaction expires if it is not mined
+        #[arg(long, conflicts_with = "expires_at_height")]
+        expires_at: Option<u64>,
+        /// The height of the last block the transaction can be mined in
+        #[arg(long)]
+        expires_at_height: Option<usize>,
+        /// The nonce of a pending transaction to replace, the fee must be higher than its fee
+        #[arg(long)]
+        nonce: Option<u64>,
+        /// A note kept with the transaction, for example the number of an invoice
+        #[arg(long)]
+        memo: Option<String>,
+        /// A pair kept with the transaction, it can be given several times
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_metadata)]
+        metadata: Vec<(String, String)>,
+        /// The unix timestamp before which the recipient can't spend the coins
+        #[arg(long, conflicts_with = "locked_until_height")]
+        locked_until: Option<u64>,
+        /// The height of the first block whose transactions can spend the coins
+        #[arg(long)]
+        locked_until_height: Option<usize>,
+        /// Send the coins to an escrow, the recipient claims them with the preimage of this hex SHA-256 hash
+        #[arg(long, requires = "timeout")]
+        hash_lock: Option<String>,
+        /// The unix timestamp from which the sender can refund the escrow
+        #[arg(long, group = "timeout", requires = "hash_lock")]
+        refund_after: Option<u64>,
+        /// The height of the first block the refund of the escrow can go into
+        #[arg(long, group = "timeout", requires = "hash_lock")]
+        refund_after_height: Option<usize>,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Send the transactions of a JSON file with a list of transactions together, fail if any is not added
+    Batch {
+        file: PathBuf,
+        /// Add every valid transaction instead of none when one is not valid
+        #[arg(long)]
+        best_effort: bool,
+    },
+    /// Cancel a pending transaction, the coins go back to the sender
+    Cancel {
+        id: String,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Mine the pending transactions and reward the given address
+    Mine { reward_address: String },
+    /// Show a transaction and its status
+    Transaction { id: String },
+    /// Inspect blocks
+    #[command(subcommand)]
+    Block(BlockCommand),
+    /// Download the whole chain and check its hashes, proof of work and links
+    Validate,
+    /// Show information about the node
+    Info,
+    /// Check if the node is ready
+    Ready,
+    /// Download the chain, the wallets and the pending transactions. The file contains the passwords of the wallets
+    Export {
+        #[arg(long, value_enum, default_value_t = FormatArg::Jsonl)]
+        format: FormatArg,
+        /// The file to write to, the standard output if missing
+        #[arg(long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        admin_token: AdminToken,
+    },
+    /// Replace the chain of the node with an exported file, the node validates every block first
+    Import {
+        file: PathBuf,
+        #[command(flatten)]
+        admin_token: AdminToken,
+    },
+    /// Rebuild the state from a write-ahead log, without a node, and show the last block
+    Replay {
+        file: PathBuf,
+        /// Fail if the hash of the last block is not this one
+        #[arg(long)]
+        expect_tip: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum WalletCommand {
+    /// Create a new wallet
+    Create {
+        address: String,
+        #[arg(long, default_value_t = 0)]
+        balance: i32,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Add coins to a wallet
+    AddCoins {
+        address: String,
+        coins: u32,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Show the balance of a wallet
+    Balance {
+        address: String,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Show the transactions of a wallet
+    History {
+        address: String,
+        #[command(flatten)]
+        password: Password,
+        #[arg(long, value_enum)]
+        direction: Option<DirectionArg>,
+        #[arg(long, value_enum)]
+        status: Option<StatusArg>,
+        #[command(flatten)]
+        notes: NotesArgs,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum MultisigCommand {
+    /// Create a multisignature wallet, its transfers must be approved by THRESHOLD of its owners
+    Create {
+        address: String,
+        /// An existing wallet that owns the new one, it must be given once for every owner
+        #[arg(long = "owner", required = true)]
+        owners: Vec<String>,
+        #[arg(long)]
+        threshold: usize,
+        /// The password of the new wallet, it can see the balance and the history but not send coins
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Propose a transfer from a multisignature wallet, it counts as the approval of the owner
+    Propose {
+        wallet: String,
+        /// The owner that proposes the transfer, the password is the one of its own wallet
+        #[arg(long)]
+        owner: String,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: u32,
+        #[arg(long, default_value_t = 0)]
+        fee: u32,
+        /// How many seconds the other owners have to approve the transfer
+        #[arg(long)]
+        expires_in: Option<u64>,
+        #[arg(long)]
+        memo: Option<String>,
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_metadata)]
+        metadata: Vec<(String, String)>,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Approve a proposal, the approval that reaches the threshold sends the coins
+    Approve {
+        id: String,
+        #[arg(long)]
+        owner: String,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Show a proposal and who approved it
+    Show {
+        id: String,
+        #[arg(long)]
+        owner: String,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// List the proposals of a multisignature wallet
+    Proposals {
+        wallet: String,
+        #[arg(long)]
+        owner: String,
+        #[command(flatten)]
+        password: Password,
+    },
+}
+
+#[derive(Subcommand)]
+enum EscrowCommand {
+    /// Claim the coins of an escrow as its recipient with the preimage of its hash
+    Claim {
+        id: String,
+        #[arg(long)]
+        preimage: String,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Give the coins of an escrow back to its sender once its timeout has passed
+    Refund {
+        id: String,
+        #[command(flatten)]
+        password: Password,
+    },
+}
+
+#[derive(Subcommand)]
+enum StandingOrderCommand {
+    /// Create a standing order, the node adds its payments to pending transactions when they are due
+    Create {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: u32,
+        #[arg(long, default_value_t = 0)]
+        fee: u32,
+        /// How many seconds there are between two payments
+        #[arg(long)]
+        interval: u64,
+        /// The unix timestamp of the first payment, now if missing
+        #[arg(long)]
+        start_at: Option<u64>,
+        /// The unix timestamp after which no payment is made
+        #[arg(long)]
+        end_at: Option<u64>,
+        #[arg(long)]
+        memo: Option<String>,
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_metadata)]
+        metadata: Vec<(String, String)>,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// List the standing orders paid from a wallet
+    List {
+        address: String,
+        #[command(flatten)]
+        password: Password,
+    },
+    /// Revoke a standing order, the payments that are already pending are not cancelled
+    Revoke {
+        id: String,
+        #[command(flatten)]
+        password: Password,
+    },
+}
+
+#[derive(Subcommand)]
+enum BlockCommand {
+    /// Show the last block
+    Latest,
+    /// Show the block at a height
+    Get { height: usize },
+    /// Show the block with a hash
+    Hash { hash: String },
+    /// List the blocks
+    List {
+        #[command(flatten)]
+        notes: NotesArgs,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+}
+
+/// The password of a wallet, it can be given in CHAIN_PASSWORD so it doesn't show up in the list of processes
+#[derive(Args)]
+struct Password {
+    #[arg(long, env = "CHAIN_PASSWORD", hide_env_values = true)]
+    password: String,
+}
+
+/// The token of the admin endpoints, it can be given in CHAIN_ADMIN_TOKEN
+#[derive(Args)]
+struct AdminToken {
+    #[arg(long, env = "CHAIN_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: String,
+}
+
+/// The filters on the memo and the metadata of the transactions
+#[derive(Args)]
+struct NotesArgs {
+    /// Only the transactions with a memo that contains this text
+    #[arg(long)]
+    memo: Option<String>,
+    /// Only the transactions with this metadata key
+    #[arg(long)]
+    metadata_key: Option<String>,
+    /// Only the transactions with this metadata value
+    #[arg(long)]
+    metadata_value: Option<String>,
+}
+
+/// The page that is shown when listing
+#[derive(Args)]
+struct PageArgs {
+    #[arg(long)]
+    limit: Option<usize>,
+    #[arg(long)]
+    cursor: Option<String>,
+    /// Show the newest first
+    #[arg(long)]
+    desc: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DirectionArg {
+    In,
+    Out,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StatusArg {
+    Confirmed,
+    Pending,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Jsonl,
+    Binary,
+}
+
+impl PageArgs {
+    fn order(&self) -> Option<SortOrder> {
+        self.desc.then_some(SortOrder::Desc)
+    }
+}
+
+//THE RESULTS THAT CAN BE PRINTED AS TEXT
+trait Human {
+    fn human(&self) -> String;
+}
+
+impl Human for WalletBalance {
+    fn human(&self) -> String {
+        let mut text = format!("Address: {}\nBalance: {}", self.address, self.balance);
+        if self.locked > 0 {
+            text += &format!("\nLocked: {}", self.locked);
+        }
+        text
+    }
+}
+
+impl Human for TransactionDetails {
+    fn human(&self) -> String {
+        let mut text = format!(
+            "Id: {}\nFrom: {}\nTo: {}\nAmount: {}\nFee: {}\nNonce: {}",
+            self.id, self.from_address, self.to_address, self.amount, self.fee, self.nonce
+        );
+        match self.expiry {
+            Some(Expiry::Timestamp(timestamp)) => text += &format!("\nExpires at: {}", timestamp),
+            Some(Expiry::Height(height)) => text += &format!("\nExpires after block: {}", height),
+            None => {}
+        }
+        match self.lock {
+            Some(LockTime::Timestamp(timestamp)) => {
+                text += &format!("\nLocked until: {}", timestamp)
+            }
+            Some(LockTime::Height(height)) => text += &format!("\nLocked until block: {}", height),
+            None => {}
+        }
+        if let Some(hash_lock) = &self.hash_lock {
+            text += &format!("\nHash lock: {}", hash_lock.hash);
+            match hash_lock.timeout {
+                LockTime::Timestamp(timestamp) => {
+                    text += &format!("\nRefundable from: {}", timestamp)
+                }
+                LockTime::Height(height) => text += &format!("\nRefundable from block: {}", height),
+            }
+        }
+        if let Some(settlement) = &self.settlement {
+            match &settlement.preimage {
+                Some(preimage) => {
+                    text += &format!(
+                        "\nClaims escrow: {}\nPreimage: {}",
+                        settlement.escrow, preimage
+                    )
+                }
+                None => text += &format!("\nRefunds escrow: {}", settlement.escrow),
+            }
+        }
+        if let Some(memo) = &self.memo {
+            text += &format!("\nMemo: {}", memo);
+        }
+        for (key, value) in &self.metadata {
+            text += &format!("\nMetadata: {}={}", key, value);
+        }
+        text
+    }
+}
+
+impl Human for TransactionStatus {
+    fn human(&self) -> String {
+        let place = match (self.block_height, self.eviction_reason) {
+            (Some(height), _) => {
+                format!("in block {} ({} confirmations)", height, self.confirmations)
+            }
+            (None, Some(reason)) => format!("without being mined ({:?})", reason),
+            (None, None) => "waiting to be mined".to_string(),
+        };
+        format!(
+            "{}\nStatus: {} {}",
+            self.transaction.human(),
+            self.status,
+            place
+        )
+    }
+}
+
+impl Human for BlockDetails {
+    fn human(&self) -> String {
+        let mut text = format!(
+            "Index: {}\nTimestamp: {}\nHash: {}\nPrevious hash: {}\nNonce: {}\nTransactions: {}",
+            self.index,
+            self.timestamp,
+            self.hash,
+            self.previous_hash,
+            self.nonce,
+            self.transactions.len()
+        );
+        for transaction in &self.transactions {
+            text.push_str(&format!(
+                "\n   {} -> {}: {}",
+                transaction.from_address, transaction.to_address, transaction.amount
+            ));
+        }
+        text
+    }
+}
+
+impl Human for HistoryEntry {
+    fn human(&self) -> String {
+        let direction = match self.direction {
+            Direction::In => "IN ",
+            Direction::Out => "OUT",
+        };
+        let status = match self.status {
+            TransactionState::Confirmed => "confirmed",
+            TransactionState::Pending => "pending",
+        };
+        let mut text = format!(
+            "{} {} {} -> {}: {} ({})",
+            self.timestamp,
+            direction,
+            self.transaction.from_address,
+            self.transaction.to_address,
+            self.transaction.amount,
+            status
+        );
+        if let Some(memo) = &self.transaction.memo {
+            text += &format!(" {:?}", memo);
+        }
+        text
+    }
+}
+
+impl Human for MultisigWalletDetails {
+    fn human(&self) -> String {
+        format!(
+            "Address: {}\nBalance: {}\nOwners: {}\nThreshold: {}",
+            self.address,
+            self.balance,
+            self.owners.join(", "),
+            self.threshold
+        )
+    }
+}
+
+impl Human for Proposal {
+    fn human(&self) -> String {
+        let mut text = format!(
+            "Id: {}\nWallet: {}\nTo: {}\nAmount: {}\nFee: {}\nStatus: {:?}\nExpires at: {}\nApprovals: {} of {}",
+            self.id,
+            self.wallet,
+            self.to_address,
+            self.amount,
+            self.fee,
+            self.status,
+            self.expires_at,
+            self.approvals.len(),
+            self.threshold
+        );
+        for approval in &self.approvals {
+            text += &format!("\n   {} at {}", approval.owner, approval.timestamp);
+        }
+        if let Some(transaction_id) = &self.transaction_id {
+            text += &format!("\nTransaction: {}", transaction_id);
+        }
+        text
+    }
+}
+
+impl Human for Vec<Proposal> {
+    fn human(&self) -> String {
+        let lines: Vec<String> = self.iter().map(Human::human).collect();
+        lines.join("\n\n")
+    }
+}
+
+impl Human for StandingOrder {
+    fn human(&self) -> String {
+        let mut text = format!(
+            "Id: {}\nFrom: {}\nTo: {}\nAmount: {}\nFee: {}\nInterval: {}\nStatus: {:?}\nNext payment at: {}",
+            self.id,
+            self.from_address,
+            self.to_address,
+            self.amount,
+            self.fee,
+            self.interval,
+            self.status,
+            self.next_payment_at
+        );
+        if let Some(end_at) = self.end_at {
+            text += &format!("\nEnds at: {}", end_at);
+        }
+        text += &format!("\nPayments: {}\nMissed: {}", self.payments, self.missed);
+        if let Some(transaction_id) = &self.transaction_id {
+            text += &format!("\nLast transaction: {}", transaction_id);
+        }
+        if let Some(memo) = &self.memo {
+            text += &format!("\nMemo: {}", memo);
+        }
+        text
+    }
+}
+
+impl Human for Vec<StandingOrder> {
+    fn human(&self) -> String {
+        let lines: Vec<String> = self.iter().map(Human::human).collect();
+        lines.join("\n\n")
+    }
+}
+
+impl Human for ImportSummary {
+    fn human(&self) -> String {
+        format!(
+            "Imported chain up to height {}\nWallets: {}\nPending transactions: {}",
+            self.height, self.wallets, self.pending_transactions
+        )
+    }
+}
+
+impl Human for BatchResponse {
+    fn human(&self) -> String {
+        let mut text = format!(
+            "Added {} of {} transactions",
+            self.accepted,
+            self.items.len()
+        );
+        for (position, item) in self.items.iter().enumerate() {
+            let result = match (&item.transaction, &item.error) {
+                (Some(transaction), _) => transaction.id.clone(),
+                (None, Some(error)) => error.clone(),
+                (None, None) => "not added, another transaction was rejected".to_string(),
+            };
+            text += &format!("\n{}: {}", position, result);
+        }
+        text
+    }
+}
+
+impl<T: Human> Human for Page<T> {
+    fn human(&self) -> String {
+        let mut lines: Vec<String> = self.items.iter().map(Human::human).collect();
+        if let Some(cursor) = &self.next_cursor {
+            lines.push(format!("Next page: --cursor {}", cursor));
+        }
+        lines.join("\n\n")
+    }
+}
+
+//PRINT A RESULT AS TEXT OR AS JSON
+fn print<T: Serialize + Human>(value: &T, json: bool) {
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(value).unwrap()),
+        false => println!("{}", value.human()),
+    }
+}
+
+//READ A 'KEY=VALUE' PAIR OF METADATA
+fn parse_metadata(pair: &str) -> Result<(String, String), String> {
+    match pair.split_once('=') {
+        Some((key, value)) => Ok((key.to_string(), value.to_string())),
+        None => Err("expected KEY=VALUE".to_string()),
+    }
+}
+
+//DOWNLOAD ALL THE BLOCKS OF THE CHAIN, OLDEST FIRST
+async fn all_blocks(client: &BlockchainClient) -> Result<Vec<BlockDetails>, ClientError> {
+    let mut blocks = vec![];
+    let mut cursor = None;
+
+    loop {
+        let page = client
+            .blocks(&BlockQuery {
+                limit: Some(MAX_PAGE_SIZE),
+                cursor,
+                ..Default::default()
+            })
+            .await?;
+        blocks.extend(page.items);
+
+        match page.next_cursor {
+            Some(next_cursor) => cursor = Some(next_cursor),
+            None => return Ok(blocks),
+        }
+    }
+}
+
+//RUN THE COMMAND
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let client = match &cli.idempotency_key {
+        Some(idempotency_key) => {
+            BlockchainClient::new(&cli.node).with_idempotency_key(idempotency_key)
+        }
+        None => BlockchainClient::new(&cli.node),
+    };
+    let json = cli.json;
+
+    match cli.command {
+        Command::Wallet(WalletCommand::Create {
+            address,
+            balance,
+            password,
+        }) => {
+            let wallet = WalletInfo {
+                address,
+                balance,
+                password: password.password,
+            };
+            print(&client.create_wallet(&wallet).await?, json);
+        }
+        Command::Wallet(WalletCommand::AddCoins {
+            address,
+            coins,
+            password,
+        }) => print(
+            &client
+                .add_coins(&address, &password.password, coins)
+                .await?,
+            json,
+        ),
+        Command::Wallet(WalletCommand::Balance { address, password }) => {
+            print(&client.balance(&address, &password.password).await?, json)
+        }
+        Command::Wallet(WalletCommand::History {
+            address,
+            password,
+            direction,
+            status,
+            notes,
+            page,
+        }) => {
+            let query = HistoryQuery {
+                limit: page.limit,
+                order: page.order(),
+                cursor: page.cursor,
+                direction: direction.map(|direction| match direction {
+                    DirectionArg::In => Direction::In,
+                    DirectionArg::Out => Direction::Out,
+                }),
+                status: status.map(|status| match status {
+                    StatusArg::Confirmed => TransactionState::Confirmed,
+                    StatusArg::Pending => TransactionState::Pending,
+                }),
+                memo: notes.memo,
+                metadata_key: notes.metadata_key,
+                metadata_value: notes.metadata_value,
+                ..Default::default()
+            };
+            print(
+                &client.history(&address, &password.password, &query).await?,
+                json,
+            );
+        }
+        Command::Multisig(MultisigCommand::Create {
+            address,
+            owners,
+            threshold,
+            password,
+        }) => {
+            let wallet = MultisigWalletInfo {
+                address,
+                password: password.password,
+                owners,
+                threshold,
+            };
+            print(&client.create_multisig_wallet(&wallet).await?, json);
+        }
+        Command::Multisig(MultisigCommand::Propose {
+            wallet,
+            owner,
+            to,
+            amount,
+            fee,
+            expires_in,
+            memo,
+            metadata,
+            password,
+        }) => {
+            let proposal = ProposalInfo {
+                owner,
+                password: password.password,
+                to_address: to,
+                amount,
+                fee,
+                memo,
+                metadata: metadata.into_iter().collect(),
+                expires_in,
+            };
+            print(&client.propose_transfer(&wallet, &proposal).await?, json);
+        }
+        Command::Multisig(MultisigCommand::Approve {
+            id,
+            owner,
+            password,
+        }) => print(
+            &client
+                .approve_proposal(&id, &owner, &password.password)
+                .await?,
+            json,
+        ),
+        Command::Multisig(MultisigCommand::Show {
+            id,
+            owner,
+            password,
+        }) => print(
+            &client.proposal(&id, &owner, &password.password).await?,
+            json,
+        ),
+        Command::Multisig(MultisigCommand::Proposals {
+            wallet,
+            owner,
+            password,
+        }) => print(
+            &client
+                .proposals(&wallet, &owner, &password.password)
+                .await?,
+            json,
+        ),
+        Command::Send {
+            from,
+            to,
+            amount,
+            fee,
+            expires_at,
+            expires_at_height,
+            nonce,
+            memo,
+            metadata,
+            locked_until,
+            locked_until_height,
+            hash_lock,
+            refund_after,
+            refund_after_height,
+            password,
+        } => {
+            let expiry = match (expires_at, expires_at_height) {
+                (Some(timestamp), _) => Some(Expiry::Timestamp(timestamp)),
+                (None, Some(height)) => Some(Expiry::Height(height)),
+                (None, None) => None,
+            };
+            let lock = match (locked_until, locked_until_height) {
+                (Some(timestamp), _) => Some(LockTime::Timestamp(timestamp)),
+                (None, Some(height)) => Some(LockTime::Height(height)),
+                (None, None) => None,
+            };
+            let timeout = match (refund_after, refund_after_height) {
+                (Some(timestamp), _) => Some(LockTime::Timestamp(timestamp)),
+                (None, Some(height)) => Some(LockTime::Height(height)),
+                (None, None) => None,
+            };
+            let hash_lock = hash_lock
+                .zip(timeout)
+                .map(|(hash, timeout)| HashLock { hash, timeout });
+            let transaction = TransactionInfo {
+                from_address: from,
+                from_password: password.password,
+                to_address: to,
+                amount,
+                fee,
+                expiry,
+                nonce,
+                memo,
+                metadata: metadata.into_iter().collect(),
+                lock,
+                hash_lock,
+            };
+            print(&client.transfer(&transaction).await?, json);
+        }
+        Command::Batch { file, best_effort } => {
+            let batch = BatchRequest {
+                transactions: serde_json::from_slice(&fs::read(file)?)?,
+                mode: match best_effort {
+                    true => BatchMode::BestEffort,
+                    false => BatchMode::Atomic,
+                },
+            };
+            let response = client.transfer_batch(&batch).await?;
+            print(&response, json);
+
+            if response.accepted < response.items.len() {
+                return Err(format!(
+                    "{} of {} transactions were not added",
+                    response.items.len() - response.accepted,
+                    response.items.len()
+                )
+                .into());
+            }
+        }
+        Command::Escrow(EscrowCommand::Claim {
+            id,
+            preimage,
+            password,
+        }) => print(
+            &client
+                .claim_escrow(&id, &password.password, &preimage)
+                .await?,
+            json,
+        ),
+        Command::Escrow(EscrowCommand::Refund { id, password }) => {
+            print(&client.refund_escrow(&id, &password.password).await?, json)
+        }
+        Command::StandingOrder(StandingOrderCommand::Create {
+            from,
+            to,
+            amount,
+            fee,
+            interval,
+            start_at,
+            end_at,
+            memo,
+            metadata,
+            password,
+        }) => {
+            let order = StandingOrderInfo {
+                from_address: from,
+                password: password.password,
+                to_address: to,
+                amount,
+                fee,
+                interval,
+                start_at,
+                end_at,
+                memo,
+                metadata: metadata.into_iter().collect(),
+            };
+            print(&client.create_standing_order(&order).await?, json);
+        }
+        Command::StandingOrder(StandingOrderCommand::List { address, password }) => print(
+            &client.standing_orders(&address, &password.password).await?,
+            json,
+        ),
+        Command::StandingOrder(StandingOrderCommand::Revoke { id, password }) => print(
+            &client
+                .revoke_standing_order(&id, &password.password)
+                .await?,
+            json,
+        ),
+        Command::Cancel { id, password } => print(
+            &client.cancel_transaction(&id, &password.password).await?,
+            json,
+        ),
+        Command::Mine { reward_address } => print(&client.mine(&reward_address).await?, json),
+        Command::Transaction { id } => print(&client.transaction(&id).await?, json),
+        Command::Block(BlockCommand::Latest) => print(&client.latest_block().await?, json),
+        Command::Block(BlockCommand::Get { height }) => print(&client.block(height).await?, json),
+        Command::Block(BlockCommand::Hash { hash }) => {
+            print(&client.block_by_hash(&hash).await?, json)
+        }
+        Command::Block(BlockCommand::List { notes, page }) => {
+            let query = BlockQuery {
+                limit: page.limit,
+                order: page.order(),
+                cursor: page.cursor,
+                memo: notes.memo,
+                metadata_key: notes.metadata_key,
+                metadata_value: notes.metadata_value,
+                ..Default::default()
+            };
+            print(&client.blocks(&query).await?, json);
+        }
+        Command::Validate => {
+            let info = client.info().await?;
+            let blocks: Vec<Block> = all_blocks(&client).await?.iter().map(Block::from).collect();
+            let valid = validate_blocks(&blocks, info.difficulty).is_ok();
+
+            match json {
+                true => println!(
+                    "{}",
+                    serde_json::json!({ "valid": valid, "blocks": blocks.len() })
+                ),
+                false => println!(
+                    "Chain of {} blocks is {}",
+                    blocks.len(),
+                    if valid { "valid" } else { "INVALID" }
+                ),
+            }
+            if !valid {
+                return Err("the chain is invalid".into());
+            }
+        }
+        Command::Info => {
+            let info = client.info().await?;
+            match json {
+                true => println!("{}", serde_json::to_string_pretty(&info).unwrap()),
+                false => println!(
+                    "Chain: {} (id {})\nGenesis: {}\nTip: {} at height {}\nDifficulty: {}\nVersion: {}\nFeatures: {}",
+                    info.chain_name,
+                    info.chain_id,
+                    info.genesis_hash,
+                    info.tip_hash,
+                    info.tip_height,
+                    info.difficulty,
+                    info.version,
+                    info.features.join(", ")
+                ),
+            }
+        }
+        Command::Ready => {
+            let readiness = client.ready().await?;
+            match json {
+                true => println!("{}", serde_json::to_string_pretty(&readiness).unwrap()),
+                false => println!(
+                    "Ready: {}\nChain valid: {}\nStorage loaded: {}\nShutting down: {}",
+                    readiness.ready,
+                    readiness.chain_valid,
+                    readiness.storage_loaded,
+                    readiness.shutting_down
+                ),
+            }
+            if !readiness.ready {
+                return Err("the node is not ready".into());
+            }
+        }
+        Command::Export {
+            format,
+            output,
+            admin_token,
+        } => {
+            let format = match format {
+                FormatArg::Jsonl => SnapshotFormat::Jsonl,
+                FormatArg::Binary => SnapshotFormat::Binary,
+            };
+            let client = client.with_admin_token(&admin_token.admin_token);
+            let snapshot = client.export_chain(format).await?;
+
+            match output {
+                Some(output) => fs::write(output, snapshot)?,
+                None => io::stdout().write_all(&snapshot)?,
+            }
+        }
+        Command::Import { file, admin_token } => {
+            let client = client.with_admin_token(&admin_token.admin_token);
+            print(&client.import_chain(fs::read(file)?).await?, json);
+        }
+        Command::Replay { file, expect_tip } => {
+            let entries = read_entries(&fs::read(file)?).map_err(|error| error.to_string())?;
+            let blockchain = BlockChain::default();
+            blockchain
+                .replay(&entries)
+                .map_err(|error| error.to_string())?;
+            let tip = blockchain
+                .get_latest_block()
+                .map_err(|error| error.to_string())?;
+
+            match json {
+                true => println!(
+                    "{}",
+                    serde_json::json!({ "entries": entries.len(), "tip_height": tip.index, "tip_hash": tip.hash })
+                ),
+                false => println!(
+                    "Replayed {} entries\nTip: {} at height {}",
+                    entries.len(),
+                    tip.hash,
+                    tip.index
+                ),
+            }
+            if let Some(expected) = expect_tip.filter(|expected| *expected != tip.hash) {
+                return Err(format!("the tip is {}, expected {}", tip.hash, expected).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use actix_web::{App, HttpServer};
+use blockchainApi::api::config::ApiConfig;
+use blockchainApi::blockchain::{
+    block_chain::BlockChain,
+    escrow::hash_of,
+    transaction::{Metadata, TransactionInfo},
+    wallet::WalletInfo,
+};
+use std::process::{Command, Output};
+
+//START A NODE ON A FREE PORT AND RETURN ITS URL
+fn start_node() -> String {
+    let server = HttpServer::new(|| {
+        App::new().configure(blockchainApi::configure(ApiConfig {
+            legacy_routes: false,
+            admin_token: Some("secret".to_string()),
+            ..ApiConfig::default()
+        }))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let address = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    format!("http://{}", address)
+}
+
+//RUN THE CLI AGAINST THE NODE, ON ANOTHER THREAD SO THE NODE KEEPS RUNNING ON THIS ONE
+async fn chain_cli(node: &str, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_chain-cli"));
+    command
+        .args(["--node", node])
+        .args(args)
+        .env("CHAIN_PASSWORD", "pass");
+
+    actix_web::rt::task::spawn_blocking(move || command.output().unwrap())
+        .await
+        .unwrap()
+}
+
+//RUN THE CLI AND READ ITS JSON OUTPUT
+async fn chain_cli_json(node: &str, args: &[&str]) -> serde_json::Value {
+    let output = chain_cli(node, &[args, &["--json"]].concat()).await;
+    assert!(output.status.success(), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[actix_web::test]
+async fn test_cli() {
+    let node = start_node();
+
+    //CREATE WALLETS, SEND COINS AND MINE THEM
+    for address in ["alice", "bob"] {
+        let wallet =
+            chain_cli_json(&node, &["wallet", "create", address, "--balance", "100"]).await;
+        assert_eq!(wallet["balance"], 100);
+    }
+    let send = [
+        "send",
+        "--from",
+        "alice",
+        "--to",
+        "bob",
+        "--amount",
+        "25",
+        "--fee",
+        "2",
+        "--expires-at-height",
+        "5",
+        "--idempotency-key",
+        "send-1",
+        "--memo",
+        "Invoice 7",
+        "--metadata",
+        "invoice=7",
+    ];
+    let transaction = chain_cli_json(&node, &send).await;
+    assert_eq!(chain_cli_json(&node, &send).await, transaction);
+    assert_eq!(transaction["fee"], 2);
+    assert_eq!(transaction["expiry"]["height"], 5);
+    assert_eq!(transaction["memo"], "Invoice 7");
+    assert_eq!(transaction["metadata"]["invoice"], "7");
+    let block = chain_cli_json(&node, &["mine", "alice"]).await;
+    assert_eq!(block["transactions"][0], transaction);
+
+    //SHOW THE RESULTS
+    let balance = chain_cli_json(&node, &["wallet", "balance", "bob"]).await;
+    assert_eq!(balance["balance"], 125);
+    let history = chain_cli_json(&node, &["wallet", "history", "bob", "--direction", "in"]).await;
+    assert_eq!(history["items"][0]["transaction"], transaction);
+    let search = ["wallet", "history", "bob", "--metadata-key", "invoice"];
+    let found = chain_cli_json(&node, &[&search[..], &["--metadata-value", "7"]].concat()).await;
+    assert_eq!(found["items"][0]["transaction"], transaction);
+    let found = chain_cli_json(&node, &[&search[..], &["--metadata-value", "8"]].concat()).await;
+    assert_eq!(found["items"], serde_json::json!([]));
+    let latest = chain_cli_json(&node, &["block", "latest"]).await;
+    assert_eq!(latest, block);
+
+    //THE TEXT OUTPUT IS READABLE
+    let output = chain_cli(&node, &["wallet", "balance", "bob"]).await;
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Address: bob\nBalance: 125\n"
+    );
+    let output = chain_cli(&node, &["validate"]).await;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Chain of 2 blocks is valid\n"
+    );
+
+    //THE ERRORS OF THE NODE ARE SHOWN AND THE EXIT CODE IS NOT ZERO
+    let output = chain_cli(&node, &["wallet", "balance", "bob", "--password", "wrong"]).await;
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "error: 424 Failed Dependency: Wrong password!\n"
+    );
+
+    //THE CHAIN IS EXPORTED TO A FILE AND IMPORTED BACK WITH THE ADMIN TOKEN
+    let file = std::env::temp_dir().join(format!("chain-cli-{}.bin", std::process::id()));
+    let file = file.to_str().unwrap();
+    let export = ["export", "--format", "binary", "--output", file];
+    let output = chain_cli(&node, &export).await;
+    assert!(!output.status.success());
+    let output = chain_cli(&node, &[&export[..], &["--admin-token", "secret"]].concat()).await;
+    assert!(output.status.success(), "{:?}", output);
+    let summary = chain_cli_json(&node, &["import", file, "--admin-token", "secret"]).await;
+    assert_eq!(summary["height"], 1);
+    assert_eq!(chain_cli_json(&node, &["block", "latest"]).await, block);
+    std::fs::remove_file(file).unwrap();
+
+    //THE COINS OF A LOCKED TRANSFER ARE MINED BUT STAY LOCKED UNTIL THE BLOCK
+    let locked = chain_cli_json(
+        &node,
+        &[
+            "send",
+            "--from",
+            "bob",
+            "--to",
+            "alice",
+            "--amount",
+            "10",
+            "--locked-until-height",
+            "100",
+        ],
+    )
+    .await;
+    assert_eq!(locked["lock"]["height"], 100);
+    chain_cli_json(&node, &["mine", "bob"]).await;
+    let balance = chain_cli_json(&node, &["wallet", "balance", "alice"]).await;
+    assert_eq!(balance["locked"], 10);
+    let output = chain_cli(&node, &["wallet", "balance", "alice"]).await;
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("\nLocked: 10\n"));
+
+    //THE RECIPIENT OF AN ESCROW CLAIMS IT WITH THE PREIMAGE OF ITS HASH
+    let hash = hash_of("secret");
+    let escrow = chain_cli_json(
+        &node,
+        &[
+            "send",
+            "--from",
+            "alice",
+            "--to",
+            "bob",
+            "--amount",
+            "5",
+            "--hash-lock",
+            &hash,
+            "--refund-after-height",
+            "100",
+        ],
+    )
+    .await;
+    assert_eq!(escrow["hash_lock"]["hash"], hash.as_str());
+    chain_cli_json(&node, &["mine", "bob"]).await;
+    let id = escrow["id"].as_str().unwrap();
+    let output = chain_cli(&node, &["escrow", "refund", id]).await;
+    assert!(!output.status.success());
+    let claim = chain_cli_json(&node, &["escrow", "claim", id, "--preimage", "secret"]).await;
+    assert_eq!(claim["to_address"], "bob");
+    assert_eq!(claim["settlement"]["escrow"], id);
+
+    //A STANDING ORDER IS LISTED AND REVOKED BY ITS PAYER
+    let order = chain_cli_json(
+        &node,
+        &[
+            "standing-order",
+            "create",
+            "--from",
+            "alice",
+            "--to",
+            "bob",
+            "--amount",
+            "1",
+            "--interval",
+            "86400",
+            "--start-at",
+            "9999999999",
+        ],
+    )
+    .await;
+    assert_eq!(order["status"], "active");
+    let id = order["id"].as_str().unwrap();
+    let orders = chain_cli_json(&node, &["standing-order", "list", "alice"]).await;
+    assert_eq!(orders[0]["id"], id);
+    let revoked = chain_cli_json(&node, &["standing-order", "revoke", id]).await;
+    assert_eq!(revoked["status"], "revoked");
+    let output = chain_cli(&node, &["standing-order", "revoke", id]).await;
+    assert!(!output.status.success());
+}
+
+#[actix_web::test]
+async fn test_replay() {
+    //WRITE A LOG WITHOUT A NODE
+    let file = std::env::temp_dir().join(format!("chain-cli-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&file);
+    let blockchain = BlockChain::default();
+    blockchain.open_log(&file).unwrap();
+    blockchain
+        .create_wallet(WalletInfo {
+            address: "alice".to_string(),
+            balance: 100,
+            password: "pass".to_string(),
+        })
+        .unwrap();
+    blockchain
+        .create_transaction(TransactionInfo {
+            from_address: "alice".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "alice".to_string(),
+            amount: 10,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        })
+        .unwrap();
+    let tip = blockchain.mine_block("alice").unwrap().hash;
+
+    //THE REPLAY ENDS WITH THE SAME TIP
+    let file = file.to_str().unwrap();
+    let replay = chain_cli_json("", &["replay", file, "--expect-tip", &tip]).await;
+    assert_eq!(replay["entries"], 4);
+    assert_eq!(replay["tip_height"], 1);
+    assert_eq!(replay["tip_hash"], tip);
+
+    let output = chain_cli("", &["replay", file, "--expect-tip", "other"]).await;
+    assert!(!output.status.success());
+    std::fs::remove_file(file).unwrap();
+}
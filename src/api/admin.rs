@@ -0,0 +1,123 @@
+use crate::blockchain::{
+    block_chain::BLOCKCHAIN,
+    block_chain_errors::BlockChainError,
+    snapshot::{ChainSnapshot, SnapshotFormat},
+};
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    get,
+    http::header::{self, ContentDisposition, DispositionParam, DispositionType},
+    middleware::Next,
+    post,
+    web::{self, Bytes, Data, Json, Query},
+    Error, HttpResponse,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+//THE LARGEST SNAPSHOT THAT CAN BE IMPORTED, IN BYTES
+pub const MAX_SNAPSHOT_SIZE: usize = 256 * 1024 * 1024;
+
+//THE TOKEN THAT THE ADMIN ENDPOINTS REQUIRE IN THE 'Authorization: Bearer ...' HEADER
+pub struct AdminToken(pub String);
+
+//THE FORMAT OF THE EXPORTED CHAIN
+#[derive(Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    pub format: Option<SnapshotFormat>,
+}
+
+//THE CHAIN AFTER AN IMPORT
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct ImportSummary {
+    pub height: usize,
+    pub wallets: usize,
+    pub pending_transactions: usize,
+}
+
+//REJECT THE REQUESTS THAT DON'T HAVE THE ADMIN TOKEN
+pub async fn require_admin_token(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let token = request
+        .app_data::<Data<AdminToken>>()
+        .map(|token| token.0.as_str());
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match (token, given) {
+        (Some(token), Some(given)) if token == given => {
+            Ok(next.call(request).await?.map_into_left_body())
+        }
+        _ => Ok(request
+            .error_response(BlockChainError::Unauthorized)
+            .map_into_right_body()),
+    }
+}
+
+//DOWNLOAD THE CHAIN, THE WALLETS AND THE PENDING TRANSACTIONS AS A SNAPSHOT
+#[utoipa::path(
+    context_path = "/v1/admin",
+    tag = "admin",
+    params(ExportQuery),
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The snapshot, as JSON lines or in the binary format", content(
+            (Vec<u8> = "application/x-ndjson"),
+            (Vec<u8> = "application/octet-stream"),
+        )),
+        (status = 401, description = "The admin token is wrong or missing", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/export")]
+pub async fn export_chain(query: Query<ExportQuery>) -> HttpResponse {
+    let format = query.format.unwrap_or_default();
+    let snapshot = BLOCKCHAIN.export_snapshot();
+    let file_name = format!(
+        "chain-{}-{}.{}",
+        snapshot.header.chain_id,
+        snapshot.header.blocks.saturating_sub(1),
+        format.extension()
+    );
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .body(snapshot.encode(format))
+}
+
+//REPLACE THE CHAIN, THE WALLETS AND THE PENDING TRANSACTIONS WITH A SNAPSHOT, AFTER VALIDATING ALL ITS BLOCKS
+#[utoipa::path(
+    context_path = "/v1/admin",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body(content = Vec<u8>, description = "A snapshot in any of the export formats", content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "The snapshot was imported", body = ImportSummary),
+        (status = 401, description = "The admin token is wrong or missing", body = String, content_type = "text/html"),
+        (status = 424, description = "The snapshot is not valid, is from another chain or its version is not supported", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/import")]
+pub async fn import_chain(body: Bytes) -> Result<Json<ImportSummary>, BlockChainError> {
+    let snapshot = ChainSnapshot::decode(&body)?;
+    let summary = ImportSummary {
+        height: snapshot.blocks.len().saturating_sub(1),
+        wallets: snapshot.wallets.len(),
+        pending_transactions: snapshot.pending_transactions.len(),
+    };
+    //THE IMPORT WAITS FOR THE BLOCK THAT IS BEING MINED, SO IT DOESN'T RUN ON THE WORKER THREAD
+    web::block(move || BLOCKCHAIN.import_snapshot(snapshot)).await??;
+
+    Ok(Json(summary))
+}
@@ -0,0 +1,36 @@
+use crate::api::idempotency;
+use std::{env, time::Duration};
+
+//THE OPTIONS THAT CHANGE WHICH ENDPOINTS ARE SERVED
+#[derive(Clone)]
+pub struct ApiConfig {
+    //SERVE THE ROUTES FROM BEFORE '/v1'
+    pub legacy_routes: bool,
+    //THE TOKEN THAT THE ADMIN ENDPOINTS REQUIRE, WITHOUT IT THEY ARE NOT SERVED
+    pub admin_token: Option<String>,
+    //HOW LONG THE RESPONSES TO THE REQUESTS WITH AN 'Idempotency-Key' ARE KEPT
+    pub idempotency_window: Duration,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig {
+            legacy_routes: true,
+            admin_token: None,
+            idempotency_window: idempotency::window(),
+        }
+    }
+}
+
+impl ApiConfig {
+    //READ THE OPTIONS FROM 'LEGACY_ROUTES' ('false' TURNS THEM OFF), 'ADMIN_TOKEN' AND 'IDEMPOTENCY_WINDOW'
+    pub fn from_env() -> Self {
+        ApiConfig {
+            legacy_routes: !matches!(env::var("LEGACY_ROUTES").as_deref(), Ok("false") | Ok("0")),
+            admin_token: env::var("ADMIN_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+            idempotency_window: idempotency::window(),
+        }
+    }
+}
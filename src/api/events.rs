@@ -0,0 +1,150 @@
+use crate::blockchain::{
+    block_chain::BLOCKCHAIN,
+    block_chain_errors::BlockChainError,
+    events::{Event, EventFilter, EventType},
+};
+use actix_web::{
+    get,
+    http::header::{CacheControl, CacheDirective},
+    web::{Bytes, Payload, Query},
+    Error, HttpRequest, HttpResponse,
+};
+use actix_ws::Message;
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::IntoParams;
+
+//THE EVENTS THAT A CLIENT WANTS TO RECEIVE, FOR EXAMPLE '/events?address=a&password=pass&types=block_mined,balance_changed'
+//THE PASSWORD IS ONLY NEEDED TO RECEIVE THE BALANCE CHANGES OF THE ADDRESS
+#[derive(Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SubscriptionQuery {
+    pub address: Option<String>,
+    pub password: Option<String>,
+    pub types: Option<String>,
+}
+
+impl SubscriptionQuery {
+    //CHECK THE QUERY AND TURN IT INTO A FILTER FOR THE EVENTS
+    pub fn filter(&self) -> Result<EventFilter, BlockChainError> {
+        let types = match &self.types {
+            Some(types) => Some(
+                types
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<Vec<EventType>, BlockChainError>>()?,
+            ),
+            None => None,
+        };
+
+        //CHECK THE PASSWORD OF THE ADDRESS IF IS GIVEN
+        let authenticated = match (&self.address, &self.password) {
+            (Some(address), Some(password)) => {
+                BLOCKCHAIN.get_balance_of_wallet(address, password)?;
+                true
+            }
+            (None, Some(_)) => return Err(BlockChainError::EmptyAddress),
+            _ => false,
+        };
+
+        Ok(EventFilter {
+            address: self.address.clone(),
+            authenticated,
+            types,
+        })
+    }
+}
+
+//STREAM THE EVENTS THAT MATCH THE QUERY AS SERVER-SENT EVENTS
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "events",
+    params(SubscriptionQuery),
+    responses(
+        (status = 200, description = "A stream of server-sent events, the data of each one is an event", body = Event, content_type = "text/event-stream"),
+        (status = 404, description = "The wallet doesn't exist or a password is given without an address", body = String, content_type = "text/html"),
+        (status = 424, description = "The query is not valid or the password is wrong", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/events")]
+pub async fn subscribe_events(
+    query: Query<SubscriptionQuery>,
+) -> Result<HttpResponse, BlockChainError> {
+    let filter = query.filter()?;
+    let receiver = BLOCKCHAIN.events.subscribe();
+
+    let events = stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) if filter.matches(&event) => {
+                    let message = format!(
+                        "event: {}\ndata: {}\n\n",
+                        event.event_type().name(),
+                        serde_json::to_string(&event).unwrap()
+                    );
+                    return Some((Ok::<_, Error>(Bytes::from(message)), (receiver, filter)));
+                }
+                //EVENTS THAT DON'T MATCH AND EVENTS MISSED BY A SLOW CLIENT ARE SKIPPED
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(events))
+}
+
+//SEND THE EVENTS THAT MATCH THE QUERY OVER A WEBSOCKET, EACH EVENT IS A JSON TEXT MESSAGE
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "events",
+    params(SubscriptionQuery),
+    responses(
+        (status = 101, description = "The connection is upgraded to a websocket, each text message is an event", body = Event),
+        (status = 404, description = "The wallet doesn't exist or a password is given without an address", body = String, content_type = "text/html"),
+        (status = 424, description = "The query is not valid or the password is wrong", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/events/ws")]
+pub async fn subscribe_events_ws(
+    request: HttpRequest,
+    body: Payload,
+    query: Query<SubscriptionQuery>,
+) -> Result<HttpResponse, Error> {
+    let filter = query.filter()?;
+    let (response, mut session, mut messages) = actix_ws::handle(&request, body)?;
+    let mut receiver = BLOCKCHAIN.events.subscribe();
+
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                event = receiver.recv() => match event {
+                    Ok(event) if filter.matches(&event) => {
+                        if session.text(serde_json::to_string(&event).unwrap()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                message = messages.next() => match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                },
+            }
+        }
+
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
@@ -0,0 +1,253 @@
+use crate::blockchain::block_chain_errors::BlockChainError;
+use crate::seconds_from_env;
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::PayloadError,
+    http::{
+        header::{self, HeaderName, HeaderValue},
+        Method, StatusCode,
+    },
+    middleware::Next,
+    web::{Bytes, BytesMut, Data},
+    Error, HttpMessage, HttpResponse,
+};
+use crypto_hash::{hex_digest, Algorithm};
+use futures_util::{stream, StreamExt};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+//THE HEADER WITH THE KEY THAT A CLIENT CHOOSES FOR A REQUEST, A RETRY WITH THE SAME KEY GETS THE FIRST RESPONSE
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+//THE HEADER ADDED TO THE RESPONSES THAT WERE STORED AND RETURNED AGAIN
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+//HOW LONG THE RESPONSES ARE KEPT BY DEFAULT, IN SECONDS
+const DEFAULT_IDEMPOTENCY_WINDOW: u64 = 24 * 60 * 60;
+
+//THE MAXIMUM LENGTH OF AN IDEMPOTENCY KEY
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+//THE LARGEST BODY OF A REQUEST WITH AN IDEMPOTENCY KEY, THE SAME AS THE LIMIT OF THE JSON BODIES
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+//READ HOW LONG THE RESPONSES ARE KEPT FROM 'IDEMPOTENCY_WINDOW', IN SECONDS
+pub fn window() -> Duration {
+    seconds_from_env("IDEMPOTENCY_WINDOW", DEFAULT_IDEMPOTENCY_WINDOW)
+}
+
+//THE PARTS OF A RESPONSE THAT ARE RETURNED AGAIN
+#[derive(Clone)]
+struct StoredResponse {
+    status: StatusCode,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+//A KEY THAT WAS USED, THE RESPONSE IS MISSING WHILE THE FIRST REQUEST IS STILL HANDLED
+struct UsedKey {
+    fingerprint: String,
+    used_at: Instant,
+    response: Option<StoredResponse>,
+}
+
+//WHAT TO DO WITH A REQUEST THAT HAS AN IDEMPOTENCY KEY
+enum Lookup {
+    New,
+    InProgress,
+    Reused,
+    Replay(StoredResponse),
+}
+
+//THE KEYS USED IN THE WINDOW AND THEIR RESPONSES, SHARED BY ALL THE WORKERS
+pub struct IdempotencyKeys {
+    window: Duration,
+    keys: Mutex<HashMap<String, UsedKey>>,
+}
+
+impl IdempotencyKeys {
+    //THE RESPONSES ARE KEPT FOR THE GIVEN TIME AFTER THE FIRST REQUEST
+    pub fn new(window: Duration) -> Self {
+        IdempotencyKeys {
+            window,
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    //RESERVE A NEW KEY, OR FIND WHAT HAPPENED TO THE REQUEST THAT USED IT FIRST
+    fn lookup(&self, key: &str, fingerprint: &str) -> Lookup {
+        let mut keys = self.keys.lock().unwrap();
+        keys.retain(|_, used| used.used_at.elapsed() < self.window);
+
+        match keys.get(key) {
+            Some(used) if used.fingerprint != fingerprint => Lookup::Reused,
+            Some(UsedKey { response: None, .. }) => Lookup::InProgress,
+            Some(UsedKey {
+                response: Some(response),
+                ..
+            }) => Lookup::Replay(response.clone()),
+            None => {
+                keys.insert(
+                    key.to_string(),
+                    UsedKey {
+                        fingerprint: fingerprint.to_string(),
+                        used_at: Instant::now(),
+                        response: None,
+                    },
+                );
+                Lookup::New
+            }
+        }
+    }
+
+    //KEEP THE RESPONSE OF THE FIRST REQUEST, OR FREE THE KEY IF THERE IS NOTHING TO KEEP
+    fn finish(&self, key: &str, response: Option<StoredResponse>) {
+        let mut keys = self.keys.lock().unwrap();
+
+        match response {
+            Some(response) => {
+                if let Some(used) = keys.get_mut(key) {
+                    used.response = Some(response);
+                }
+            }
+            None => {
+                keys.remove(key);
+            }
+        }
+    }
+}
+
+//RETURN THE STORED RESPONSE WHEN A 'POST' IS RETRIED WITH THE SAME 'Idempotency-Key'
+//THE SAME KEY WITH ANOTHER METHOD, PATH OR BODY IS A CONFLICT, SO IS A RETRY WHILE THE FIRST REQUEST IS STILL HANDLED
+pub async fn honour_idempotency_key(
+    mut request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let keys = request.app_data::<Data<IdempotencyKeys>>().cloned();
+    let key = request.headers().get(IDEMPOTENCY_KEY_HEADER).cloned();
+
+    let (Some(keys), Some(key)) = (keys, key) else {
+        return Ok(next.call(request).await?.map_into_boxed_body());
+    };
+    if request.method() != Method::POST {
+        return Ok(next.call(request).await?.map_into_boxed_body());
+    }
+    let Some(key) = key.to_str().ok().filter(|key| is_valid_key(key)) else {
+        return Ok(request.error_response(BlockChainError::InvalidIdempotencyKey));
+    };
+    let key = key.to_string();
+
+    //THE BODY IS READ TO COMPARE THE REQUESTS AND PUT BACK FOR THE HANDLER
+    let body = read_body(request.take_payload()).await?;
+    let fingerprint = fingerprint(&request, &body);
+    request.set_payload(into_payload(body));
+
+    match keys.lookup(&key, &fingerprint) {
+        Lookup::New => {}
+        Lookup::InProgress => {
+            return Ok(request.error_response(BlockChainError::IdempotencyKeyInProgress))
+        }
+        Lookup::Reused => return Ok(request.error_response(BlockChainError::IdempotencyKeyReused)),
+        Lookup::Replay(stored) => {
+            info!(key, status = stored.status.as_u16(), "request replayed");
+            let mut response = into_response(stored);
+            response.headers_mut().insert(
+                HeaderName::from_static(IDEMPOTENT_REPLAYED_HEADER),
+                HeaderValue::from_static("true"),
+            );
+            return Ok(request.into_response(response));
+        }
+    }
+    let mut reservation = Reservation {
+        keys,
+        key,
+        response: None,
+    };
+
+    let (request, response) = next.call(request).await?.into_parts();
+    let status = response.status();
+    let content_type = response.headers().get(header::CONTENT_TYPE).cloned();
+    let (response, body) = response.into_parts();
+    let Ok(body) = body::to_bytes(body).await else {
+        return Ok(ServiceResponse::new(
+            request,
+            HttpResponse::InternalServerError().finish(),
+        ));
+    };
+
+    //THE SERVER ERRORS ARE NOT KEPT, NOTHING WAS CHANGED SO THE REQUEST CAN BE RETRIED
+    if !status.is_server_error() {
+        reservation.response = Some(StoredResponse {
+            status,
+            content_type,
+            body: body.clone(),
+        });
+    }
+
+    Ok(ServiceResponse::new(
+        request,
+        response.set_body(BoxBody::new(body)),
+    ))
+}
+
+//A KEY RESERVED FOR A REQUEST, WHEN THE REQUEST ENDS THE RESPONSE IS KEPT OR, IF THERE IS NONE, THE KEY IS FREED
+//THE KEY IS ALSO FREED IF THE REQUEST FAILS OR IS DROPPED BECAUSE THE CLIENT WENT AWAY
+struct Reservation {
+    keys: Data<IdempotencyKeys>,
+    key: String,
+    response: Option<StoredResponse>,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.keys.finish(&self.key, self.response.take());
+    }
+}
+
+//ONLY KEYS MADE OF VISIBLE ASCII CHARACTERS ARE ACCEPTED, LIKE THE UUIDS THAT MOST CLIENTS SEND
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH
+        && key.chars().all(|c| c.is_ascii_graphic())
+}
+
+//A HASH OF THE METHOD, THE PATH AND THE BODY, THE PASSWORDS IN THEM ARE NOT KEPT
+fn fingerprint(request: &ServiceRequest, body: &Bytes) -> String {
+    let mut data = format!("{} {}\n", request.method(), request.uri()).into_bytes();
+    data.extend_from_slice(body);
+    hex_digest(Algorithm::SHA256, &data)
+}
+
+//READ THE WHOLE BODY OF THE REQUEST
+async fn read_body(mut payload: Payload) -> Result<Bytes, Error> {
+    let mut body = BytesMut::new();
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > MAX_BODY_SIZE {
+            return Err(PayloadError::Overflow.into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
+//TURN A BODY THAT WAS ALREADY READ BACK INTO A PAYLOAD
+fn into_payload(body: Bytes) -> Payload {
+    Payload::from(stream::once(async move { Ok::<_, PayloadError>(body) }).boxed_local())
+}
+
+//BUILD THE RESPONSE AGAIN FROM WHAT WAS STORED
+fn into_response(stored: StoredResponse) -> HttpResponse {
+    let mut response = HttpResponse::build(stored.status);
+    if let Some(content_type) = stored.content_type {
+        response.insert_header((header::CONTENT_TYPE, content_type));
+    }
+    response.body(stored.body)
+}
@@ -0,0 +1,28 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    Error,
+};
+
+//THE HEADER THAT TELLS THE CLIENTS THAT A ROUTE IS DEPRECATED
+pub const DEPRECATION_HEADER: &str = "deprecation";
+
+//MARK THE RESPONSES OF THE LEGACY ROUTES AS DEPRECATED
+pub async fn deprecate(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let mut response = next.call(request).await?;
+
+    //REQUESTS THAT DON'T MATCH ANY LEGACY ROUTE ARE NOT MARKED
+    if response.request().match_pattern().is_some() {
+        response.headers_mut().insert(
+            HeaderName::from_static(DEPRECATION_HEADER),
+            HeaderValue::from_static("true"),
+        );
+    }
+
+    Ok(response)
+}
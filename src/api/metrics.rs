@@ -0,0 +1,47 @@
+use crate::blockchain::block_chain::BLOCKCHAIN;
+use crate::metrics::METRICS;
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    get,
+    middleware::Next,
+    Error, HttpResponse,
+};
+use std::time::Instant;
+
+//COUNT EVERY REQUEST AND MEASURE HOW LONG IT TOOK, GROUPED BY THE ROUTE THAT HANDLED IT
+pub async fn record_request_metrics(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let method = request.method().to_string();
+
+    let response = next.call(request).await?;
+    let endpoint = response
+        .request()
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    METRICS.record_request(
+        &method,
+        &endpoint,
+        response.status().as_u16(),
+        started.elapsed(),
+    );
+
+    Ok(response)
+}
+
+//SHOW THE METRICS OF THE NODE IN THE PROMETHEUS TEXT FORMAT
+#[utoipa::path(
+    tag = "node",
+    responses(
+        (status = 200, description = "The metrics in the Prometheus text format", body = String, content_type = "text/plain; version=0.0.4"),
+    )
+)]
+#[get("/metrics")]
+pub async fn show_metrics() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(METRICS.render(&BLOCKCHAIN))
+}
@@ -1 +1,11 @@
-pub mod requests;
\ No newline at end of file
+pub mod admin;
+pub mod config;
+pub mod events;
+pub mod idempotency;
+pub mod legacy;
+pub mod metrics;
+pub mod node;
+pub mod openapi;
+pub mod request_id;
+pub mod requests;
+pub mod v1;
@@ -0,0 +1,148 @@
+use crate::api::config::ApiConfig;
+use crate::blockchain::{block_chain::BLOCKCHAIN, block_chain_errors::BlockChainError};
+use actix_web::{
+    get,
+    web::{Data, Json},
+    HttpResponse,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+//THE VERSION OF THE NODE SOFTWARE
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+//THE OPTIONAL PARTS OF THE API THAT ARE BUILT INTO THIS NODE, THE ONES THAT DEPEND ON THE CONFIG ARE ADDED BY 'features'
+pub const FEATURES: &[&str] = &[
+    "versioned_api",
+    "block_lookup",
+    "pagination",
+    "address_index",
+    "event_stream",
+    "metrics",
+    "request_tracing",
+    "openapi",
+    "idempotency",
+    "snapshots",
+    "wal",
+    "mempool_limits",
+    "rbf",
+    "batch",
+    "memo",
+    "multisig",
+    "locks",
+    "escrow",
+    "standing_orders",
+];
+
+//THE OPTIONAL PARTS OF THE API THAT THIS NODE SERVES WITH THE GIVEN CONFIG
+pub fn features(config: &ApiConfig) -> Vec<String> {
+    let mut features: Vec<String> = FEATURES.iter().map(|feature| feature.to_string()).collect();
+    if config.legacy_routes {
+        features.push("legacy_routes".to_string());
+    }
+    if config.admin_token.is_some() {
+        features.push("admin".to_string());
+    }
+    features
+}
+
+//THE RESULT OF THE LIVENESS CHECK
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Health {
+    pub status: String,
+}
+
+//THE RESULT OF THE READINESS CHECK, THE NODE IS READY WHEN ALL THE CHECKS PASS
+//'storage_loaded' IS FALSE UNTIL THE WRITE-AHEAD LOG WAS REPLAYED
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    pub chain_valid: bool,
+    #[serde(default = "loaded")]
+    pub storage_loaded: bool,
+    pub shutting_down: bool,
+}
+
+//THE NODES FROM BEFORE 'storage_loaded' ONLY ANSWERED ONCE THEIR STORAGE WAS LOADED
+fn loaded() -> bool {
+    true
+}
+
+//GENERAL INFORMATION ABOUT THE NODE AND ITS CHAIN
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct NodeInfo {
+    pub chain_name: String,
+    pub chain_id: u32,
+    pub genesis_hash: String,
+    pub tip_height: usize,
+    pub tip_hash: String,
+    pub difficulty: usize,
+    pub version: String,
+    pub features: Vec<String>,
+}
+
+//THE NODE IS ALIVE WHILE IT CAN ANSWER REQUESTS
+#[utoipa::path(
+    tag = "node",
+    responses(
+        (status = 200, description = "The node is alive", body = Health),
+    )
+)]
+#[get("/health")]
+pub async fn health() -> Json<Health> {
+    Json(Health {
+        status: "ok".to_string(),
+    })
+}
+
+//THE NODE IS READY TO RECEIVE TRAFFIC WHEN ITS STATE IS LOADED, ITS CHAIN IS VALID AND IT IS NOT SHUTTING DOWN, OTHERWISE '503 SERVICE UNAVAILABLE' IS RETURNED
+//THE CHAIN IS NOT CHECKED AGAIN, THE RESULT OF THE LAST CHECK IS USED
+#[utoipa::path(
+    tag = "node",
+    responses(
+        (status = 200, description = "The node is ready", body = Readiness),
+        (status = 503, description = "The node is not ready", body = Readiness),
+    )
+)]
+#[get("/ready")]
+pub async fn ready() -> HttpResponse {
+    let chain_valid = BLOCKCHAIN.is_chain_valid();
+    let storage_loaded = BLOCKCHAIN.is_storage_loaded();
+    let shutting_down = BLOCKCHAIN.is_shutting_down();
+    let readiness = Readiness {
+        ready: chain_valid && storage_loaded && !shutting_down,
+        chain_valid,
+        storage_loaded,
+        shutting_down,
+    };
+
+    match readiness.ready {
+        true => HttpResponse::Ok().json(readiness),
+        false => HttpResponse::ServiceUnavailable().json(readiness),
+    }
+}
+
+//SHOW THE NAME AND ID OF THE CHAIN, ITS FIRST AND LAST BLOCKS, THE DIFFICULTY, THE SOFTWARE VERSION AND THE FEATURES THAT ARE SERVED
+#[utoipa::path(
+    tag = "node",
+    responses(
+        (status = 200, description = "Information about the node and its chain", body = NodeInfo),
+    )
+)]
+#[get("/info")]
+pub async fn info(config: Data<ApiConfig>) -> Result<Json<NodeInfo>, BlockChainError> {
+    let chain = BLOCKCHAIN.chain.read().unwrap();
+    let genesis = chain.first().ok_or(BlockChainError::ChainIsEmpty)?;
+    let tip = chain.last().ok_or(BlockChainError::ChainIsEmpty)?;
+
+    Ok(Json(NodeInfo {
+        chain_name: BLOCKCHAIN.name.clone(),
+        chain_id: BLOCKCHAIN.chain_id,
+        genesis_hash: genesis.hash.clone(),
+        tip_height: tip.index,
+        tip_hash: tip.hash.clone(),
+        difficulty: BLOCKCHAIN.difficulty,
+        version: VERSION.to_string(),
+        features: features(&config),
+    }))
+}
@@ -0,0 +1,161 @@
+use crate::api::{admin, events, metrics, node, requests, v1};
+use utoipa::{
+    openapi::{
+        path::{Operation, ParameterBuilder, ParameterIn},
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        Deprecated, Object, Required, ResponseBuilder, Type,
+    },
+    Modify, OpenApi,
+};
+use utoipa_swagger_ui::SwaggerUi;
+
+//THE PATH OF THE OPENAPI DOCUMENT
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+//THE OPENAPI 3 DOCUMENT OF ALL THE ENDPOINTS. EVERY HANDLER REGISTERED IN 'routes' MUST BE LISTED IN 'paths'
+//THE HANDLERS THAT ARE BOTH IN '/v1' AND IN THE LEGACY ROUTES ARE DOCUMENTED UNDER '/v1'
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Blockchain API"),
+    paths(
+        v1::submit_transaction,
+        v1::cancel_transaction,
+        v1::submit_batch,
+        v1::claim_escrow,
+        v1::refund_escrow,
+        v1::mine_block,
+        v1::create_wallet,
+        v1::add_coins,
+        v1::get_wallet_balance,
+        v1::get_wallet_history,
+        v1::create_multisig_wallet,
+        v1::propose_transfer,
+        v1::get_proposals,
+        v1::get_proposal,
+        v1::approve_proposal,
+        v1::create_standing_order,
+        v1::get_standing_orders,
+        v1::revoke_standing_order,
+        requests::create_transaction,
+        requests::mine_pending_transactions,
+        requests::show_blockchain,
+        requests::get_wallet_balance,
+        requests::create_wallet,
+        requests::get_wallet_transactions,
+        requests::add_coins,
+        requests::get_latest_block,
+        requests::get_block_by_hash,
+        requests::get_block,
+        requests::get_blocks,
+        requests::get_transaction,
+        requests::get_wallet_history,
+        events::subscribe_events_ws,
+        events::subscribe_events,
+        metrics::show_metrics,
+        node::health,
+        node::ready,
+        node::info,
+        admin::export_chain,
+        admin::import_chain,
+    ),
+    modifiers(&DeprecateLegacyRoutes, &AdminTokenScheme, &IdempotencyKeyHeader),
+    tags(
+        (name = "transactions", description = "Create, mine and look up transactions"),
+        (name = "wallets", description = "Create wallets and see their balances and histories"),
+        (name = "multisig", description = "Create multisignature wallets, propose transfers from them and approve the proposals"),
+        (name = "escrow", description = "Claim the coins of the escrows with the preimages of their hashes or refund them after their timeouts"),
+        (name = "standing-orders", description = "Create, list and revoke the standing orders that pay a wallet at every interval"),
+        (name = "blocks", description = "Look up and list the blocks of the chain"),
+        (name = "events", description = "Subscribe to the events of the chain"),
+        (name = "node", description = "Health, readiness, information and metrics of the node"),
+        (name = "admin", description = "Export and import the chain, they are only served when 'ADMIN_TOKEN' is set"),
+        (name = "legacy", description = "The routes from before '/v1', they are deprecated and can be disabled with 'LEGACY_ROUTES=false'"),
+    )
+)]
+pub struct ApiDoc;
+
+//MARK THE OPERATIONS WITH THE 'legacy' TAG AS DEPRECATED
+struct DeprecateLegacyRoutes;
+
+impl Modify for DeprecateLegacyRoutes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for item in openapi.paths.paths.values_mut() {
+            let operations = [
+                &mut item.get,
+                &mut item.post,
+                &mut item.put,
+                &mut item.patch,
+                &mut item.delete,
+            ];
+
+            for operation in operations.into_iter().flatten() {
+                if has_tag(operation, "legacy") {
+                    operation.deprecated = Some(Deprecated::True);
+                }
+            }
+        }
+    }
+}
+
+//ADD THE 'Authorization: Bearer ...' HEADER THAT THE ADMIN ENDPOINTS REQUIRE
+struct AdminTokenScheme;
+
+impl Modify for AdminTokenScheme {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "admin_token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+    }
+}
+
+//ADD THE 'Idempotency-Key' HEADER AND ITS CONFLICT RESPONSE TO THE 'POST' OPERATIONS, EXCEPT THE ADMIN ONES
+struct IdempotencyKeyHeader;
+
+impl Modify for IdempotencyKeyHeader {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for item in openapi.paths.paths.values_mut() {
+            let Some(operation) = item.post.as_mut() else {
+                continue;
+            };
+            if has_tag(operation, "admin") {
+                continue;
+            }
+
+            operation.parameters.get_or_insert_with(Vec::new).push(
+                ParameterBuilder::new()
+                    .name("Idempotency-Key")
+                    .parameter_in(ParameterIn::Header)
+                    .required(Required::False)
+                    .description(Some(
+                        "A key chosen by the client, a retry with the same key returns the first response instead of running the request again",
+                    ))
+                    .schema(Some(Object::with_type(Type::String)))
+                    .build(),
+            );
+            operation.responses.responses.insert(
+                "409".to_string(),
+                ResponseBuilder::new()
+                    .description("The idempotency key was used for another request, or the first request is still in progress")
+                    .build()
+                    .into(),
+            );
+        }
+    }
+}
+
+//CHECK IF THE OPERATION HAS THE GIVEN TAG
+fn has_tag(operation: &Operation, name: &str) -> bool {
+    operation
+        .tags
+        .as_ref()
+        .is_some_and(|tags| tags.iter().any(|tag| tag == name))
+}
+
+//SERVE THE OPENAPI DOCUMENT AT '/openapi.json' AND THE SWAGGER UI THAT SHOWS IT AT '/swagger-ui/'
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/swagger-ui/{_:.*}").url(OPENAPI_PATH, ApiDoc::openapi())
+}
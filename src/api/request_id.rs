@@ -0,0 +1,64 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    Error,
+};
+use std::time::Instant;
+use tracing::{info, info_span, Instrument};
+use uuid::Uuid;
+
+//THE HEADER THAT CONTAINS THE ID OF A REQUEST, IT IS TAKEN FROM THE REQUEST OR GENERATED AND RETURNED IN THE RESPONSE
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+//THE MAXIMUM LENGTH OF A REQUEST ID SENT BY A CLIENT
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+//RUN THE REQUEST INSIDE A SPAN WITH ITS ID, SO EVERY LOG WRITTEN WHILE HANDLING IT CONTAINS THE ID
+//ONLY THE ROUTE PATTERN IS LOGGED, NOT THE PATH, BECAUSE SOME PATHS CONTAIN PASSWORDS
+pub async fn trace_request(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let endpoint = request
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        endpoint = %endpoint,
+    );
+    let started = Instant::now();
+
+    let mut response = next.call(request).instrument(span.clone()).await?;
+    span.in_scope(|| {
+        info!(
+            status = response.status().as_u16(),
+            duration_ms = started.elapsed().as_millis() as u64,
+            "request handled"
+        )
+    });
+
+    response.headers_mut().insert(
+        HeaderName::from_static(REQUEST_ID_HEADER),
+        HeaderValue::from_str(&request_id).unwrap(),
+    );
+    Ok(response)
+}
+
+//ONLY SHORT IDS MADE OF LETTERS, DIGITS AND DASHES ARE ACCEPTED FROM CLIENTS
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
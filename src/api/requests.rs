@@ -1,89 +1,169 @@
 use crate::blockchain::{
+    block::BlockDetails,
     block_chain::BLOCKCHAIN,
     block_chain_errors::BlockChainError,
-    transaction::TransactionInfo,
+    query::{BlockQuery, HistoryEntry, HistoryQuery, Page, DEFAULT_PAGE_SIZE},
+    transaction::{TransactionInfo, TransactionStatus},
     wallet::{MineRewardAddress, WalletCoins, WalletInfo},
 };
-use actix_web::{get, post, web::Json, web::Path, HttpResponse};
+use actix_web::{
+    get, post, web, web::Json, web::Path, web::Query, HttpResponse, HttpResponseBuilder,
+};
 use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+//THE HEADER THAT CONTAINS THE ID OF A NEWLY CREATED TRANSACTION
+pub const TRANSACTION_ID_HEADER: &str = "Transaction-Id";
+
+//THE HEADER THAT CONTAINS THE CURSOR OF THE NEXT PAGE WHEN THE TEXT ENDPOINTS ARE PAGINATED
+pub const NEXT_CURSOR_HEADER: &str = "Next-Cursor";
+
+//ADD THE 'Next-Cursor' HEADER TO THE RESPONSE IF THERE IS ANOTHER PAGE
+fn with_next_cursor<T>(mut response: HttpResponseBuilder, page: &Page<T>) -> HttpResponseBuilder {
+    if let Some(cursor) = &page.next_cursor {
+        response.insert_header((NEXT_CURSOR_HEADER, cursor.as_str()));
+    }
+    response
+}
 
 //CREATE NEW TRANSACTION WITH 'FROM' ADDRESS, 'FROM' PASSWORD, 'TO' ADDRESS AND THE AMOUNT. ALL THE TRANSACTIONS THAT ARE CREATED ARE GOING TO PENDING TRANSACTIONS
+//THE ID OF THE TRANSACTION IS RETURNED IN THE 'Transaction-Id' HEADER
+//DEPRECATED, USE 'POST /v1/transactions' INSTEAD
+#[utoipa::path(
+    tag = "legacy",
+    responses(
+        (status = 200, description = "The transaction was added to pending transactions", body = String, content_type = "text/plain",
+            headers(("Transaction-Id" = String, description = "The id of the new transaction"))),
+        (status = 404, description = "An address is empty", body = String, content_type = "text/html"),
+        (status = 424, description = "The transaction is not valid", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
 #[post("/transaction/new")]
 pub async fn create_transaction(
     transaction: Json<TransactionInfo>,
-) -> Result<String, BlockChainError> {
-    Ok(BLOCKCHAIN
-        .lock()
-        .unwrap()
-        .create_transaction(transaction.0)?)
+) -> Result<HttpResponse, BlockChainError> {
+    let transaction = BLOCKCHAIN.submit_transaction(transaction.0)?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header((TRANSACTION_ID_HEADER, transaction.id))
+        .body("Transaction successfully made"))
 }
 
 //ALL THE TRANSACTIONS FROM PENDING TRANSACTIONS ARE MOVED INTO THE BLOCK CHAIN AND THE MINER IS REWARDED WITH AN AMOUNT OF COINS
+//DEPRECATED, USE 'POST /v1/blocks' INSTEAD
+#[utoipa::path(
+    tag = "legacy",
+    responses(
+        (status = 200, description = "The pending transactions were mined into a new block", body = String, content_type = "text/plain"),
+        (status = 424, description = "There are no pending transactions or the reward address doesn't exist", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
 #[post("/transaction/mine")]
 pub async fn mine_pending_transactions(
     reward_address: Json<MineRewardAddress>,
 ) -> Result<String, BlockChainError> {
-    Ok(BLOCKCHAIN
-        .lock()
-        .unwrap()
-        .mine_pending_transactions(&reward_address.mining_reward_address)?)
+    //MINING TAKES AS LONG AS THE PROOF OF WORK, SO IT DOESN'T RUN ON THE WORKER THREAD
+    web::block(move || BLOCKCHAIN.mine_pending_transactions(&reward_address.mining_reward_address))
+        .await?
 }
 
 //CREATE A NEW WALLET WITH AN ADDRESS, A PASSWORD AND AN AMOUNT OF COINS
+//DEPRECATED, USE 'POST /v1/wallets' INSTEAD
+#[utoipa::path(
+    tag = "legacy",
+    responses(
+        (status = 200, description = "The wallet was created", body = String, content_type = "text/plain"),
+        (status = 404, description = "The address is empty", body = String, content_type = "text/html"),
+        (status = 424, description = "The wallet is not valid or already exists", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
 #[post("/wallet/new")]
 pub async fn create_wallet(wallet: Json<WalletInfo>) -> Result<String, BlockChainError> {
-    Ok(BLOCKCHAIN.lock().unwrap().create_wallet(wallet.0)?)
+    BLOCKCHAIN.create_wallet(wallet.0)
 }
 
 //ADD COINS TO AN EXISTING WALLET, THE ADDRESS AND THE PASSWORD ARE NEEDED
+//DEPRECATED, USE 'POST /v1/wallets/{address}/coins' INSTEAD
+#[utoipa::path(
+    tag = "legacy",
+    responses(
+        (status = 200, description = "The coins were added to the wallet", body = String, content_type = "text/plain"),
+        (status = 404, description = "The wallet doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
 #[post("/wallet/addCoins")]
 pub async fn add_coins(wallet: Json<WalletCoins>) -> Result<String, BlockChainError> {
-    Ok(BLOCKCHAIN.lock().unwrap().add_coins(wallet.0)?)
+    BLOCKCHAIN.add_coins(wallet.0)
 }
 
 //PARSE THE DATA AND SHOW THE BLOCKCHAIN
+//THE BLOCKS CAN BE FILTERED AND PAGINATED WITH THE SAME QUERY AS '/blocks', WITHOUT A LIMIT THE WHOLE CHAIN IS SHOWN
+//DEPRECATED, USE 'GET /v1/blocks' INSTEAD
+#[utoipa::path(
+    tag = "legacy",
+    params(BlockQuery),
+    responses(
+        (status = 200, description = "The blocks as text", body = String, content_type = "text/plain",
+            headers(("Next-Cursor" = String, description = "The cursor of the next page, missing on the last page"))),
+        (status = 424, description = "The query is not valid", body = String, content_type = "text/html"),
+    )
+)]
 #[get("/blockchain/get")]
-pub async fn show_blockchain() -> Result<HttpResponse, BlockChainError> {
-    let chain = BLOCKCHAIN.lock().unwrap().chain.clone();
+pub async fn show_blockchain(query: Query<BlockQuery>) -> Result<HttpResponse, BlockChainError> {
+    let page = BLOCKCHAIN.list_blocks(&query)?;
     let mut chain_string = String::new();
 
-    for block in chain {
+    for block in &page.items {
         let mut transactions_string = String::new();
 
-        for transaction in block.transactions {
+        for transaction in &block.transactions {
             transactions_string = format!(
-                "{}\n{}",
+                "{}\n   From: {}\n   To: {}\n   Amount: {}\n",
                 transactions_string,
-                format!(
-                    "   From: {}\n   To: {}\n   Amount: {}\n",
-                    transaction.from_wallet.address,
-                    transaction.to_wallet.address,
-                    transaction.amount,
-                )
+                transaction.from_address,
+                transaction.to_address,
+                transaction.amount,
             );
         }
 
         chain_string = format!(
-            "{}{}",
+            "{}Index: {}\nTimestamp: {}\nTransactions: \n{}\nHash: {}\nPrevious hash: {}\n\n",
             chain_string,
-            format!(
-                "Index: {}\nTimestamp: {}\nTransactions: \n{}\nHash: {}\nPrevious hash: {}\n\n",
-                block.index, block.timestamp, transactions_string, block.hash, block.previous_hash
-            )
+            block.index,
+            block.timestamp,
+            transactions_string,
+            block.hash,
+            block.previous_hash
         )
     }
 
-    Ok(HttpResponse::Ok().body(chain_string))
+    Ok(with_next_cursor(HttpResponse::Ok(), &page).body(chain_string))
 }
 
 //CONTAINS THE ADDRESS AND THE PASSWORD OF AN WALLET IN ORDER TO SEE IT BALANCE
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Path)]
 pub struct AddressIdentifier {
     address: String,
     password: String,
 }
 
 //SHOW THE BALLANCE OF AN WALLET BASED ON THE ADDRESS AND PASSWORD
+//DEPRECATED, USE 'GET /v1/wallets/{address}/balance' INSTEAD
+#[utoipa::path(
+    tag = "legacy",
+    params(AddressIdentifier),
+    responses(
+        (status = 200, description = "The balance of the wallet as text", body = String, content_type = "text/plain"),
+        (status = 404, description = "The wallet doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong", body = String, content_type = "text/html"),
+    )
+)]
 #[get("/wallet/balance/{address}/{password}")]
 pub async fn get_wallet_balance(
     address_identifier: Path<AddressIdentifier>,
@@ -91,45 +171,157 @@ pub async fn get_wallet_balance(
     let address_iden = address_identifier.into_inner();
     let (address, password) = (address_iden.address, address_iden.password);
 
-    match BLOCKCHAIN
-        .lock()
-        .unwrap()
-        .get_balance_of_wallet(&address, &password)
-    {
-        Ok(balance) => Ok(HttpResponse::Ok().body(format!("Your balance is: {}", balance))),
+    match BLOCKCHAIN.get_balance_of_wallet(&address, &password) {
+        Ok(balance) => Ok(HttpResponse::Ok().body(format!("Your balance is: {}", balance.balance))),
         Err(err) => Err(err),
     }
 }
 
 //SHOW THE TRANSACTIONS THAT HAD BEEN MADE IN AN WALLET BASED ON THE ADDRESS AND PASSWORD
-#[get("wallet/transactions/{address}/{password}")]
+//THE TRANSACTIONS CAN BE FILTERED AND PAGINATED WITH THE SAME QUERY AS '/wallet/history', WITHOUT A LIMIT ALL OF THEM ARE SHOWN
+//DEPRECATED, USE 'GET /v1/wallets/{address}/history' INSTEAD
+#[utoipa::path(
+    tag = "legacy",
+    params(AddressIdentifier, HistoryQuery),
+    responses(
+        (status = 200, description = "The transactions of the wallet as text", body = String, content_type = "text/plain",
+            headers(("Next-Cursor" = String, description = "The cursor of the next page, missing on the last page"))),
+        (status = 404, description = "The wallet doesn't exist or has no transactions", body = String),
+        (status = 424, description = "The password is wrong or the query is not valid", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/wallet/transactions/{address}/{password}")]
 pub async fn get_wallet_transactions(
     address_identifier: Path<AddressIdentifier>,
+    query: Query<HistoryQuery>,
 ) -> Result<HttpResponse, BlockChainError> {
     let address_identifier = address_identifier.into_inner();
     let (address, password) = (address_identifier.address, address_identifier.password);
 
-    let transactions = BLOCKCHAIN
-        .lock()
-        .unwrap()
-        .get_transactions_of_wallet(&address, &password)?;
+    let page = BLOCKCHAIN.get_wallet_history(&address, &password, &query)?;
 
-    if transactions.len() == 0 {
+    if page.items.is_empty() {
         return Ok(HttpResponse::NotFound().body("No transactions found for this wallet!"));
     }
 
     let mut transactions_string = String::new();
 
-    for transaction in transactions {
+    for entry in &page.items {
+        let transaction = &entry.transaction;
         transactions_string = format!(
-            "{}{}",
+            "{}From: {}\nTo: {}\nAmount: {}\nStatus: {:?}\n\n",
             transactions_string,
-            format!(
-                "From: {}\nTo: {}\nAmount: {}\n\n",
-                transaction.from_address, transaction.to_address, transaction.amount,
-            )
+            transaction.from_address,
+            transaction.to_address,
+            transaction.amount,
+            entry.status,
         );
     }
 
-    Ok(HttpResponse::Ok().body(transactions_string))
+    Ok(with_next_cursor(HttpResponse::Ok(), &page).body(transactions_string))
+}
+
+//SHOW A PAGE OF THE TRANSACTIONS OF AN WALLET, FOR EXAMPLE '/wallet/history/{address}/{password}?direction=in&limit=10'
+//DEPRECATED, USE 'GET /v1/wallets/{address}/history' INSTEAD
+#[utoipa::path(
+    tag = "legacy",
+    params(AddressIdentifier, HistoryQuery),
+    responses(
+        (status = 200, description = "A page of the transactions of the wallet", body = Page<HistoryEntry>),
+        (status = 404, description = "The wallet doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong or the query is not valid", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/wallet/history/{address}/{password}")]
+pub async fn get_wallet_history(
+    address_identifier: Path<AddressIdentifier>,
+    query: Query<HistoryQuery>,
+) -> Result<Json<Page<HistoryEntry>>, BlockChainError> {
+    let address_identifier = address_identifier.into_inner();
+    let mut query = query.into_inner();
+    query.limit = query.limit.or(Some(DEFAULT_PAGE_SIZE));
+
+    Ok(Json(BLOCKCHAIN.get_wallet_history(
+        &address_identifier.address,
+        &address_identifier.password,
+        &query,
+    )?))
+}
+
+//SHOW THE LAST BLOCK FROM THE CHAIN
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "blocks",
+    responses(
+        (status = 200, description = "The last block of the chain", body = BlockDetails),
+    )
+)]
+#[get("/blocks/latest")]
+pub async fn get_latest_block() -> Result<Json<BlockDetails>, BlockChainError> {
+    Ok(Json(BLOCKCHAIN.get_latest_block()?))
+}
+
+//SHOW THE BLOCK WITH THE GIVEN HASH
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "blocks",
+    params(("hash" = String, Path, description = "The hash of the block")),
+    responses(
+        (status = 200, description = "The block with the hash", body = BlockDetails),
+        (status = 404, description = "There is no block with the hash", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/blocks/hash/{hash}")]
+pub async fn get_block_by_hash(hash: Path<String>) -> Result<Json<BlockDetails>, BlockChainError> {
+    Ok(Json(BLOCKCHAIN.get_block_by_hash(&hash)?))
+}
+
+//SHOW THE BLOCK FOUND AT THE GIVEN HEIGHT
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "blocks",
+    params(("height" = usize, Path, description = "The height of the block, the genesis block is at 0")),
+    responses(
+        (status = 200, description = "The block at the height", body = BlockDetails),
+        (status = 404, description = "There is no block at the height", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/blocks/{height}")]
+pub async fn get_block(height: Path<usize>) -> Result<Json<BlockDetails>, BlockChainError> {
+    Ok(Json(BLOCKCHAIN.get_block(height.into_inner())?))
+}
+
+//SHOW A PAGE OF BLOCKS, FOR EXAMPLE '/blocks?from=1&to=10' OR '/blocks?order=desc&limit=5&cursor=...'
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "blocks",
+    params(BlockQuery),
+    responses(
+        (status = 200, description = "A page of blocks", body = Page<BlockDetails>),
+        (status = 424, description = "The query is not valid", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/blocks")]
+pub async fn get_blocks(
+    query: Query<BlockQuery>,
+) -> Result<Json<Page<BlockDetails>>, BlockChainError> {
+    let mut query = query.into_inner();
+    query.limit = query.limit.or(Some(DEFAULT_PAGE_SIZE));
+
+    Ok(Json(BLOCKCHAIN.list_blocks(&query)?))
+}
+
+//SHOW A TRANSACTION, THE BLOCK WHERE IT WAS MINED AND THE NUMBER OF CONFIRMATIONS OR THAT IT IS STILL PENDING
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "transactions",
+    params(("id" = String, Path, description = "The id of the transaction")),
+    responses(
+        (status = 200, description = "The transaction and its status", body = TransactionStatus),
+        (status = 404, description = "There is no transaction with the id", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/transactions/{id}")]
+pub async fn get_transaction(id: Path<String>) -> Result<Json<TransactionStatus>, BlockChainError> {
+    Ok(Json(BLOCKCHAIN.get_transaction(&id)?))
 }
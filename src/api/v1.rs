@@ -0,0 +1,436 @@
+use crate::blockchain::{
+    batch::{BatchItem, BatchItemStatus, BatchMode},
+    block::BlockDetails,
+    block_chain::BLOCKCHAIN,
+    block_chain_errors::BlockChainError,
+    escrow::ClaimInfo,
+    multisig::{
+        MultisigWalletDetails, MultisigWalletInfo, OwnerCredentials, Proposal, ProposalInfo,
+    },
+    query::{HistoryEntry, HistoryQuery, Page, DEFAULT_PAGE_SIZE},
+    standing_order::{StandingOrder, StandingOrderInfo},
+    transaction::{TransactionDetails, TransactionInfo, TransactionStatus},
+    wallet::{MineRewardAddress, WalletBalance, WalletCoins, WalletInfo},
+};
+use actix_web::{
+    get, post,
+    web::{self, Json, Path, Query},
+    HttpResponse,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+//THE PREFIX OF ALL THE VERSIONED ROUTES
+pub const V1_PREFIX: &str = "/v1";
+
+//THE PASSWORD OF THE WALLET WHOSE ADDRESS IS IN THE PATH, OR OF THE SENDER OF THE TRANSACTION
+#[derive(Deserialize, Serialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+pub struct WalletPassword {
+    pub password: String,
+}
+
+//THE COINS ADDED TO THE WALLET WHOSE ADDRESS IS IN THE PATH
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct CoinsDeposit {
+    pub password: String,
+    pub coins: u32,
+}
+
+//TRANSACTIONS THAT ARE SUBMITTED TOGETHER, 'mode' IS 'atomic' WHEN MISSING
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct BatchRequest {
+    pub transactions: Vec<TransactionInfo>,
+    #[serde(default)]
+    pub mode: BatchMode,
+}
+
+//THE RESULT OF EVERY TRANSACTION OF A BATCH, IN THE SAME ORDER AS IN THE BATCH
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct BatchResponse {
+    pub accepted: usize,
+    pub items: Vec<BatchItem>,
+}
+
+//CREATE A NEW TRANSACTION AND ADD IT TO PENDING TRANSACTIONS
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "transactions",
+    responses(
+        (status = 201, description = "The transaction was added to pending transactions", body = TransactionDetails),
+        (status = 404, description = "An address is empty", body = String, content_type = "text/html"),
+        (status = 424, description = "The transaction is not valid", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/transactions")]
+pub async fn submit_transaction(
+    transaction: Json<TransactionInfo>,
+) -> Result<HttpResponse, BlockChainError> {
+    Ok(HttpResponse::Created().json(BLOCKCHAIN.submit_transaction(transaction.0)?))
+}
+
+//ADD A BATCH OF TRANSACTIONS TO PENDING TRANSACTIONS, THE TOTAL OF EVERY SENDER IS CHECKED AGAINST ITS BALANCE
+//THE ANSWER IS '201' WHEN ALL THE TRANSACTIONS WERE ADDED AND '207' WHEN SOME WERE NOT, THE ITEMS SAY WHY
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "transactions",
+    request_body = BatchRequest,
+    responses(
+        (status = 201, description = "All the transactions were added to pending transactions", body = BatchResponse),
+        (status = 207, description = "Some or all the transactions were not added", body = BatchResponse),
+        (status = 424, description = "The batch is empty or has too many transactions", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/transactions/batch")]
+pub async fn submit_batch(batch: Json<BatchRequest>) -> Result<HttpResponse, BlockChainError> {
+    let batch = batch.into_inner();
+    let items = BLOCKCHAIN.submit_batch(batch.transactions, batch.mode)?;
+    let accepted = items
+        .iter()
+        .filter(|item| item.status == BatchItemStatus::Accepted)
+        .count();
+
+    let mut response = match accepted == items.len() {
+        true => HttpResponse::Created(),
+        false => HttpResponse::MultiStatus(),
+    };
+    Ok(response.json(BatchResponse { accepted, items }))
+}
+
+//CANCEL A PENDING TRANSACTION WITH THE PASSWORD OF THE SENDER, THE COINS TAKEN FOR IT GO BACK TO THE SENDER
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "transactions",
+    params(("id" = String, Path, description = "The id of the transaction")),
+    request_body = WalletPassword,
+    responses(
+        (status = 200, description = "The transaction was cancelled", body = TransactionStatus),
+        (status = 404, description = "The transaction doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong or the transaction is no longer pending", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/transactions/{id}/cancel")]
+pub async fn cancel_transaction(
+    id: Path<String>,
+    password: Json<WalletPassword>,
+) -> Result<Json<TransactionStatus>, BlockChainError> {
+    Ok(Json(
+        BLOCKCHAIN.cancel_transaction(&id, &password.password)?,
+    ))
+}
+
+//CLAIM THE COINS OF AN ESCROW AS ITS RECIPIENT WITH THE PREIMAGE OF ITS HASH, THE CLAIM IS ADDED TO PENDING TRANSACTIONS
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "escrow",
+    params(("id" = String, Path, description = "The id of the escrow transaction")),
+    request_body = ClaimInfo,
+    responses(
+        (status = 201, description = "The claim was added to pending transactions", body = TransactionDetails),
+        (status = 404, description = "The escrow doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password or the preimage is wrong, or the escrow is not mined or already settled", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/escrows/{id}/claim")]
+pub async fn claim_escrow(
+    id: Path<String>,
+    claim: Json<ClaimInfo>,
+) -> Result<HttpResponse, BlockChainError> {
+    Ok(HttpResponse::Created().json(BLOCKCHAIN.claim_escrow(&id, claim.into_inner())?))
+}
+
+//GIVE THE COINS OF AN ESCROW BACK TO ITS SENDER AFTER THE TIMEOUT, THE REFUND IS ADDED TO PENDING TRANSACTIONS
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "escrow",
+    params(("id" = String, Path, description = "The id of the escrow transaction")),
+    request_body = WalletPassword,
+    responses(
+        (status = 201, description = "The refund was added to pending transactions", body = TransactionDetails),
+        (status = 404, description = "The escrow doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong, the timeout has not passed, or the escrow is not mined or already settled", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/escrows/{id}/refund")]
+pub async fn refund_escrow(
+    id: Path<String>,
+    password: Json<WalletPassword>,
+) -> Result<HttpResponse, BlockChainError> {
+    Ok(HttpResponse::Created().json(BLOCKCHAIN.refund_escrow(&id, &password.password)?))
+}
+
+//MINE THE PENDING TRANSACTIONS INTO A NEW BLOCK AND REWARD THE MINER
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "blocks",
+    responses(
+        (status = 201, description = "The pending transactions were mined into the block", body = BlockDetails),
+        (status = 424, description = "There are no pending transactions or the reward address doesn't exist", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/blocks")]
+pub async fn mine_block(
+    reward_address: Json<MineRewardAddress>,
+) -> Result<HttpResponse, BlockChainError> {
+    //MINING TAKES AS LONG AS THE PROOF OF WORK, SO IT DOESN'T RUN ON THE WORKER THREAD
+    let block =
+        web::block(move || BLOCKCHAIN.mine_block(&reward_address.mining_reward_address)).await??;
+    Ok(HttpResponse::Created().json(block))
+}
+
+//CREATE A NEW WALLET WITH AN ADDRESS, A PASSWORD AND AN AMOUNT OF COINS
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "wallets",
+    responses(
+        (status = 201, description = "The wallet was created", body = WalletBalance),
+        (status = 404, description = "The address is empty", body = String, content_type = "text/html"),
+        (status = 424, description = "The wallet is not valid or already exists", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/wallets")]
+pub async fn create_wallet(wallet: Json<WalletInfo>) -> Result<HttpResponse, BlockChainError> {
+    Ok(HttpResponse::Created().json(BLOCKCHAIN.add_wallet(wallet.0)?))
+}
+
+//ADD COINS TO AN EXISTING WALLET
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "wallets",
+    params(("address" = String, Path, description = "The address of the wallet")),
+    responses(
+        (status = 200, description = "The coins were added to the wallet", body = WalletBalance),
+        (status = 404, description = "The wallet doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/wallets/{address}/coins")]
+pub async fn add_coins(
+    address: Path<String>,
+    deposit: Json<CoinsDeposit>,
+) -> Result<Json<WalletBalance>, BlockChainError> {
+    let deposit = deposit.into_inner();
+
+    Ok(Json(BLOCKCHAIN.deposit_coins(WalletCoins {
+        address: address.into_inner(),
+        password: deposit.password,
+        coins: deposit.coins,
+    })?))
+}
+
+//SHOW THE BALANCE OF A WALLET, THE COINS IT CAN SPEND AND THE ONES IT RECEIVED WITH A LOCK THAT HAS NOT PASSED YET
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "wallets",
+    params(("address" = String, Path, description = "The address of the wallet"), WalletPassword),
+    responses(
+        (status = 200, description = "The balance of the wallet", body = WalletBalance),
+        (status = 404, description = "The wallet doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/wallets/{address}/balance")]
+pub async fn get_wallet_balance(
+    address: Path<String>,
+    password: Query<WalletPassword>,
+) -> Result<Json<WalletBalance>, BlockChainError> {
+    Ok(Json(
+        BLOCKCHAIN.get_balance_of_wallet(&address, &password.password)?,
+    ))
+}
+
+//SHOW A PAGE OF THE TRANSACTIONS OF A WALLET, FOR EXAMPLE '/v1/wallets/{address}/history?password=pass&direction=in&limit=10'
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "wallets",
+    params(("address" = String, Path, description = "The address of the wallet"), WalletPassword, HistoryQuery),
+    responses(
+        (status = 200, description = "A page of the transactions of the wallet", body = Page<HistoryEntry>),
+        (status = 404, description = "The wallet doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong or the query is not valid", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/wallets/{address}/history")]
+pub async fn get_wallet_history(
+    address: Path<String>,
+    password: Query<WalletPassword>,
+    query: Query<HistoryQuery>,
+) -> Result<Json<Page<HistoryEntry>>, BlockChainError> {
+    let mut query = query.into_inner();
+    query.limit = query.limit.or(Some(DEFAULT_PAGE_SIZE));
+
+    Ok(Json(BLOCKCHAIN.get_wallet_history(
+        &address,
+        &password.password,
+        &query,
+    )?))
+}
+
+//CREATE A MULTISIGNATURE WALLET, TRANSFERS FROM IT MUST BE APPROVED BY 'threshold' OF ITS OWNERS
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "multisig",
+    responses(
+        (status = 201, description = "The wallet was created", body = MultisigWalletDetails),
+        (status = 404, description = "The address is empty", body = String, content_type = "text/html"),
+        (status = 424, description = "The wallet, its owners or its threshold are not valid, or the wallet already exists", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/wallets/multisig")]
+pub async fn create_multisig_wallet(
+    wallet: Json<MultisigWalletInfo>,
+) -> Result<HttpResponse, BlockChainError> {
+    Ok(HttpResponse::Created().json(BLOCKCHAIN.create_multisig_wallet(wallet.0)?))
+}
+
+//PROPOSE A TRANSFER FROM A MULTISIGNATURE WALLET AS ONE OF ITS OWNERS
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "multisig",
+    params(("address" = String, Path, description = "The address of the multisignature wallet")),
+    responses(
+        (status = 201, description = "The proposal was created, it is executed right away when one approval is enough", body = Proposal),
+        (status = 404, description = "The wallet doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The owner, its password or the transfer are not valid", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/wallets/{address}/proposals")]
+pub async fn propose_transfer(
+    address: Path<String>,
+    proposal: Json<ProposalInfo>,
+) -> Result<HttpResponse, BlockChainError> {
+    Ok(HttpResponse::Created().json(BLOCKCHAIN.propose_transfer(&address, proposal.0)?))
+}
+
+//SHOW THE PROPOSALS OF A MULTISIGNATURE WALLET AND WHO APPROVED THEM TO ONE OF ITS OWNERS
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "multisig",
+    params(("address" = String, Path, description = "The address of the multisignature wallet"), OwnerCredentials),
+    responses(
+        (status = 200, description = "The proposals of the wallet, the oldest first", body = Vec<Proposal>),
+        (status = 404, description = "The wallet doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The owner or its password are not valid", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/wallets/{address}/proposals")]
+pub async fn get_proposals(
+    address: Path<String>,
+    credentials: Query<OwnerCredentials>,
+) -> Result<Json<Vec<Proposal>>, BlockChainError> {
+    Ok(Json(BLOCKCHAIN.get_proposals(&address, &credentials)?))
+}
+
+//SHOW A PROPOSAL AND WHO APPROVED IT TO ONE OF THE OWNERS OF ITS WALLET
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "multisig",
+    params(("id" = String, Path, description = "The id of the proposal"), OwnerCredentials),
+    responses(
+        (status = 200, description = "The proposal", body = Proposal),
+        (status = 404, description = "The proposal doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The owner or its password are not valid", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/proposals/{id}")]
+pub async fn get_proposal(
+    id: Path<String>,
+    credentials: Query<OwnerCredentials>,
+) -> Result<Json<Proposal>, BlockChainError> {
+    Ok(Json(BLOCKCHAIN.get_proposal(&id, &credentials)?))
+}
+
+//APPROVE A PROPOSAL AS ONE OF THE OWNERS OF ITS WALLET, THE APPROVAL THAT REACHES THE THRESHOLD ADDS THE TRANSFER TO PENDING TRANSACTIONS
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "multisig",
+    params(("id" = String, Path, description = "The id of the proposal")),
+    request_body = OwnerCredentials,
+    responses(
+        (status = 200, description = "The approval was recorded", body = Proposal),
+        (status = 404, description = "The proposal doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The owner or its password are not valid, the owner already approved, the proposal is no longer pending or the transfer can't be added", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/proposals/{id}/approve")]
+pub async fn approve_proposal(
+    id: Path<String>,
+    credentials: Json<OwnerCredentials>,
+) -> Result<Json<Proposal>, BlockChainError> {
+    Ok(Json(
+        BLOCKCHAIN.approve_proposal(&id, credentials.into_inner())?,
+    ))
+}
+
+//CREATE A STANDING ORDER AUTHORISED BY THE PAYER, ITS PAYMENTS ARE ADDED TO PENDING TRANSACTIONS AT EVERY INTERVAL
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "standing-orders",
+    request_body = StandingOrderInfo,
+    responses(
+        (status = 201, description = "The standing order was created", body = StandingOrder),
+        (status = 424, description = "The wallets, the password, the payment or the schedule are not valid", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/standing-orders")]
+pub async fn create_standing_order(
+    order: Json<StandingOrderInfo>,
+) -> Result<HttpResponse, BlockChainError> {
+    Ok(HttpResponse::Created().json(BLOCKCHAIN.create_standing_order(order.into_inner())?))
+}
+
+//SHOW THE STANDING ORDERS PAID FROM A WALLET TO ITS OWNER
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "standing-orders",
+    params(("address" = String, Path, description = "The address of the paying wallet"), WalletPassword),
+    responses(
+        (status = 200, description = "The standing orders of the wallet, the oldest first", body = Vec<StandingOrder>),
+        (status = 404, description = "The wallet doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong", body = String, content_type = "text/html"),
+    )
+)]
+#[get("/wallets/{address}/standing-orders")]
+pub async fn get_standing_orders(
+    address: Path<String>,
+    password: Query<WalletPassword>,
+) -> Result<Json<Vec<StandingOrder>>, BlockChainError> {
+    Ok(Json(
+        BLOCKCHAIN.get_standing_orders(&address, &password.password)?,
+    ))
+}
+
+//REVOKE A STANDING ORDER WITH THE PASSWORD OF THE PAYER, NO PAYMENT IS ADDED AFTER IT
+#[utoipa::path(
+    context_path = "/v1",
+    tag = "standing-orders",
+    params(("id" = String, Path, description = "The id of the standing order")),
+    request_body = WalletPassword,
+    responses(
+        (status = 200, description = "The standing order was revoked", body = StandingOrder),
+        (status = 404, description = "The standing order doesn't exist", body = String, content_type = "text/html"),
+        (status = 424, description = "The password is wrong or the standing order was already completed or revoked", body = String, content_type = "text/html"),
+        (status = 503, description = "The node is shutting down", body = String, content_type = "text/html"),
+    )
+)]
+#[post("/standing-orders/{id}/revoke")]
+pub async fn revoke_standing_order(
+    id: Path<String>,
+    password: Json<WalletPassword>,
+) -> Result<Json<StandingOrder>, BlockChainError> {
+    Ok(Json(
+        BLOCKCHAIN.revoke_standing_order(&id, &password.password)?,
+    ))
+}
@@ -0,0 +1,67 @@
+use super::block_chain_errors::BlockChainError;
+use super::transaction::TransactionDetails;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+//THE MOST TRANSACTIONS A BATCH CAN HAVE
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+//HOW A BATCH WITH INVALID TRANSACTIONS IS HANDLED
+//'atomic' ADDS ALL THE TRANSACTIONS OR NONE, 'best_effort' ADDS EVERY VALID TRANSACTION
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    #[default]
+    Atomic,
+    BestEffort,
+}
+
+//WHAT HAPPENED TO A TRANSACTION OF A BATCH
+//'not_applied' IS A VALID TRANSACTION OF AN ATOMIC BATCH THAT WAS NOT ADDED BECAUSE ANOTHER ONE WAS REJECTED
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Accepted,
+    Rejected,
+    NotApplied,
+}
+
+//THE RESULT OF A TRANSACTION OF A BATCH, IN THE SAME ORDER AS IN THE BATCH
+//AN ACCEPTED TRANSACTION HAS ITS DETAILS AND A REJECTED ONE THE ERROR SHOWN TO THE USERS
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct BatchItem {
+    pub status: BatchItemStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionDetails>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BatchItem {
+    //A TRANSACTION THAT WAS ADDED TO PENDING TRANSACTIONS
+    pub fn accepted(transaction: TransactionDetails) -> Self {
+        BatchItem {
+            status: BatchItemStatus::Accepted,
+            transaction: Some(transaction),
+            error: None,
+        }
+    }
+
+    //A TRANSACTION THAT IS NOT VALID
+    pub fn rejected(error: &BlockChainError) -> Self {
+        BatchItem {
+            status: BatchItemStatus::Rejected,
+            transaction: None,
+            error: Some(error.to_string()),
+        }
+    }
+
+    //A VALID TRANSACTION THAT WAS NOT ADDED
+    pub fn not_applied() -> Self {
+        BatchItem {
+            status: BatchItemStatus::NotApplied,
+            transaction: None,
+            error: None,
+        }
+    }
+}
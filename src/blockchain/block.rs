@@ -1,7 +1,10 @@
 use crypto_hash::{hex_digest, Algorithm};
-use std::{time::SystemTime};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+use utoipa::ToSchema;
 
-use crate::blockchain::transaction::Transaction;
+use crate::blockchain::transaction::{Expiry, LockTime, Transaction, TransactionDetails};
 
 //CONTAINS INFORMATION ABOUT A BLOCK FROM THE BLOCKCHAIN
 #[derive(Clone)]
@@ -14,9 +17,34 @@ pub struct Block {
     pub nonce: usize,
 }
 
+//THE INFORMATION ABOUT A BLOCK THAT IS SHOWN TO THE USERS
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct BlockDetails {
+    pub index: usize,
+    pub timestamp: u64,
+    pub transactions: Vec<TransactionDetails>,
+    pub hash: String,
+    pub previous_hash: String,
+    pub nonce: usize,
+}
+
+//REBUILD A BLOCK FROM ITS DETAILS, FOR EXAMPLE TO VALIDATE A CHAIN RECEIVED FROM A NODE
+impl From<&BlockDetails> for Block {
+    fn from(details: &BlockDetails) -> Self {
+        Block {
+            index: details.index,
+            timestamp: details.timestamp,
+            transactions: details.transactions.iter().map(Transaction::from).collect(),
+            hash: details.hash.clone(),
+            previous_hash: details.previous_hash.clone(),
+            nonce: details.nonce,
+        }
+    }
+}
+
 impl Block {
     //CREATE A NEW BLOCK
-    pub fn new(index: usize, transactions: &Vec<Transaction>) -> Self {
+    pub fn new(index: usize, transactions: &[Transaction]) -> Self {
         let mut new_block = Block {
             index,
             timestamp: SystemTime::now()
@@ -33,9 +61,10 @@ impl Block {
         new_block
     }
 
-    //SET BLOCK PREVIOUS HASH
+    //SET BLOCK PREVIOUS HASH AND CALCULATE THE HASH AGAIN, SO MINING NEVER STARTS FROM A HASH WITHOUT IT
     pub fn set_previous_hash(&mut self, previous_hash: &str) {
         self.previous_hash = previous_hash.to_string();
+        self.set_hash();
     }
 
     //SET HASH VALUE
@@ -49,6 +78,7 @@ impl Block {
 
         //ADD TRANSACTION INFO INTO A VEC AS STRINGS
         for transaction in &self.transactions {
+            transactions_string.push(transaction.id.clone());
             transactions_string.push(format!(
                 "{}{}",
                 transaction.from_wallet.address, transaction.from_wallet.address
@@ -58,16 +88,77 @@ impl Block {
                 transaction.to_wallet.address, transaction.to_wallet.address
             ));
             transactions_string.push(transaction.amount.to_string());
+            //THE FEE AND THE NONCE ARE ONLY PART OF THE HASH WHEN THEY ARE NOT 0, SO THE BLOCKS FROM BEFORE THEM KEEP THEIR HASHES
+            if transaction.fee > 0 {
+                transactions_string.push(format!("fee{}", transaction.fee));
+            }
+            if transaction.nonce > 0 {
+                transactions_string.push(format!("nonce{}", transaction.nonce));
+            }
+            //SO ARE THE TIME THE TRANSACTION WAS CREATED AND ITS EXPIRY, THE TIMES OF A TRANSACTION CAN'T BE CHANGED AFTER IT WAS MINED
+            if transaction.timestamp > 0 {
+                transactions_string.push(format!("timestamp{}", transaction.timestamp));
+            }
+            match transaction.expiry {
+                Some(Expiry::Timestamp(timestamp)) => {
+                    transactions_string.push(format!("expirytime{}", timestamp))
+                }
+                Some(Expiry::Height(height)) => {
+                    transactions_string.push(format!("expiryheight{}", height))
+                }
+                None => {}
+            }
+            //THE MEMO AND THE METADATA ARE ONLY PART OF THE HASH WHEN THEY ARE SET, WITH THEIR LENGTHS SO THEY CAN'T BE SHIFTED
+            if let Some(memo) = &transaction.memo {
+                transactions_string.push(format!("memo{}:{}", memo.len(), memo));
+            }
+            for (key, value) in &transaction.metadata {
+                transactions_string.push(format!(
+                    "meta{}:{}{}:{}",
+                    key.len(),
+                    key,
+                    value.len(),
+                    value
+                ));
+            }
+            //THE LOCK IS ONLY PART OF THE HASH WHEN IT IS SET
+            match transaction.lock {
+                Some(LockTime::Timestamp(timestamp)) => {
+                    transactions_string.push(format!("locktime{}", timestamp))
+                }
+                Some(LockTime::Height(height)) => {
+                    transactions_string.push(format!("lockheight{}", height))
+                }
+                None => {}
+            }
+            //SO ARE THE HASH LOCK OF AN ESCROW AND THE SETTLEMENT OF A CLAIM OR A REFUND
+            if let Some(hash_lock) = &transaction.hash_lock {
+                transactions_string.push(format!("hashlock{}", hash_lock.hash));
+                match hash_lock.timeout {
+                    LockTime::Timestamp(timestamp) => {
+                        transactions_string.push(format!("timeout{}", timestamp))
+                    }
+                    LockTime::Height(height) => {
+                        transactions_string.push(format!("timeoutheight{}", height))
+                    }
+                }
+            }
+            if let Some(settlement) = &transaction.settlement {
+                transactions_string.push(format!("settles{}", settlement.escrow));
+                if let Some(preimage) = &settlement.preimage {
+                    transactions_string.push(format!("preimage{}:{}", preimage.len(), preimage));
+                }
+            }
         }
 
         //ADD ALL INFOS ABOUT THE TRANSACTION INTO A STRING
         let hash_string = format!(
             "{}{}{}{}{}",
             self.index,
-            self.timestamp.to_string(),
+            self.timestamp,
             transactions_string.join(""),
             self.previous_hash,
-            self.nonce.to_string()
+            self.nonce
         );
 
         //CREATE AND RETURN THE HASH
@@ -75,11 +166,34 @@ impl Block {
         hex_digest(Algorithm::SHA256, hash_bytes)
     }
 
-    //MINE BLOCK BASED ON THE BLOCKCHAIN DIFFICULTY
-    pub fn mine_block(&mut self, difficulty: usize) {
-        while &self.hash[0..difficulty] != vec!["0"; difficulty].join("") {
+    //CHECK IF THE HASH MATCHES THE BLOCK INFO AND, EXCEPT FOR THE GENESIS BLOCK, IF IT WAS MINED WITH THE DIFFICULTY
+    pub fn is_valid(&self, difficulty: usize) -> bool {
+        self.hash == self.calculate_hash()
+            && (self.index == 0 || self.hash.starts_with(&"0".repeat(difficulty)))
+    }
+
+    //MINE BLOCK BASED ON THE BLOCKCHAIN DIFFICULTY, RETURN FALSE IF THE MINING WAS CANCELLED BEFORE THE HASH WAS FOUND
+    pub fn mine_block(&mut self, difficulty: usize, cancelled: &AtomicBool) -> bool {
+        let target = "0".repeat(difficulty);
+        while self.hash[0..difficulty] != target {
+            if cancelled.load(Ordering::Relaxed) {
+                return false;
+            }
             self.nonce += 1;
             self.set_hash();
         }
+        true
     }
-}
\ No newline at end of file
+
+    //RETURN THE INFORMATION ABOUT THE BLOCK THAT CAN BE SHOWN TO THE USERS
+    pub fn details(&self) -> BlockDetails {
+        BlockDetails {
+            index: self.index,
+            timestamp: self.timestamp,
+            transactions: self.transactions.iter().map(Transaction::details).collect(),
+            hash: self.hash.clone(),
+            previous_hash: self.previous_hash.clone(),
+            nonce: self.nonce,
+        }
+    }
+}
@@ -1,271 +1,2557 @@
+use super::batch::{BatchItem, BatchItemStatus, BatchMode, MAX_BATCH_SIZE};
 use super::block_chain_errors::BlockChainError;
-use super::transaction::TransactionInfo;
-use super::wallet::{Wallet, WalletCoins, WalletInfo, AddressType};
-use crate::blockchain::block::Block;
+use super::chain_index::ChainIndex;
+use super::escrow::{validate_settlements, ClaimInfo, Settlement, ESCROW_ADDRESS};
+use super::events::{Event, EventBus};
+use super::mempool::{Eviction, EvictionReason, Mempool, MempoolLimits};
+use super::multisig::{
+    authenticate_owner, Approval, Multisig, MultisigWalletDetails, MultisigWalletInfo,
+    OwnerCredentials, Proposal, ProposalInfo, ProposalStatus,
+};
+use super::query::{
+    paginate, BlockQuery, Cursor, Direction, HistoryCursor, HistoryEntry, HistoryPosition,
+    HistoryQuery, Page, SortOrder, TransactionState,
+};
+use super::snapshot::{ChainSnapshot, SnapshotHeader, SNAPSHOT_VERSION};
+use super::standing_order::{
+    StandingOrder, StandingOrderInfo, StandingOrderStatus, MAX_STANDING_ORDER_INTERVAL,
+    MIN_STANDING_ORDER_INTERVAL,
+};
+use super::transaction::{TransactionDetails, TransactionInfo, TransactionStatus};
+use super::wallet::{AddressType, LockedCoins, Wallet, WalletBalance, WalletCoins, WalletInfo};
+use super::write_ahead_log::{
+    AuthorisedProposal, AuthorisedStandingOrder, AuthorisedTransaction, Command, LogEntry,
+    MinedBlock, RecordedTransaction, ScheduledPayment, WriteAheadLog,
+};
+use crate::blockchain::block::{Block, BlockDetails};
 use crate::blockchain::transaction::Transaction;
+use crate::metrics::METRICS;
 use lazy_static::lazy_static;
-use std::sync::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Instant, SystemTime};
 use std::vec;
+use tracing::{info, warn};
 
 //THE ONLY 'INSTANCE' OF THE BLOCKCHAIN THAT EXISTS THROW ALL THE PROGRAM
 lazy_static! {
-    pub static ref BLOCKCHAIN: Mutex<BlockChain> = Mutex::new(BlockChain::default());
+    pub static ref BLOCKCHAIN: BlockChain = BlockChain::default();
 }
 
 //THE ADDRESS RESERVED FOR GIVING MINERS REWARDS
 pub const MINING_ADDRESS: &str = "MINING";
 
 //HOLDS ALL THE INFORMATION FROM THE BLOCKCHAIN
+//THE CHAIN, THE PENDING TRANSACTIONS AND THE WALLETS HAVE THEIR OWN LOCKS SO READS DON'T WAIT FOR EACH OTHER
+//WHEN MORE THAN ONE LOCK IS NEEDED THEY ARE ALWAYS TAKEN IN THIS ORDER: MINING, CHAIN, INDEX, PENDING TRANSACTIONS, WALLETS, PROPOSALS, STANDING ORDERS, LOG
+//EVERY CHANGE IS WRITTEN TO THE LOG WHILE HOLDING THE WALLETS LOCK, SO THE ORDER OF THE LOG IS THE ORDER THE CHANGES WERE APPLIED
+//THE COMMANDS THAT ONLY CHANGE THE STANDING ORDERS ARE WRITTEN WHILE HOLDING THE STANDING ORDERS LOCK INSTEAD
 pub struct BlockChain {
-    pub chain: Vec<Block>,
+    pub chain: RwLock<Vec<Block>>,
+    pub index: RwLock<ChainIndex>,
     pub difficulty: usize,
-    pub pending_transactions: Vec<Transaction>,
+    pub pending_transactions: Mutex<Mempool>,
     pub mining_reward: u32,
     pub name: String,
-    pub wallets: Vec<Wallet>,
+    pub chain_id: u32,
+    pub wallets: RwLock<Vec<Wallet>>,
+    pub proposals: RwLock<Vec<Proposal>>,
+    pub standing_orders: RwLock<Vec<StandingOrder>>,
+    pub events: EventBus,
+    mining: Mutex<()>,
+    shutting_down: AtomicBool,
+    chain_valid: AtomicBool,
+    storage_loaded: AtomicBool,
+    log: Mutex<Option<WriteAheadLog>>,
 }
 
 //THE DEFAULT VALUES USED FOR THE BLOCKCHAIN
 impl Default for BlockChain {
     fn default() -> Self {
         //THE BLOCKCHAIN STARTS WITH AN EMPTY BLOCK CALLED THE GENESIS BLOCK
-        let genesis_block = Block::new(0, &vec![]);
+        let chain = vec![Block::new(0, &[])];
         Self {
-            chain: vec![genesis_block],
+            index: RwLock::new(ChainIndex::build(&chain)),
+            chain: RwLock::new(chain),
             difficulty: 2,
-            pending_transactions: vec![],
+            pending_transactions: Mutex::new(Mempool::default()),
             mining_reward: 100,
             name: "Blockchain".to_string(),
-            wallets: vec![],
+            chain_id: 1,
+            wallets: RwLock::new(vec![]),
+            proposals: RwLock::new(vec![]),
+            standing_orders: RwLock::new(vec![]),
+            events: EventBus::default(),
+            mining: Mutex::new(()),
+            shutting_down: AtomicBool::new(false),
+            chain_valid: AtomicBool::new(true),
+            storage_loaded: AtomicBool::new(true),
+            log: Mutex::new(None),
         }
     }
 }
 
 impl BlockChain {
     //MINE THE PENDING TRANSACTIONS, REWARD THE MINER WITH THE MINING REWARD AMOUNT SPECIFIED IN THE DEFAULT. ADD A NEW TRANSACTION WITH THE MINER REWARD TO PENDING TRANSACTIONS
+    //THE PROOF OF WORK IS DONE WITHOUT HOLDING ANY STATE LOCK, SO READS AND NEW TRANSACTIONS ARE NOT BLOCKED WHILE MINING
     pub fn mine_pending_transactions(
-        &mut self,
-        mining_reward_address: &String,
+        &self,
+        mining_reward_address: &str,
     ) -> Result<String, BlockChainError> {
-        if self.pending_transactions.is_empty() {
-            return Err(BlockChainError::NoPendingTransactions);
-        }
+        self.mine_block(mining_reward_address)?;
+
+        Ok("Transactions successfully mined".to_string())
+    }
 
-        //UPDATE THE 'TO' WALLETS FOUND IN THE PENDING TRANSACTIONS
-        for transaction in self.pending_transactions.clone() {
-            let from_wallet = transaction.from_wallet;
-            let mut to_wallet = self.get_wallet(&transaction.to_wallet.address, AddressType::TO)?;
+    //MINE THE PENDING TRANSACTIONS INTO A NEW BLOCK AND RETURN IT
+    #[tracing::instrument(skip(self))]
+    pub fn mine_block(&self, mining_reward_address: &str) -> Result<BlockDetails, BlockChainError> {
+        self.mine(mining_reward_address, None)
+    }
 
-            to_wallet.balance += transaction.amount;
-            to_wallet.transactions.push(TransactionInfo {
-                from_address: from_wallet.address.clone(),
-                from_password: from_wallet.password.clone(),
-                to_address: to_wallet.address.clone(),
-                amount: transaction.amount,
-            });
+    //MINE A NEW BLOCK. WHEN REPLAYING A LOG THE BLOCK IS BUILT FROM THE RECORDED VALUES INSTEAD OF BEING MINED
+    fn mine(
+        &self,
+        mining_reward_address: &str,
+        recorded: Option<&MinedBlock>,
+    ) -> Result<BlockDetails, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        //ONLY ONE MINER CAN BUILD ON TOP OF THE CHAIN AT A TIME
+        //ONLY THE MINER REMOVES PENDING TRANSACTIONS, SO THE ONES CHECKED HERE ARE STILL THE FIRST ONES WHEN THE BLOCK IS COMMITTED
+        let _mining = self.mining.lock().unwrap();
+
+        //CHECK THE PENDING TRANSACTIONS AGAIN AND EVICT THE ONES THAT CAN'T BE MINED. A LOG HAS THE EVICTIONS AS SEPARATE ENTRIES
+        if recorded.is_none() {
+            self.evict_transactions_to_sweep()?;
+        }
 
-            self.update_wallet(to_wallet.clone())?;
+        //TAKE A SNAPSHOT OF THE TRANSACTIONS THAT WILL GO INTO THE BLOCK
+        let mut transactions: Vec<Transaction> = self
+            .pending_transactions
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        if let Some(recorded) = recorded {
+            if transactions.len() < recorded.transactions {
+                return Err(BlockChainError::ReplayMismatch);
+            }
+            transactions.truncate(recorded.transactions);
+        }
+        if transactions.is_empty() {
+            return Err(BlockChainError::NoPendingTransactions);
         }
 
-        //SEARCH FOR THE MINING REWARD ADDRESS AND RETURN AN ERROR IF IS NOT FOUND
+        //CHECK THAT THE MINING REWARD ADDRESS EXISTS BEFORE DOING ANY WORK
         let mining_reward_wallet = self.get_wallet(mining_reward_address, AddressType::REWARD)?;
 
         //CREATE BLOCK AND SET THE PREVIOUS HASH
-        let mut block = Block::new(self.chain.len(), &self.pending_transactions);
-        match self.chain.last() {
-            Some(latest_block) => {
-                block.set_previous_hash(&latest_block.hash);
-            }
-            None => {}
+        let (index, previous_hash) = {
+            let chain = self.chain.read().unwrap();
+            let latest_block = chain.last().ok_or(BlockChainError::ChainIsEmpty)?;
+            (chain.len(), latest_block.hash.clone())
         };
+        let mut block = Block::new(index, &transactions);
+        block.set_previous_hash(&previous_hash);
 
         //SET HASH FOR THE BLOCK
-        block.mine_block(self.difficulty);
+        match recorded {
+            Some(recorded) => {
+                block.timestamp = recorded.timestamp;
+                block.nonce = recorded.nonce;
+                block.set_hash();
+                if block.hash != recorded.hash || !block.is_valid(self.difficulty) {
+                    return Err(BlockChainError::ReplayMismatch);
+                }
+            }
+            None => {
+                let started = Instant::now();
+                if !block.mine_block(self.difficulty, &self.shutting_down) {
+                    info!(nonce = block.nonce, "mining cancelled");
+                    return Err(BlockChainError::ShuttingDown);
+                }
+                METRICS.record_mining(started.elapsed(), block.nonce + 1);
+            }
+        }
 
-        //PUSH THE BLOCK TO THE CHAIN
-        self.chain.push(block);
+        //COMMIT THE BLOCK, THE NEW PENDING TRANSACTIONS AND THE WALLETS TOGETHER
+        let mut chain = self.chain.write().unwrap();
+        let mut index = self.index.write().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
 
-        //ADD A NEW BLOCK WITH THE TRANSACTION FOR THE MINER REWARD TO PENDING TRANSACTIONS
-        self.pending_transactions = vec![Transaction::new(
+        //FIND THE 'TO' WALLETS BEFORE CHANGING ANYTHING
+        let to_wallets = transactions
+            .iter()
+            .map(|transaction| {
+                wallets
+                    .iter()
+                    .position(|wallet| wallet.address == transaction.to_wallet.address)
+                    .ok_or(BlockChainError::InvalidToAddress)
+            })
+            .collect::<Result<Vec<usize>, BlockChainError>>()?;
+
+        //THE TRANSACTION FOR THE MINER REWARD REPLACES THE MINED TRANSACTIONS IN PENDING TRANSACTIONS, THE MINER ALSO GETS THEIR FEES
+        let fees = transactions.iter().fold(0, |fees: u32, transaction| {
+            fees.saturating_add(transaction.fee)
+        });
+        let mut reward_transaction = Transaction::new(
             Wallet::new(MINING_ADDRESS.to_string(), 0, "".to_string()),
             mining_reward_wallet,
-            self.mining_reward,
-        )];
+            self.mining_reward.saturating_add(fees),
+        );
+        if let Some(recorded) = recorded {
+            reward_transaction.id = recorded.reward_id.clone();
+            reward_transaction.timestamp = recorded.reward_timestamp;
+        }
 
-        Ok("Transactions successfully mined".to_string())
+        //CHECK IF THE 'TO' WALLETS CAN HOLD THE COINS, 'invalid_transactions' EVICTED THE TRANSACTIONS THEY COULDN'T
+        let mut credits: HashMap<usize, u64> = HashMap::new();
+        for (transaction, position) in transactions.iter().zip(&to_wallets) {
+            if transaction.hash_lock.is_none() {
+                *credits.entry(*position).or_default() += transaction.amount as u64;
+            }
+        }
+        for (position, coins) in credits {
+            let wallet = &wallets[position];
+            wallet.check_credit(pending_transactions.pending_spend(&wallet.address), coins)?;
+        }
+
+        self.append_to_log(Command::MineBlock(MinedBlock {
+            reward_address: mining_reward_address.to_string(),
+            transactions: transactions.len(),
+            timestamp: block.timestamp,
+            nonce: block.nonce,
+            hash: block.hash.clone(),
+            reward_id: reward_transaction.id.clone(),
+            reward_timestamp: reward_transaction.timestamp,
+        }))?;
+
+        //UPDATE THE 'TO' WALLETS FOUND IN THE MINED TRANSACTIONS, THE COINS OF A TRANSACTION WITH A LOCK ARE KEPT LOCKED
+        //THE COINS OF AN ESCROW ARE NOT CREDITED, THE CLAIM OR THE REFUND THAT SETTLES IT CREDITS THEM
+        let mut credited_addresses: Vec<&str> = vec![];
+        for (transaction, position) in transactions.iter().zip(to_wallets) {
+            if transaction.hash_lock.is_some() {
+                continue;
+            }
+            match transaction.lock {
+                Some(lock) => wallets[position].locked.push(LockedCoins {
+                    transaction_id: transaction.id.clone(),
+                    amount: transaction.amount,
+                    lock,
+                }),
+                None => wallets[position].balance += transaction.amount,
+            }
+            if !credited_addresses.contains(&transaction.to_wallet.address.as_str()) {
+                credited_addresses.push(&transaction.to_wallet.address);
+            }
+        }
+
+        //INDEX THE MINED TRANSACTIONS AND PUSH THE BLOCK TO THE CHAIN
+        let block_details = block.details();
+        index.add_block(&block);
+        chain.push(block);
+
+        //TRANSACTIONS CREATED WHILE MINING STAY PENDING, THE MINED ONES ARE REPLACED BY THE TRANSACTION FOR THE MINER REWARD
+        let reward_details = reward_transaction.details();
+        pending_transactions.take(transactions.len());
+        pending_transactions.push_front(reward_transaction);
+
+        //LET THE SUBSCRIBERS KNOW ABOUT THE NEW BLOCK, THE NEW BALANCES AND THE REWARD TRANSACTION
+        self.events.publish(Event::BlockMined {
+            block: block_details.clone(),
+        });
+        for address in credited_addresses {
+            if let Ok(wallet) = find_wallet(&wallets, address, AddressType::GENERIC) {
+                self.publish_balance(&wallet, chain.len());
+            }
+        }
+        self.events.publish(Event::PendingTransaction {
+            transaction: reward_details,
+        });
+
+        info!(
+            block_height = chain.len() - 1,
+            transactions = transactions.len(),
+            "block mined"
+        );
+
+        Ok(block_details)
     }
 
     //CREATE A NEW TRANSACTION AND ADD IT TO PENDING TRANSACTIONS
     pub fn create_transaction(
-        &mut self,
+        &self,
         transaction: TransactionInfo,
     ) -> Result<String, BlockChainError> {
-        //CHECK IF THE TRANSACTION IS VALID
-        match transaction.check_transaction_info() {
-            Ok(_) => (),
-            Err(err) => return Err(err),
-        }
+        self.submit_transaction(transaction)?;
 
-        //GET THE 'FROM' WALLET AND RETURN AN ERROR IF IS NOT FOUND
-        let mut from_wallet = self.get_wallet(&transaction.from_address, AddressType::FROM)?;
+        Ok("Transaction successfully made".to_string())
+    }
 
-        //RETURN AN ERROR IF THE PASSWORD IS WRONG
-        if from_wallet.password != transaction.from_password {
-            return Err(BlockChainError::WrongPassword);
+    //CREATE A NEW TRANSACTION, ADD IT TO PENDING TRANSACTIONS AND RETURN IT
+    #[tracing::instrument(
+        skip_all,
+        fields(from = %transaction.from_address, to = %transaction.to_address, amount = transaction.amount)
+    )]
+    pub fn submit_transaction(
+        &self,
+        transaction: TransactionInfo,
+    ) -> Result<TransactionDetails, BlockChainError> {
+        self.submit(&transaction, Some(&transaction.from_password), None)
+    }
+
+    //ADD A NEW TRANSACTION TO PENDING TRANSACTIONS. WHEN REPLAYING A LOG THE TRANSACTION GETS ITS RECORDED ID AND TIMESTAMP
+    //THE LOG DOESN'T KEEP THE PASSWORD, SO A REPLAYED TRANSACTION HAS NONE AND IS NOT AUTHORISED AGAIN
+    fn submit(
+        &self,
+        transaction: &TransactionInfo,
+        password: Option<&str>,
+        recorded: Option<(String, u64)>,
+    ) -> Result<TransactionDetails, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        //A TRANSACTION WITH A NONCE CAN REPLACE A PENDING ONE, ONLY THE MINER REMOVES PENDING TRANSACTIONS WHILE A BLOCK IS MINED
+        let _mining = transaction.nonce.map(|_| self.mining.lock().unwrap());
+
+        //THE BALANCE CHECK, THE DEBIT AND THE NEW PENDING TRANSACTION ARE DONE UNDER THE SAME LOCKS
+        //THE CHAIN CAN'T GROW MEANWHILE, SO THE EXPIRY IS CHECKED AGAINST THE BLOCK THE TRANSACTION WOULD GO INTO
+        let chain = self.chain.read().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+
+        //CHECK THE TRANSACTION AND WRITE IT TO THE LOG BEFORE CHANGING ANYTHING
+        let (id, timestamp) = recorded.unzip();
+        let staged = stage_transaction(
+            transaction,
+            password,
+            id,
+            timestamp.unwrap_or_else(unix_time),
+            chain.len(),
+            &pending_transactions,
+            &wallets,
+        )?;
+        self.append_to_log(Command::SubmitTransaction {
+            transaction: AuthorisedTransaction::from(transaction),
+            id: staged.transaction.id.clone(),
+            timestamp: staged.transaction.timestamp,
+        })?;
+
+        let submitted = apply_staged(staged, &mut pending_transactions, &mut wallets)?;
+        self.announce(&submitted, chain.len());
+
+        Ok(submitted.details)
+    }
+
+    //ADD A BATCH OF TRANSACTIONS TO PENDING TRANSACTIONS UNDER THE SAME LOCKS, IN THE ORDER OF THE BATCH
+    //IN ATOMIC MODE ALL THE TRANSACTIONS ARE ADDED OR NONE, IN BEST-EFFORT MODE EVERY VALID TRANSACTION IS ADDED. RETURN THE RESULT OF EVERY TRANSACTION
+    #[tracing::instrument(skip_all, fields(transactions = transactions.len(), ?mode))]
+    pub fn submit_batch(
+        &self,
+        transactions: Vec<TransactionInfo>,
+        mode: BatchMode,
+    ) -> Result<Vec<BatchItem>, BlockChainError> {
+        let transactions = transactions
+            .into_iter()
+            .map(|transaction| (transaction, None))
+            .collect();
+
+        self.batch(transactions, mode)
+    }
+
+    //ADD A BATCH OF TRANSACTIONS. WHEN REPLAYING A LOG THE TRANSACTIONS GET THEIR RECORDED IDS AND TIMESTAMPS
+    //AND, LIKE ANY REPLAYED TRANSACTION, THEY ARE NOT AUTHORISED AGAIN
+    fn batch(
+        &self,
+        transactions: Vec<(TransactionInfo, Option<(String, u64)>)>,
+        mode: BatchMode,
+    ) -> Result<Vec<BatchItem>, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        if transactions.is_empty() {
+            return Err(BlockChainError::EmptyBatch);
+        }
+        if transactions.len() > MAX_BATCH_SIZE {
+            return Err(BlockChainError::BatchTooLarge);
         }
 
-        //GET THE 'TO' WALLET AND RETURN AN ERROR IF IS NOT FOUND
-        let to_wallet = self.get_wallet(&transaction.to_address, AddressType::TO)?;
+        let replaces = transactions
+            .iter()
+            .any(|(transaction, _)| transaction.nonce.is_some());
+        let _mining = replaces.then(|| self.mining.lock().unwrap());
+        let chain = self.chain.read().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
 
-        //CHECK IF THE 'FROM' WALLET HAVE ENOUGH COINS FOR THIS TRANSACTION
-        if from_wallet.balance < transaction.amount {
-            return Err(BlockChainError::NotEnoughCoins);
+        //IN ATOMIC MODE WHAT THE BATCH TAKES FROM EVERY SENDER IS CHECKED ONCE AGAINST ITS BALANCE
+        let overdrawn = match mode {
+            BatchMode::Atomic => {
+                overdrawn_senders(&transactions, &pending_transactions, &wallets, chain.len())
+            }
+            BatchMode::BestEffort => HashSet::new(),
         };
 
-        //CHECK IF THE AMOUNT IS LESS THAN 0
-        #[allow(unused_comparisons)]
-        if transaction.amount < 0 {
-            return Err(BlockChainError::NegativeAmount);
-        };
+        //THE TRANSACTIONS ARE ADDED TO COPIES OF THE PENDING TRANSACTIONS AND THE WALLETS, ONE AFTER THE OTHER
+        //NOTHING CHANGES UNTIL THE ACCEPTED ONES ARE WRITTEN TO THE LOG
+        let mut staged_transactions = pending_transactions.clone();
+        let mut staged_wallets = wallets.clone();
+        let mut accepted = vec![];
+        let results: Vec<Result<Submitted, BlockChainError>> = transactions
+            .into_iter()
+            .map(|(transaction, recorded)| {
+                if overdrawn.contains(&transaction.from_address) {
+                    return Err(BlockChainError::NotEnoughCoins);
+                }
+                let password = recorded
+                    .is_none()
+                    .then_some(transaction.from_password.as_str());
+                let (id, timestamp) = recorded.unzip();
+                let staged = stage_transaction(
+                    &transaction,
+                    password,
+                    id,
+                    timestamp.unwrap_or_else(unix_time),
+                    chain.len(),
+                    &staged_transactions,
+                    &staged_wallets,
+                )?;
+                let submitted =
+                    apply_staged(staged, &mut staged_transactions, &mut staged_wallets)?;
+                accepted.push(RecordedTransaction {
+                    transaction: AuthorisedTransaction::from(&transaction),
+                    id: submitted.details.id.clone(),
+                    timestamp: submitted.details.timestamp,
+                });
+                Ok(submitted)
+            })
+            .collect();
+
+        //AN ATOMIC BATCH IS ONLY APPLIED IF NOTHING WAS REJECTED
+        let rejected = results.iter().filter(|result| result.is_err()).count();
+        let applied = !accepted.is_empty() && (rejected == 0 || mode == BatchMode::BestEffort);
+        if applied {
+            self.append_to_log(Command::SubmitBatch {
+                transactions: accepted,
+            })?;
+            *pending_transactions = staged_transactions;
+            *wallets = staged_wallets;
+        }
+
+        let items = results
+            .into_iter()
+            .map(|result| match result {
+                Ok(submitted) if applied => {
+                    self.announce(&submitted, chain.len());
+                    BatchItem::accepted(submitted.details)
+                }
+                Ok(_) => BatchItem::not_applied(),
+                Err(error) => BatchItem::rejected(&error),
+            })
+            .collect();
+        info!(applied, rejected, "batch submitted");
+
+        Ok(items)
+    }
 
-        //SUBTRACT THE AMOUNT SEND FROM THE 'FROM WALLET
-        if from_wallet.address != MINING_ADDRESS.to_string() {
-            from_wallet.balance -= transaction.amount;
-            from_wallet.transactions.push(TransactionInfo {
-                from_address: from_wallet.address.clone(),
-                from_password: from_wallet.password.clone(),
-                to_address: to_wallet.address.clone(),
-                amount: transaction.amount,
+    //LET THE SUBSCRIBERS KNOW ABOUT THE NEW TRANSACTION, THE ONE IT REPLACED AND THE NEW BALANCE OF THE SENDER
+    //THE NEXT BLOCK IS AT THE GIVEN HEIGHT
+    fn announce(&self, submitted: &Submitted, height: usize) {
+        if let Some(replaced) = &submitted.replaced {
+            METRICS.record_eviction(EvictionReason::Replaced);
+            self.events.publish(Event::TransactionEvicted {
+                transaction: replaced.clone(),
+                reason: EvictionReason::Replaced,
             });
-            self.update_wallet(from_wallet.clone())?;
+            info!(id = %replaced.id, by = %submitted.details.id, "transaction replaced");
         }
 
-        //CREATE THE TRANSACTION AND ADD IT TO PENDING TRANSACTIONS
-        let new_transaction = Transaction::new(from_wallet, to_wallet, transaction.amount);
-        self.pending_transactions.push(new_transaction);
+        self.events.publish(Event::PendingTransaction {
+            transaction: submitted.details.clone(),
+        });
+        if let Some(sender) = &submitted.sender {
+            self.publish_balance(sender, height);
+        }
+        info!(id = %submitted.details.id, "transaction added to pending transactions");
+    }
 
-        Ok("Transaction successfully made".to_string())
+    //LET THE SUBSCRIBERS KNOW WHAT THE WALLET CAN SPEND NOW, THE NEXT BLOCK IS AT THE GIVEN HEIGHT
+    fn publish_balance(&self, wallet: &Wallet, height: usize) {
+        self.events.publish(Event::BalanceChanged {
+            address: wallet.address.clone(),
+            balance: wallet.balance(unix_time(), height).balance,
+        });
     }
 
     //CREATE A NEW  WALLET
-    pub fn create_wallet(&mut self, wallet: WalletInfo) -> Result<String, BlockChainError> {
+    pub fn create_wallet(&self, wallet: WalletInfo) -> Result<String, BlockChainError> {
+        self.add_wallet(wallet)?;
+
+        Ok("Wallet created!".to_string())
+    }
+
+    //CREATE A NEW WALLET AND RETURN ITS ADDRESS AND BALANCE
+    #[tracing::instrument(skip_all, fields(address = %wallet.address))]
+    pub fn add_wallet(&self, wallet: WalletInfo) -> Result<WalletBalance, BlockChainError> {
+        self.check_not_shutting_down()?;
+
         //CHECK IF THE WALLET INFORMATION ARE VALID
-        match wallet.check_wallet_info() {
-            Ok(_) => (),
-            Err(err) => return Err(err),
-        }
+        wallet.check_wallet_info()?;
+
+        let chain = self.chain.read().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
 
         //CHECK IF AN WALLET WITH THE SAME ADDRESS ALREADY EXISTS
-        match self.get_wallet(&wallet.address, AddressType::GENERIC) {
-            Ok(_) => return Err(BlockChainError::WalletAlreadyExists),
-            Err(_) => (),
+        if find_wallet(&wallets, &wallet.address, AddressType::GENERIC).is_ok() {
+            return Err(BlockChainError::WalletAlreadyExists);
         }
 
         //CREATE THE WALLET
-        let new_wallet = Wallet::new(wallet.address, wallet.balance as u32, wallet.password);
-        self.wallets.push(new_wallet);
+        let new_wallet = Wallet::new(
+            wallet.address.clone(),
+            wallet.balance as u32,
+            wallet.password.clone(),
+        );
+        let balance = new_wallet.balance(unix_time(), chain.len());
+        self.append_to_log(Command::CreateWallet(wallet))?;
+        wallets.push(new_wallet);
 
-        Ok("Wallet created!".to_string())
+        info!("wallet created");
+
+        Ok(balance)
     }
 
-    //RETURN WALLET BALLANCE BASED ON THE ADDRESS AND PASSWORD
+    //RETURN WALLET BALLANCE BASED ON THE ADDRESS AND PASSWORD, THE COINS THAT CAN BE SPENT AND THE ONES THAT ARE STILL LOCKED
     pub fn get_balance_of_wallet(
         &self,
-        address: &String,
-        password: &String,
-    ) -> Result<u32, BlockChainError> {
+        address: &str,
+        password: &str,
+    ) -> Result<WalletBalance, BlockChainError> {
+        //THE HEIGHT LOCKS ARE CHECKED AGAINST THE NEXT BLOCK, LIKE FOR A NEW TRANSACTION
+        let chain = self.chain.read().unwrap();
+
         //CHECK IF THE WALLET EXISTS
         let wallet = self.get_wallet(address, AddressType::GENERIC)?;
 
         //CHECK IF THE PASSWORD IS GOOD
-        if wallet.password != password.to_string() {
+        if wallet.password != password {
             return Err(BlockChainError::WrongPassword);
         }
 
         //RETURN THE BALANCE
-        Ok(wallet.balance)
+        Ok(wallet.balance(unix_time(), chain.len()))
+    }
+
+    //RETURN THE WALLET BASED ON ADDRESS
+    pub fn get_wallet(
+        &self,
+        address: &str,
+        address_type: AddressType,
+    ) -> Result<Wallet, BlockChainError> {
+        find_wallet(&self.wallets.read().unwrap(), address, address_type)
+    }
+
+    //ADD COINS TO AN WALLET
+    pub fn add_coins(&self, add_coins: WalletCoins) -> Result<String, BlockChainError> {
+        self.deposit_coins(add_coins)?;
+
+        Ok("Coins added!".to_string())
+    }
+
+    //ADD COINS TO AN WALLET AND RETURN ITS NEW BALANCE
+    #[tracing::instrument(skip_all, fields(address = %add_coins.address, coins = add_coins.coins))]
+    pub fn deposit_coins(&self, add_coins: WalletCoins) -> Result<WalletBalance, BlockChainError> {
+        self.deposit(
+            &add_coins.address,
+            Some(&add_coins.password),
+            add_coins.coins,
+        )
     }
 
-    //RETURN WALLET TRANSACTIONS BASED ON THE ADDRESS AND PASSWORD
-    pub fn get_transactions_of_wallet(
+    //ADD COINS TO AN WALLET. THE LOG DOESN'T KEEP THE PASSWORD, SO A REPLAYED DEPOSIT HAS NONE AND IS NOT AUTHORISED AGAIN
+    fn deposit(
         &self,
-        address: &String,
-        password: &String,
-    ) -> Result<Vec<TransactionInfo>, BlockChainError> {
+        address: &str,
+        password: Option<&str>,
+        coins: u32,
+    ) -> Result<WalletBalance, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        let chain = self.chain.read().unwrap();
+        let pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+
         //CHECK IF THE WALLET EXISTS
-        let wallet = self.get_wallet(address, AddressType::GENERIC)?;
+        let mut wallet = find_wallet(&wallets, address, AddressType::GENERIC)?;
 
-        //CHECK IF THE PASSWORD IS GOOD
-        if wallet.password != password.to_string() {
+        //CHECK IF THE PASSWORD IS CORRECT
+        if password.is_some_and(|password| wallet.password != password) {
             return Err(BlockChainError::WrongPassword);
         }
 
-        //RETURN THE TRANSACTIONS
-        Ok(wallet.transactions)
+        //CHECK IF THE WALLET CAN HOLD THE COINS
+        wallet.check_credit(
+            pending_transactions.pending_spend(&wallet.address),
+            coins as u64,
+        )?;
+
+        //UPDATE THE BALLANCE OF THE WALLET
+        wallet.balance += coins;
+        self.append_to_log(Command::AddCoins {
+            address: address.to_string(),
+            coins,
+        })?;
+        self.publish_balance(&wallet, chain.len());
+        let balance = wallet.balance(unix_time(), chain.len());
+        replace_wallet(&mut wallets, wallet)?;
+
+        info!("coins added");
+
+        Ok(balance)
     }
 
-    //RETURN THE WALLET BASED ON ADDRESS
-    pub fn get_wallet(
+    //CREATE A NEW MULTISIGNATURE WALLET WITHOUT COINS, ITS OWNERS MUST BE EXISTING WALLETS THAT ARE NOT MULTISIGNATURE WALLETS
+    #[tracing::instrument(skip_all, fields(address = %wallet.address, owners = wallet.owners.len(), threshold = wallet.threshold))]
+    pub fn create_multisig_wallet(
         &self,
-        address: &String,
-        address_type: AddressType,
-    ) -> Result<Wallet, BlockChainError> {
-        //INITIALIZE THE WALLET WITH NONE
-        let mut wallet = None;
+        wallet: MultisigWalletInfo,
+    ) -> Result<MultisigWalletDetails, BlockChainError> {
+        self.check_not_shutting_down()?;
 
-        //CHECK IF AN WALLET WITH THE GIVEN ADDRESS EXISTS
-        for current_wallet in self.wallets.clone() {
-            if current_wallet.address == address.to_string() {
-                wallet = Some(current_wallet);
-            }
+        //CHECK IF THE WALLET INFORMATION ARE VALID
+        wallet.check_multisig_wallet_info()?;
+
+        let chain = self.chain.read().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+
+        //CHECK IF AN WALLET WITH THE SAME ADDRESS ALREADY EXISTS
+        if find_wallet(&wallets, &wallet.address, AddressType::GENERIC).is_ok() {
+            return Err(BlockChainError::WalletAlreadyExists);
         }
 
-        //IF AN WALLET WITH THE GIVEN ADDRESS WAS FOUND IS RETURNED, IF NOT AN ERROR IS RETURNED
-        match wallet {
-            Some(wallet) => Ok(wallet),
-            None => match address_type {
-                AddressType::TO => return Err(BlockChainError::InvalidToAddress),
-                AddressType::FROM => return Err(BlockChainError::InvalidFromAddress),
-                AddressType::REWARD => return Err(BlockChainError::InvalidRewardAddress),
-                AddressType::GENERIC => return Err(BlockChainError::WalletNotFound),
-            },
+        //THE OWNERS APPROVE WITH THE PASSWORDS OF THEIR OWN WALLETS
+        let valid_owner = |owner: &String| {
+            wallets
+                .iter()
+                .any(|other| other.address == *owner && other.multisig.is_none())
+        };
+        if !wallet.owners.iter().all(valid_owner) {
+            return Err(BlockChainError::InvalidMultisig);
         }
+
+        //CREATE THE WALLET
+        let mut new_wallet = Wallet::new(wallet.address.clone(), 0, wallet.password.clone());
+        new_wallet.multisig = Some(Multisig {
+            owners: wallet.owners.clone(),
+            threshold: wallet.threshold,
+        });
+        let details = MultisigWalletDetails::of(&new_wallet, unix_time(), chain.len())
+            .ok_or(BlockChainError::NotMultisigWallet)?;
+        self.append_to_log(Command::CreateMultisigWallet(wallet))?;
+        wallets.push(new_wallet);
+
+        info!("multisignature wallet created");
+
+        Ok(details)
     }
 
-    //ADD COINS TO AN WALLET
-    pub fn add_coins(&mut self, add_coins: WalletCoins) -> Result<String, BlockChainError> {
-        //CHECK IF THE WALLET EXISTS
-        let mut wallet = self.get_wallet(&add_coins.address, AddressType::GENERIC)?;
+    //PROPOSE A TRANSFER FROM A MULTISIGNATURE WALLET AS ONE OF ITS OWNERS, THE PROPOSAL COUNTS AS THE APPROVAL OF THAT OWNER
+    //WHEN ONE APPROVAL IS ENOUGH THE TRANSFER IS ADDED TO PENDING TRANSACTIONS RIGHT AWAY
+    #[tracing::instrument(
+        skip(self, proposal),
+        fields(owner = %proposal.owner, to = %proposal.to_address, amount = proposal.amount)
+    )]
+    pub fn propose_transfer(
+        &self,
+        address: &str,
+        proposal: ProposalInfo,
+    ) -> Result<Proposal, BlockChainError> {
+        self.propose(address, &proposal, Some(&proposal.password), None)
+    }
 
-        //CHECK IF THE PASSWORD IS CORRECT
-        if wallet.password != add_coins.password.to_string() {
+    //CREATE A PROPOSAL. WHEN REPLAYING A LOG IT GETS ITS RECORDED ID AND TIMESTAMP, AND ITS TRANSACTION THE RECORDED ID
+    //THE LOG DOESN'T KEEP THE PASSWORD, SO A REPLAYED PROPOSAL HAS NONE AND ITS OWNER IS NOT AUTHENTICATED AGAIN
+    fn propose(
+        &self,
+        address: &str,
+        info: &ProposalInfo,
+        password: Option<&str>,
+        recorded: Option<(String, u64, Option<String>)>,
+    ) -> Result<Proposal, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        let chain = self.chain.read().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+        let mut proposals = self.proposals.write().unwrap();
+
+        let (wallet, multisig) = find_multisig_wallet(&wallets, address)?;
+        if let Some(password) = password {
+            authenticate_owner(&multisig, &wallets, &info.owner, password)?;
+        }
+
+        let replaying = recorded.is_some();
+        let (id, timestamp, transaction_id) = match recorded {
+            Some((id, timestamp, transaction_id)) => (Some(id), timestamp, transaction_id),
+            None => (None, unix_time(), None),
+        };
+        let mut proposal = Proposal::new(&wallet, multisig.threshold, info, timestamp)?;
+        if let Some(id) = id {
+            proposal.id = id;
+        }
+
+        //THE TRANSFER IS CHECKED NOW, SO THE OWNERS DON'T APPROVE A TRANSFER THAT CAN NEVER BE ADDED
+        let transaction = proposal.transaction(&wallet);
+        transaction.check_transaction_info()?;
+        find_wallet(&wallets, &transaction.to_address, AddressType::TO)?;
+
+        let staged = stage_proposal(
+            &proposal,
+            &wallet,
+            transaction_id.clone(),
+            chain.len(),
+            &pending_transactions,
+            &wallets,
+        )?;
+        //A REPLAYED PROPOSAL MUST BE EXECUTED BY THE SAME COMMAND AS WHEN IT WAS RECORDED
+        if replaying && transaction_id.is_some() != staged.is_some() {
+            return Err(BlockChainError::ReplayMismatch);
+        }
+        self.append_to_log(Command::ProposeTransfer {
+            wallet: address.to_string(),
+            proposal: AuthorisedProposal::from(info),
+            id: proposal.id.clone(),
+            timestamp,
+            transaction_id: staged.as_ref().map(|staged| staged.transaction.id.clone()),
+        })?;
+
+        if let Some(staged) = staged {
+            self.execute_proposal(
+                &mut proposal,
+                staged,
+                &mut pending_transactions,
+                &mut wallets,
+                chain.len(),
+            )?;
+        }
+        proposals.push(proposal.clone());
+        info!(id = %proposal.id, "transfer proposed");
+
+        Ok(proposal)
+    }
+
+    //APPROVE A PROPOSAL AS ONE OF THE OWNERS OF ITS WALLET, THE APPROVAL THAT REACHES THE THRESHOLD ADDS THE TRANSFER TO PENDING TRANSACTIONS
+    //IF THE TRANSFER CAN'T BE ADDED, FOR EXAMPLE BECAUSE THE WALLET DOESN'T HAVE ENOUGH COINS, THE APPROVAL IS NOT RECORDED
+    #[tracing::instrument(skip(self, credentials), fields(owner = %credentials.owner))]
+    pub fn approve_proposal(
+        &self,
+        id: &str,
+        credentials: OwnerCredentials,
+    ) -> Result<Proposal, BlockChainError> {
+        self.approve(id, &credentials.owner, Some(&credentials.password), None)
+    }
+
+    //APPROVE A PROPOSAL. WHEN REPLAYING A LOG THE APPROVAL GETS ITS RECORDED TIMESTAMP AND THE TRANSACTION ITS RECORDED ID
+    //THE LOG DOESN'T KEEP THE PASSWORD, SO A REPLAYED APPROVAL HAS NONE AND ITS OWNER IS NOT AUTHENTICATED AGAIN
+    fn approve(
+        &self,
+        id: &str,
+        owner: &str,
+        password: Option<&str>,
+        recorded: Option<(u64, Option<String>)>,
+    ) -> Result<Proposal, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        let chain = self.chain.read().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+        let mut proposals = self.proposals.write().unwrap();
+
+        let position = proposals
+            .iter()
+            .position(|proposal| proposal.id == id)
+            .ok_or(BlockChainError::ProposalNotFound)?;
+        let mut proposal = proposals[position].clone();
+        let (wallet, multisig) = find_multisig_wallet(&wallets, &proposal.wallet)?;
+        if let Some(password) = password {
+            authenticate_owner(&multisig, &wallets, owner, password)?;
+        }
+
+        //THE EXPIRY IS CHECKED AT THE TIME OF THE APPROVAL, SO A REPLAY GIVES THE SAME RESULT
+        let replaying = recorded.is_some();
+        let (timestamp, transaction_id) = recorded.unwrap_or_else(|| (unix_time(), None));
+        if proposal.status != ProposalStatus::Pending {
+            return Err(BlockChainError::ProposalNotPending);
+        }
+        if proposal.is_expired(timestamp) {
+            return Err(BlockChainError::ProposalExpired);
+        }
+        if proposal.is_approved_by(owner) {
+            return Err(BlockChainError::AlreadyApproved);
+        }
+        proposal.approvals.push(Approval {
+            owner: owner.to_string(),
+            timestamp,
+        });
+
+        let staged = stage_proposal(
+            &proposal,
+            &wallet,
+            transaction_id.clone(),
+            chain.len(),
+            &pending_transactions,
+            &wallets,
+        )?;
+        //A REPLAYED PROPOSAL MUST BE EXECUTED BY THE SAME COMMAND AS WHEN IT WAS RECORDED
+        if replaying && transaction_id.is_some() != staged.is_some() {
+            return Err(BlockChainError::ReplayMismatch);
+        }
+        self.append_to_log(Command::ApproveProposal {
+            id: id.to_string(),
+            owner: owner.to_string(),
+            timestamp,
+            transaction_id: staged.as_ref().map(|staged| staged.transaction.id.clone()),
+        })?;
+
+        if let Some(staged) = staged {
+            self.execute_proposal(
+                &mut proposal,
+                staged,
+                &mut pending_transactions,
+                &mut wallets,
+                chain.len(),
+            )?;
+        }
+        proposals[position] = proposal.clone();
+        info!(
+            id,
+            approvals = proposal.approvals.len(),
+            threshold = proposal.threshold,
+            "proposal approved"
+        );
+
+        Ok(proposal)
+    }
+
+    //ADD THE TRANSACTION OF A PROPOSAL THAT REACHED ITS THRESHOLD TO PENDING TRANSACTIONS
+    fn execute_proposal(
+        &self,
+        proposal: &mut Proposal,
+        staged: StagedTransaction,
+        pending_transactions: &mut Mempool,
+        wallets: &mut [Wallet],
+        height: usize,
+    ) -> Result<(), BlockChainError> {
+        let submitted = apply_staged(staged, pending_transactions, wallets)?;
+        proposal.status = ProposalStatus::Executed;
+        proposal.transaction_id = Some(submitted.details.id.clone());
+        self.announce(&submitted, height);
+        info!(id = %proposal.id, transaction = %submitted.details.id, "proposal executed");
+
+        Ok(())
+    }
+
+    //RETURN A PROPOSAL TO ONE OF THE OWNERS OF ITS WALLET
+    pub fn get_proposal(
+        &self,
+        id: &str,
+        credentials: &OwnerCredentials,
+    ) -> Result<Proposal, BlockChainError> {
+        let wallets = self.wallets.read().unwrap();
+        let proposals = self.proposals.read().unwrap();
+
+        let proposal = proposals
+            .iter()
+            .find(|proposal| proposal.id == id)
+            .ok_or(BlockChainError::ProposalNotFound)?;
+        let (_, multisig) = find_multisig_wallet(&wallets, &proposal.wallet)?;
+        authenticate_owner(
+            &multisig,
+            &wallets,
+            &credentials.owner,
+            &credentials.password,
+        )?;
+
+        Ok(proposal.as_of(unix_time()))
+    }
+
+    //RETURN THE PROPOSALS OF A MULTISIGNATURE WALLET TO ONE OF ITS OWNERS, THE OLDEST FIRST
+    pub fn get_proposals(
+        &self,
+        address: &str,
+        credentials: &OwnerCredentials,
+    ) -> Result<Vec<Proposal>, BlockChainError> {
+        let wallets = self.wallets.read().unwrap();
+        let proposals = self.proposals.read().unwrap();
+
+        let (_, multisig) = find_multisig_wallet(&wallets, address)?;
+        authenticate_owner(
+            &multisig,
+            &wallets,
+            &credentials.owner,
+            &credentials.password,
+        )?;
+
+        let now = unix_time();
+        Ok(proposals
+            .iter()
+            .filter(|proposal| proposal.wallet == address)
+            .map(|proposal| proposal.as_of(now))
+            .collect())
+    }
+
+    //CREATE A STANDING ORDER AUTHORISED WITH THE PASSWORD OF THE PAYER, ITS PAYMENTS ARE ADDED TO PENDING TRANSACTIONS WHEN THEY ARE DUE
+    #[tracing::instrument(
+        skip_all,
+        fields(from = %order.from_address, to = %order.to_address, amount = order.amount, interval = order.interval)
+    )]
+    pub fn create_standing_order(
+        &self,
+        order: StandingOrderInfo,
+    ) -> Result<StandingOrder, BlockChainError> {
+        self.schedule(&order, Some(&order.password), None)
+    }
+
+    //CREATE A STANDING ORDER. WHEN REPLAYING A LOG IT GETS ITS RECORDED ID AND TIMESTAMP
+    //THE LOG DOESN'T KEEP THE PASSWORD, SO A REPLAYED STANDING ORDER HAS NONE AND IS NOT AUTHORISED AGAIN
+    fn schedule(
+        &self,
+        info: &StandingOrderInfo,
+        password: Option<&str>,
+        recorded: Option<(String, u64)>,
+    ) -> Result<StandingOrder, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        //THE WALLETS ARE ONLY READ, THE COMMANDS ON THE STANDING ORDERS ARE LOGGED IN ORDER UNDER THEIR OWN LOCK
+        let wallets = self.wallets.read().unwrap();
+        let mut standing_orders = self.standing_orders.write().unwrap();
+
+        let payer = find_wallet(&wallets, &info.from_address, AddressType::FROM)?;
+        if password.is_some_and(|password| payer.password != password) {
             return Err(BlockChainError::WrongPassword);
         }
+        if payer.multisig.is_some() {
+            return Err(BlockChainError::MultisigApprovalRequired);
+        }
 
-        //CHECK THE AMOUNT TO BE ADDED
-        #[allow(unused_comparisons)]
-        if add_coins.coins < 0 {
-            return Err(BlockChainError::NegativeAmount);
+        let (id, timestamp) = recorded.unzip();
+        let mut order = StandingOrder::new(info, timestamp.unwrap_or_else(unix_time))?;
+        if let Some(id) = id {
+            order.id = id;
         }
 
-        //UPDATE THE BALLANCE OF THE WALLET
-        wallet.balance += add_coins.coins;
-        self.update_wallet(wallet)?;
+        //THE PAYMENT IS CHECKED NOW, SO THE ORDER IS NOT CREATED FOR A PAYMENT THAT CAN NEVER BE ADDED
+        order.transaction(&payer).check_transaction_info()?;
+        find_wallet(&wallets, &order.to_address, AddressType::TO)?;
 
-        Ok("Coins added!".to_string())
+        self.append_to_log(Command::CreateStandingOrder {
+            order: AuthorisedStandingOrder::from(info),
+            id: order.id.clone(),
+            timestamp: order.created_at,
+        })?;
+        standing_orders.push(order.clone());
+        info!(id = %order.id, first_payment = order.next_payment_at, "standing order created");
+
+        Ok(order)
+    }
+
+    //RETURN THE STANDING ORDERS PAID FROM A WALLET TO ITS OWNER, THE OLDEST FIRST
+    pub fn get_standing_orders(
+        &self,
+        address: &str,
+        password: &str,
+    ) -> Result<Vec<StandingOrder>, BlockChainError> {
+        let wallets = self.wallets.read().unwrap();
+        let standing_orders = self.standing_orders.read().unwrap();
+
+        let wallet = find_wallet(&wallets, address, AddressType::GENERIC)?;
+        if wallet.password != password {
+            return Err(BlockChainError::WrongPassword);
+        }
+
+        Ok(standing_orders
+            .iter()
+            .filter(|order| order.from_address == address)
+            .cloned()
+            .collect())
+    }
+
+    //REVOKE A STANDING ORDER WITH THE PASSWORD OF THE PAYER, NO PAYMENT IS ADDED AFTER IT
+    //THE PAYMENTS THAT ARE ALREADY PENDING STAY, THEY CAN BE CANCELLED LIKE ANY OTHER TRANSACTION
+    #[tracing::instrument(skip(self, password))]
+    pub fn revoke_standing_order(
+        &self,
+        id: &str,
+        password: &str,
+    ) -> Result<StandingOrder, BlockChainError> {
+        self.revoke(id, Some(password), None)
+    }
+
+    //REVOKE A STANDING ORDER. WHEN REPLAYING A LOG IT IS REVOKED AT THE RECORDED TIME
+    //THE LOG DOESN'T KEEP THE PASSWORD, SO A REPLAYED REVOCATION HAS NONE AND IS NOT AUTHORISED AGAIN
+    fn revoke(
+        &self,
+        id: &str,
+        password: Option<&str>,
+        recorded: Option<u64>,
+    ) -> Result<StandingOrder, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        let wallets = self.wallets.read().unwrap();
+        let mut standing_orders = self.standing_orders.write().unwrap();
+
+        let order = standing_orders
+            .iter_mut()
+            .find(|order| order.id == id)
+            .ok_or(BlockChainError::StandingOrderNotFound)?;
+        if let Some(password) = password {
+            let payer = find_wallet(&wallets, &order.from_address, AddressType::FROM)?;
+            if payer.password != password {
+                return Err(BlockChainError::WrongPassword);
+            }
+        }
+        if order.status != StandingOrderStatus::Active {
+            return Err(BlockChainError::StandingOrderNotActive);
+        }
+
+        self.append_to_log(Command::RevokeStandingOrder {
+            id: id.to_string(),
+            timestamp: recorded.unwrap_or_else(unix_time),
+        })?;
+        order.status = StandingOrderStatus::Revoked;
+        info!(id, payments = order.payments, "standing order revoked");
+
+        Ok(order.clone())
+    }
+
+    //ADD THE PAYMENTS OF THE STANDING ORDERS THAT ARE DUE TO PENDING TRANSACTIONS, AT MOST ONE PER ORDER EACH TIME
+    //THE RUNS OVERDUE BY MORE THAN ONE INTERVAL, E.G. WHILE THE NODE WAS DOWN, ARE NOT CAUGHT UP BUT COUNTED AS MISSED
+    //A PAYMENT THE PAYER CAN'T PAY IS MISSED, THE ORDER GOES ON WITH THE NEXT ONE. RETURN HOW MANY PAYMENTS WERE ADDED
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn pay_standing_orders(&self) -> Result<usize, BlockChainError> {
+        self.pay(None)
+    }
+
+    //PAY THE STANDING ORDERS THAT ARE DUE. WHEN REPLAYING A LOG THEY ARE PAID AT THE RECORDED TIME, AND THE PAYMENTS GET THEIR RECORDED IDS
+    fn pay(
+        &self,
+        recorded: Option<(u64, Vec<ScheduledPayment>)>,
+    ) -> Result<usize, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        let chain = self.chain.read().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+        let mut standing_orders = self.standing_orders.write().unwrap();
+
+        let replaying = recorded.is_some();
+        let (timestamp, recorded_payments) = recorded.unwrap_or_else(|| (unix_time(), vec![]));
+        if !standing_orders.iter().any(|order| order.is_due(timestamp)) {
+            return match replaying {
+                true => Err(BlockChainError::ReplayMismatch),
+                false => Ok(0),
+            };
+        }
+
+        //THE PAYMENTS ARE ADDED TO COPIES OF THE ORDERS, THE PENDING TRANSACTIONS AND THE WALLETS, ONE AFTER THE OTHER
+        //NOTHING CHANGES UNTIL THEY ARE WRITTEN TO THE LOG
+        let mut staged_orders = standing_orders.clone();
+        let mut staged_transactions = pending_transactions.clone();
+        let mut staged_wallets = wallets.clone();
+        let mut recorded_payments = recorded_payments.into_iter();
+        let mut payments = vec![];
+        let mut submitted = vec![];
+        for order in staged_orders.iter_mut() {
+            //THE SKIPPED RUNS DEPEND ONLY ON THE TIME, SO A REPLAY SKIPS THE SAME ONES WITHOUT RECORDING THEM
+            let skipped = order.skip_overdue(timestamp);
+            if skipped > 0 {
+                warn!(id = %order.id, skipped, "overdue standing order runs skipped");
+            }
+            if order.is_due(timestamp) {
+                let recorded_payment = recorded_payments.next();
+                if replaying
+                    && recorded_payment.as_ref().is_none_or(|payment| {
+                        payment.order != order.id || payment.due_at != order.next_payment_at
+                    })
+                {
+                    return Err(BlockChainError::ReplayMismatch);
+                }
+                let transaction_id = recorded_payment.and_then(|payment| payment.transaction_id);
+
+                //THE PAYMENT IS CHECKED LIKE ANY OTHER TRANSACTION OF THE PAYER, AT THE TIME IT IS MADE
+                //THE PAYER AUTHORISED IT WHEN IT CREATED THE ORDER
+                let result = find_wallet(&staged_wallets, &order.from_address, AddressType::FROM)
+                    .and_then(|payer| {
+                        stage_transaction(
+                            &order.transaction(&payer),
+                            None,
+                            transaction_id.clone(),
+                            timestamp,
+                            chain.len(),
+                            &staged_transactions,
+                            &staged_wallets,
+                        )
+                    })
+                    .and_then(|staged| {
+                        apply_staged(staged, &mut staged_transactions, &mut staged_wallets)
+                    });
+                let paid = match result {
+                    Ok(payment) => {
+                        order.payments += 1;
+                        order.transaction_id = Some(payment.details.id.clone());
+                        let id = payment.details.id.clone();
+                        submitted.push(payment);
+                        Some(id)
+                    }
+                    Err(error) => {
+                        order.missed += 1;
+                        warn!(id = %order.id, %error, "standing order payment missed");
+                        None
+                    }
+                };
+
+                //A REPLAYED PAYMENT MUST BE ADDED OR MISSED LIKE WHEN IT WAS RECORDED
+                if replaying && transaction_id.is_some() != paid.is_some() {
+                    return Err(BlockChainError::ReplayMismatch);
+                }
+                payments.push(ScheduledPayment {
+                    order: order.id.clone(),
+                    due_at: order.next_payment_at,
+                    transaction_id: paid,
+                });
+                order.advance();
+            }
+        }
+        if recorded_payments.next().is_some() {
+            return Err(BlockChainError::ReplayMismatch);
+        }
+
+        self.append_to_log(Command::PayStandingOrders {
+            timestamp,
+            payments,
+        })?;
+        *pending_transactions = staged_transactions;
+        *wallets = staged_wallets;
+        *standing_orders = staged_orders;
+
+        for payment in &submitted {
+            self.announce(payment, chain.len());
+        }
+        info!(payments = submitted.len(), "standing orders paid");
+
+        Ok(submitted.len())
+    }
+
+    //RETURN THE BLOCK FOUND AT THE GIVEN HEIGHT
+    pub fn get_block(&self, height: usize) -> Result<BlockDetails, BlockChainError> {
+        match self.chain.read().unwrap().get(height) {
+            Some(block) => Ok(block.details()),
+            None => Err(BlockChainError::BlockNotFound),
+        }
     }
 
-    //UPDATE THE WALLET FROM THE BLOCKCHAIN OR RETURN AN ERROR IF IS NOT FOUND
-    pub fn update_wallet(&mut self, wallet: Wallet) -> Result<(), BlockChainError> {
+    //RETURN THE BLOCK WITH THE GIVEN HASH
+    pub fn get_block_by_hash(&self, hash: &str) -> Result<BlockDetails, BlockChainError> {
         match self
-            .wallets
+            .chain
+            .read()
+            .unwrap()
             .iter()
-            .position(|w| *w.address == wallet.address)
+            .rev()
+            .find(|block| block.hash == hash)
         {
-            Some(index) => self.wallets[index] = wallet,
-            None => return Err(BlockChainError::WalletNotFound),
+            Some(block) => Ok(block.details()),
+            None => Err(BlockChainError::BlockNotFound),
         }
+    }
 
-        Ok(())
+    //RETURN THE LAST BLOCK FROM THE CHAIN
+    pub fn get_latest_block(&self) -> Result<BlockDetails, BlockChainError> {
+        match self.chain.read().unwrap().last() {
+            Some(block) => Ok(block.details()),
+            None => Err(BlockChainError::ChainIsEmpty),
+        }
+    }
+
+    //RETURN A PAGE OF BLOCKS THAT MATCH THE FILTERS FROM THE QUERY
+    pub fn list_blocks(&self, query: &BlockQuery) -> Result<Page<BlockDetails>, BlockChainError> {
+        //CHECK IF THE RANGE IS VALID
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if from > to {
+                return Err(BlockChainError::InvalidBlockRange);
+            }
+        }
+
+        let chain = self.chain.read().unwrap();
+        let notes = query.notes();
+        let filters_transactions = query.min_amount.is_some()
+            || query.max_amount.is_some()
+            || query.address.is_some()
+            || !notes.is_empty();
+
+        let blocks = chain
+            .iter()
+            .filter(|block| query.from.is_none_or(|from| block.index >= from))
+            .filter(|block| query.to.is_none_or(|to| block.index <= to))
+            .filter(|block| query.from_time.is_none_or(|from| block.timestamp >= from))
+            .filter(|block| query.to_time.is_none_or(|to| block.timestamp <= to))
+            .filter(|block| {
+                !filters_transactions
+                    || block.transactions.iter().any(|transaction| {
+                        query.min_amount.is_none_or(|min| transaction.amount >= min)
+                            && query.max_amount.is_none_or(|max| transaction.amount <= max)
+                            && query
+                                .address
+                                .as_ref()
+                                .is_none_or(|address| transaction.touches(address))
+                            && notes.matches(transaction.memo.as_deref(), &transaction.metadata)
+                    })
+            })
+            .map(|block| (block.index, block));
+
+        let page = paginate(
+            blocks,
+            query.order.unwrap_or_default(),
+            query.cursor.as_deref(),
+            query.limit,
+        )?;
+
+        Ok(Page {
+            items: page.items.into_iter().map(Block::details).collect(),
+            next_cursor: page.next_cursor,
+        })
     }
+
+    //RETURN A PAGE OF TRANSACTIONS THAT SENT OR RECEIVED COINS FROM THE WALLET AND MATCH THE FILTERS FROM THE QUERY
+    pub fn get_wallet_history(
+        &self,
+        address: &str,
+        password: &str,
+        query: &HistoryQuery,
+    ) -> Result<Page<HistoryEntry>, BlockChainError> {
+        //CHECK IF THE WALLET EXISTS AND THE PASSWORD IS GOOD
+        let wallet = self.get_wallet(address, AddressType::GENERIC)?;
+        if wallet.password != password {
+            return Err(BlockChainError::WrongPassword);
+        }
+
+        let chain = self.chain.read().unwrap();
+        let index = self.index.read().unwrap();
+        let pending_transactions = self.pending_transactions.lock().unwrap();
+        let order = query.order.unwrap_or_default();
+
+        //FIND THE CURRENT POSITION OF THE CURSOR. A PENDING TRANSACTION THAT WAS MINED SINCE IS FOUND IN ITS BLOCK
+        //ONE THAT WAS EVICTED CAN'T BE FOUND, THE PAGE THEN STARTS FROM ITS OLD PLACE IN PENDING TRANSACTIONS
+        let cursor = match query
+            .cursor
+            .as_deref()
+            .map(HistoryCursor::decode)
+            .transpose()?
+        {
+            Some(HistoryCursor::Mined(position)) => Some(position),
+            Some(HistoryCursor::Pending { index: old, id }) => Some(
+                pending_transactions
+                    .iter()
+                    .position(|transaction| transaction.id == id)
+                    .map(HistoryPosition::pending)
+                    .or_else(|| {
+                        let block_height = index.block_height(&id)?;
+                        let index = chain[block_height]
+                            .transactions
+                            .iter()
+                            .position(|transaction| transaction.id == id)?;
+                        Some(HistoryPosition {
+                            block_height,
+                            index,
+                        })
+                    })
+                    .unwrap_or(HistoryPosition::pending(old)),
+            ),
+            None => None,
+        };
+
+        //ONLY THE INDEXED POSITIONS AFTER THE CURSOR ARE VISITED
+        let mut positions = index.positions(address);
+        if let Some(cursor) = cursor {
+            positions = match order {
+                SortOrder::Asc => &positions[positions.partition_point(|p| *p <= cursor)..],
+                SortOrder::Desc => &positions[..positions.partition_point(|p| *p < cursor)],
+            };
+        }
+
+        //THE MINED TRANSACTIONS ARE FOLLOWED BY THE PENDING ONES
+        let mined = positions.iter().map(|position| {
+            let block = &chain[position.block_height];
+            (
+                *position,
+                &block.transactions[position.index],
+                TransactionState::Confirmed,
+                block.timestamp,
+            )
+        });
+        let pending = pending_transactions
+            .iter()
+            .enumerate()
+            .filter(|(_, transaction)| transaction.touches(address))
+            .map(|(index, transaction)| {
+                (
+                    HistoryPosition::pending(index),
+                    transaction,
+                    TransactionState::Pending,
+                    transaction.timestamp,
+                )
+            });
+
+        let entries = mined
+            .chain(pending)
+            .map(|(position, transaction, status, timestamp)| {
+                let direction = if transaction.from_wallet.address == address {
+                    Direction::Out
+                } else {
+                    Direction::In
+                };
+                let entry = HistoryEntry {
+                    transaction: transaction.details(),
+                    direction,
+                    status,
+                    block_height: (status == TransactionState::Confirmed)
+                        .then_some(position.block_height),
+                    timestamp,
+                };
+                (position, entry)
+            })
+            .filter(|(_, entry)| history_entry_matches(entry, query));
+
+        let cursor = cursor.map(|position| position.encode());
+        let page = paginate(entries, order, cursor.as_deref(), query.limit)?;
+
+        //THE CURSOR OF A PENDING TRANSACTION KEEPS ITS ID
+        let next_cursor = page
+            .next_cursor
+            .map(|cursor| {
+                let position = HistoryPosition::decode(&cursor)?;
+                let cursor = match pending_transactions.iter().nth(position.index) {
+                    Some(transaction) if position.block_height == usize::MAX => {
+                        HistoryCursor::Pending {
+                            index: position.index,
+                            id: transaction.id.clone(),
+                        }
+                    }
+                    _ => HistoryCursor::Mined(position),
+                };
+                Ok::<_, BlockChainError>(cursor.encode())
+            })
+            .transpose()?;
+
+        Ok(Page {
+            items: page.items,
+            next_cursor,
+        })
+    }
+
+    //RETURN THE TRANSACTION WITH THE GIVEN ID, THE BLOCK WHERE IT WAS MINED AND THE NUMBER OF CONFIRMATIONS
+    pub fn get_transaction(&self, id: &str) -> Result<TransactionStatus, BlockChainError> {
+        let chain = self.chain.read().unwrap();
+
+        //SEARCH THE TRANSACTION IN THE MINED BLOCKS
+        if let Some(height) = self.index.read().unwrap().block_height(id) {
+            let block = &chain[height];
+            let transaction = block
+                .transactions
+                .iter()
+                .find(|transaction| transaction.id == id)
+                .ok_or(BlockChainError::TransactionNotFound)?;
+
+            return Ok(TransactionStatus {
+                transaction: transaction.details(),
+                status: "confirmed".to_string(),
+                block_height: Some(height),
+                confirmations: chain.len() - height,
+                eviction_reason: None,
+            });
+        }
+
+        //SEARCH THE TRANSACTION IN THE PENDING TRANSACTIONS AND THEN IN THE EVICTED ONES
+        let pending_transactions = self.pending_transactions.lock().unwrap();
+        if let Some(transaction) = pending_transactions
+            .iter()
+            .find(|transaction| transaction.id == id)
+        {
+            return Ok(TransactionStatus {
+                transaction: transaction.details(),
+                status: "pending".to_string(),
+                block_height: None,
+                confirmations: 0,
+                eviction_reason: None,
+            });
+        }
+
+        match pending_transactions.eviction(id) {
+            Some(eviction) => Ok(TransactionStatus {
+                transaction: eviction.transaction.clone(),
+                status: "evicted".to_string(),
+                block_height: None,
+                confirmations: 0,
+                eviction_reason: Some(eviction.reason),
+            }),
+            None => Err(BlockChainError::TransactionNotFound),
+        }
+    }
+
+    //CANCEL A PENDING TRANSACTION, ONLY THE SENDER CAN DO IT WITH THE PASSWORD OF ITS WALLET. THE COINS TAKEN FOR IT GO BACK TO THE SENDER
+    //THE BLOCK THAT IS BEING MINED IS WAITED FOR, A TRANSACTION THAT WAS MINED IN IT CAN NO LONGER BE CANCELLED
+    #[tracing::instrument(skip(self, password))]
+    pub fn cancel_transaction(
+        &self,
+        id: &str,
+        password: &str,
+    ) -> Result<TransactionStatus, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        let _mining = self.mining.lock().unwrap();
+        let chain = self.chain.read().unwrap();
+        let index = self.index.read().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+
+        let Some(transaction) = pending_transactions
+            .iter()
+            .find(|transaction| transaction.id == id)
+        else {
+            let known =
+                index.block_height(id).is_some() || pending_transactions.eviction(id).is_some();
+            return match known {
+                true => Err(BlockChainError::TransactionNotPending),
+                false => Err(BlockChainError::TransactionNotFound),
+            };
+        };
+
+        //THE MINER REWARDS HAVE NO WALLET TO CANCEL THEM WITH
+        let sender = find_wallet(
+            &wallets,
+            &transaction.from_wallet.address,
+            AddressType::FROM,
+        )?;
+        if sender.password != password {
+            return Err(BlockChainError::WrongPassword);
+        }
+        //THE PASSWORD OF A MULTISIGNATURE WALLET CAN'T UNDO WHAT ITS OWNERS APPROVED
+        if sender.multisig.is_some() {
+            return Err(BlockChainError::MultisigApprovalRequired);
+        }
+
+        let eviction = Eviction {
+            transaction: transaction.details(),
+            reason: EvictionReason::Cancelled,
+        };
+        check_refunds(&pending_transactions, &wallets, slice::from_ref(&eviction))?;
+        self.append_to_log(Command::EvictTransactions {
+            evictions: vec![eviction.clone()],
+        })?;
+        self.evict_transactions(
+            &mut pending_transactions,
+            &mut wallets,
+            slice::from_ref(&eviction),
+            chain.len(),
+        )?;
+        info!(id, "transaction cancelled");
+
+        Ok(TransactionStatus {
+            transaction: eviction.transaction,
+            status: "evicted".to_string(),
+            block_height: None,
+            confirmations: 0,
+            eviction_reason: Some(eviction.reason),
+        })
+    }
+
+    //CLAIM THE COINS OF A MINED ESCROW AS ITS RECIPIENT WITH THE PASSWORD OF ITS WALLET AND THE PREIMAGE OF THE HASH
+    //THE CLAIM IS ADDED TO PENDING TRANSACTIONS AND CREDITS THE RECIPIENT WHEN IT IS MINED, THE PREIMAGE IS THEN PUBLIC
+    #[tracing::instrument(skip(self, claim))]
+    pub fn claim_escrow(
+        &self,
+        id: &str,
+        claim: ClaimInfo,
+    ) -> Result<TransactionDetails, BlockChainError> {
+        self.settle(id, Some(&claim.password), Some(claim.preimage), None)
+    }
+
+    //GIVE THE COINS OF A MINED ESCROW BACK TO ITS SENDER, WITH THE PASSWORD OF ITS WALLET, ONCE THE TIMEOUT HAS PASSED
+    //THE REFUND IS ADDED TO PENDING TRANSACTIONS AND CREDITS THE SENDER WHEN IT IS MINED
+    #[tracing::instrument(skip(self, password))]
+    pub fn refund_escrow(
+        &self,
+        id: &str,
+        password: &str,
+    ) -> Result<TransactionDetails, BlockChainError> {
+        self.settle(id, Some(password), None, None)
+    }
+
+    //ADD THE CLAIM, WITH A PREIMAGE, OR THE REFUND, WITHOUT ONE, OF AN ESCROW TO PENDING TRANSACTIONS
+    //WHEN REPLAYING A LOG IT GETS ITS RECORDED ID AND TIMESTAMP, THE TIMEOUT IS CHECKED AT THAT TIME
+    //THE LOG DOESN'T KEEP THE PASSWORD, SO A REPLAYED SETTLEMENT HAS NONE AND IS NOT AUTHORISED AGAIN
+    fn settle(
+        &self,
+        escrow_id: &str,
+        password: Option<&str>,
+        preimage: Option<String>,
+        recorded: Option<(String, u64)>,
+    ) -> Result<TransactionDetails, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        let chain = self.chain.read().unwrap();
+        let index = self.index.read().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+
+        //THE ESCROW MUST BE MINED AND NOT SETTLED BY A MINED OR A PENDING TRANSACTION
+        let Some(height) = index.block_height(escrow_id) else {
+            return match pending_transactions
+                .iter()
+                .any(|transaction| transaction.id == escrow_id && transaction.hash_lock.is_some())
+            {
+                true => Err(BlockChainError::EscrowNotMined),
+                false => Err(BlockChainError::EscrowNotFound),
+            };
+        };
+        let escrow = chain[height]
+            .transactions
+            .iter()
+            .find(|transaction| transaction.id == escrow_id)
+            .ok_or(BlockChainError::EscrowNotFound)?;
+        let hash_lock = escrow
+            .hash_lock
+            .as_ref()
+            .ok_or(BlockChainError::EscrowNotFound)?;
+        let settling = pending_transactions.iter().any(|transaction| {
+            transaction
+                .settlement
+                .as_ref()
+                .is_some_and(|settlement| settlement.escrow == escrow_id)
+        });
+        if index.settlement(escrow_id).is_some() || settling {
+            return Err(BlockChainError::EscrowSettled);
+        }
+
+        //A CLAIM PAYS THE RECIPIENT AND A REFUND THE SENDER, ONLY THEY CAN ASK FOR IT
+        let (id, timestamp) = recorded.unzip();
+        let timestamp = timestamp.unwrap_or_else(unix_time);
+        let payee = match &preimage {
+            Some(_) => &escrow.to_wallet.address,
+            None => &escrow.from_wallet.address,
+        };
+        let payee = find_wallet(&wallets, payee, AddressType::TO)?;
+        if password.is_some_and(|password| payee.password != password) {
+            return Err(BlockChainError::WrongPassword);
+        }
+        match &preimage {
+            Some(preimage) if !hash_lock.is_opened_by(preimage) => {
+                return Err(BlockChainError::WrongPreimage)
+            }
+            None if !hash_lock.is_timed_out(timestamp, chain.len()) => {
+                return Err(BlockChainError::EscrowNotTimedOut)
+            }
+            _ => {}
+        }
+
+        //CREATE THE TRANSACTION FROM THE ESCROW ADDRESS AND WRITE IT TO THE LOG BEFORE CHANGING ANYTHING
+        let mut transaction = Transaction::new(
+            Wallet::new(ESCROW_ADDRESS.to_string(), 0, String::new()),
+            payee,
+            escrow.amount,
+        );
+        transaction.timestamp = timestamp;
+        if let Some(id) = id {
+            transaction.id = id;
+        }
+        transaction.settlement = Some(Settlement {
+            escrow: escrow_id.to_string(),
+            preimage: preimage.clone(),
+        });
+        self.append_to_log(Command::SettleEscrow {
+            escrow: escrow_id.to_string(),
+            preimage,
+            id: transaction.id.clone(),
+            timestamp,
+        })?;
+
+        let staged = StagedTransaction {
+            transaction,
+            replaces: None,
+            debited: false,
+        };
+        let submitted = apply_staged(staged, &mut pending_transactions, &mut wallets)?;
+        self.announce(&submitted, chain.len());
+
+        Ok(submitted.details)
+    }
+
+    //EVICT THE PENDING TRANSACTIONS THAT ARE EXPIRED OR CAN NO LONGER BE MINED AND, IF THE MEMPOOL IS OVER ITS LIMITS, THE ONES WITH THE LOWEST FEES
+    //THE COINS GO BACK TO THE SENDERS. A BLOCK THAT IS BEING MINED CHECKS ITS TRANSACTIONS ITSELF, SO THE SWEEP IS SKIPPED. RETURN HOW MANY WERE EVICTED
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn sweep_mempool(&self) -> Result<usize, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        //ONLY THE MINER REMOVES PENDING TRANSACTIONS, SEE 'mine'
+        let Ok(_mining) = self.mining.try_lock() else {
+            return Ok(0);
+        };
+        self.evict_transactions_to_sweep()
+    }
+
+    //CHANGE HOW BIG THE MEMPOOL CAN GROW
+    pub fn set_mempool_limits(&self, limits: MempoolLimits) {
+        self.pending_transactions.lock().unwrap().set_limits(limits);
+    }
+
+    //EVICT THE TRANSACTIONS FOUND BY THE SWEEP, THE MINING LOCK MUST BE HELD
+    fn evict_transactions_to_sweep(&self) -> Result<usize, BlockChainError> {
+        let now = unix_time();
+        let chain = self.chain.read().unwrap();
+        let index = self.index.read().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+
+        let mut evictions = invalid_transactions(
+            &pending_transactions,
+            &wallets,
+            &index,
+            self.mining_reward,
+            now,
+            chain.len(),
+        );
+        evictions.extend(pending_transactions.over_limits(&evictions));
+        if evictions.is_empty() {
+            return Ok(0);
+        }
+
+        check_refunds(&pending_transactions, &wallets, &evictions)?;
+        self.append_to_log(Command::EvictTransactions {
+            evictions: evictions.clone(),
+        })?;
+        self.evict_transactions(
+            &mut pending_transactions,
+            &mut wallets,
+            &evictions,
+            chain.len(),
+        )?;
+        Ok(evictions.len())
+    }
+
+    //REMOVE THE TRANSACTIONS FROM PENDING TRANSACTIONS AND GIVE THE COINS TAKEN FOR THEM BACK TO THEIR SENDERS
+    //THE NEXT BLOCK IS AT THE GIVEN HEIGHT. NOTHING IS CHANGED WHEN A REFUND WOULD NOT FIT IN THE BALANCE OF ITS SENDER
+    fn evict_transactions(
+        &self,
+        pending_transactions: &mut Mempool,
+        wallets: &mut [Wallet],
+        evictions: &[Eviction],
+        height: usize,
+    ) -> Result<(), BlockChainError> {
+        check_refunds(pending_transactions, wallets, evictions)?;
+        for eviction in evictions {
+            let (transaction, refund) = pending_transactions
+                .evict(&eviction.transaction.id, eviction.reason)
+                .ok_or(BlockChainError::TransactionNotFound)?;
+
+            //ONLY THE COINS THE WALLET WAS DEBITED FOR GO BACK TO IT, SEE 'invalid_transactions'
+            let sender = wallets
+                .iter_mut()
+                .find(|wallet| wallet.address == transaction.from_wallet.address);
+            if let Some(sender) =
+                sender.filter(|sender| refund > 0 && transaction.nonce < sender.nonce)
+            {
+                sender.balance = sender
+                    .balance
+                    .checked_add(refund)
+                    .ok_or(BlockChainError::BalanceOverflow)?;
+                self.publish_balance(sender, height);
+            }
+
+            METRICS.record_eviction(eviction.reason);
+            self.events.publish(Event::TransactionEvicted {
+                transaction: eviction.transaction.clone(),
+                reason: eviction.reason,
+            });
+            warn!(id = %transaction.id, reason = ?eviction.reason, refund, "transaction evicted");
+        }
+
+        Ok(())
+    }
+
+    //STOP ACCEPTING NEW TRANSACTIONS AND WALLET CHANGES AND CANCEL THE BLOCK THAT IS BEING MINED
+    //THE PENDING TRANSACTIONS OF A CANCELLED BLOCK STAY IN PENDING TRANSACTIONS
+    pub fn shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        info!("blockchain is shutting down");
+    }
+
+    //CHECK IF THE BLOCKCHAIN IS SHUTTING DOWN
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    //RETURN AN ERROR IF THE BLOCKCHAIN NO LONGER ACCEPTS CHANGES
+    fn check_not_shutting_down(&self) -> Result<(), BlockChainError> {
+        match self.is_shutting_down() {
+            true => Err(BlockChainError::ShuttingDown),
+            false => Ok(()),
+        }
+    }
+
+    //CHECK THE HASHES, THE PROOF OF WORK AND THE LINKS BETWEEN ALL THE BLOCKS OF THE CHAIN
+    //THE RESULT IS KEPT FOR 'is_chain_valid'
+    pub fn validate_chain(&self) -> Result<(), BlockChainError> {
+        let result = validate_blocks(&self.chain.read().unwrap(), self.difficulty);
+        self.chain_valid.store(result.is_ok(), Ordering::Relaxed);
+        result
+    }
+
+    //CHECK IF THE CHAIN WAS VALID WHEN IT WAS LAST CHECKED, IT IS CHECKED WHEN IT IS LOADED FROM THE LOG OR IMPORTED
+    //THE MINED BLOCKS ARE VALID, SO THE CHAIN DOESN'T HAVE TO BE CHECKED AGAIN AFTER EACH BLOCK
+    pub fn is_chain_valid(&self) -> bool {
+        self.chain_valid.load(Ordering::Relaxed)
+    }
+
+    //CHECK IF THE STATE WAS LOADED, A BLOCKCHAIN WITH A LOG IS ONLY LOADED ONCE THE LOG WAS REPLAYED
+    pub fn is_storage_loaded(&self) -> bool {
+        self.storage_loaded.load(Ordering::Relaxed)
+    }
+
+    //RETURN A CONSISTENT COPY OF THE CHAIN, THE WALLETS, THE PENDING TRANSACTIONS, THE PROPOSALS AND THE STANDING ORDERS
+    pub fn export_snapshot(&self) -> ChainSnapshot {
+        self.snapshot(
+            &self.chain.read().unwrap(),
+            &self.pending_transactions.lock().unwrap(),
+            &self.wallets.read().unwrap(),
+            &self.proposals.read().unwrap(),
+            &self.standing_orders.read().unwrap(),
+        )
+    }
+
+    //THE SNAPSHOT OF THE GIVEN STATE, THE CALLER HOLDS ITS LOCKS
+    fn snapshot(
+        &self,
+        chain: &[Block],
+        pending_transactions: &Mempool,
+        wallets: &[Wallet],
+        proposals: &[Proposal],
+        standing_orders: &[StandingOrder],
+    ) -> ChainSnapshot {
+        ChainSnapshot {
+            header: SnapshotHeader {
+                version: SNAPSHOT_VERSION,
+                chain_name: self.name.clone(),
+                chain_id: self.chain_id,
+                difficulty: self.difficulty,
+                blocks: chain.len(),
+            },
+            blocks: chain.iter().map(Block::details).collect(),
+            wallets: wallets.to_vec(),
+            pending_transactions: pending_transactions
+                .iter()
+                .map(Transaction::details)
+                .collect(),
+            proposals: proposals.to_vec(),
+            standing_orders: standing_orders.to_vec(),
+        }
+    }
+
+    //REPLACE THE LOG WITH THE GENESIS BLOCK AND A SNAPSHOT OF THE CURRENT STATE, ITS REPLAY BUILDS THE SAME STATE
+    //THE BLOCK THAT IS BEING MINED IS WAITED FOR AND NOTHING CHANGES WHILE THE NEW LOG IS WRITTEN. NOTHING IS DONE WITHOUT A LOG
+    #[tracing::instrument(skip(self))]
+    pub fn checkpoint(&self) -> Result<(), BlockChainError> {
+        let _mining = self.mining.lock().unwrap();
+        let chain = self.chain.read().unwrap();
+        let pending_transactions = self.pending_transactions.lock().unwrap();
+        let wallets = self.wallets.read().unwrap();
+        let proposals = self.proposals.read().unwrap();
+        let standing_orders = self.standing_orders.read().unwrap();
+        let mut log = self.log.lock().unwrap();
+
+        let Some(log) = log.as_mut() else {
+            return Ok(());
+        };
+        let snapshot = self.snapshot(
+            &chain,
+            &pending_transactions,
+            &wallets,
+            &proposals,
+            &standing_orders,
+        );
+        log.checkpoint(vec![
+            Command::Genesis {
+                timestamp: chain[0].timestamp,
+                hash: chain[0].hash.clone(),
+            },
+            Command::ImportSnapshot { snapshot },
+        ])?;
+        info!(
+            block_height = chain.len() - 1,
+            "write-ahead log checkpointed"
+        );
+
+        Ok(())
+    }
+
+    //REPLACE THE CHAIN, THE WALLETS, THE PENDING TRANSACTIONS, THE PROPOSALS AND THE STANDING ORDERS WITH THE ONES FROM A SNAPSHOT
+    //EVERY BLOCK IS VALIDATED WITH THE DIFFICULTY OF THIS NODE BEFORE ANYTHING IS REPLACED. RETURN THE HEIGHT OF THE NEW CHAIN
+    #[tracing::instrument(skip_all, fields(blocks = snapshot.blocks.len()))]
+    pub fn import_snapshot(&self, snapshot: ChainSnapshot) -> Result<usize, BlockChainError> {
+        self.check_not_shutting_down()?;
+
+        if snapshot.header.chain_id != self.chain_id {
+            return Err(BlockChainError::ChainIdMismatch);
+        }
+
+        let blocks: Vec<Block> = snapshot.blocks.iter().map(Block::from).collect();
+        validate_blocks(&blocks, self.difficulty)?;
+
+        //EVERY ADDRESS CAN ONLY HAVE ONE WALLET
+        for (position, wallet) in snapshot.wallets.iter().enumerate() {
+            if snapshot.wallets[..position]
+                .iter()
+                .any(|other| other.address == wallet.address)
+            {
+                return Err(BlockChainError::InvalidSnapshot);
+            }
+        }
+
+        //EVERY PROPOSAL IS FROM A MULTISIGNATURE WALLET OF THE SNAPSHOT
+        for proposal in &snapshot.proposals {
+            find_multisig_wallet(&snapshot.wallets, &proposal.wallet)
+                .map_err(|_| BlockChainError::InvalidSnapshot)?;
+        }
+
+        //EVERY STANDING ORDER IS PAID FROM A WALLET OF THE SNAPSHOT, WITH AN INTERVAL IT COULD HAVE BEEN CREATED WITH
+        for order in &snapshot.standing_orders {
+            find_wallet(&snapshot.wallets, &order.from_address, AddressType::FROM)
+                .map_err(|_| BlockChainError::InvalidSnapshot)?;
+            if !(MIN_STANDING_ORDER_INTERVAL..=MAX_STANDING_ORDER_INTERVAL)
+                .contains(&order.interval)
+            {
+                return Err(BlockChainError::InvalidSnapshot);
+            }
+        }
+        let transactions: Vec<Transaction> = snapshot
+            .pending_transactions
+            .iter()
+            .map(Transaction::from)
+            .collect();
+        let mempool = Mempool::new(
+            transactions,
+            self.pending_transactions.lock().unwrap().limits(),
+        );
+
+        //NO WALLET HOLDS MORE THAN u32::MAX COINS
+        if snapshot.wallets.iter().any(|wallet| {
+            wallet
+                .check_credit(mempool.pending_spend(&wallet.address), 0)
+                .is_err()
+        }) {
+            return Err(BlockChainError::InvalidSnapshot);
+        }
+
+        //WAIT FOR THE BLOCK THAT IS BEING MINED AND REPLACE EVERYTHING TOGETHER
+        let _mining = self.mining.lock().unwrap();
+        let mut chain = self.chain.write().unwrap();
+        let mut index = self.index.write().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let mut wallets = self.wallets.write().unwrap();
+        let mut proposals = self.proposals.write().unwrap();
+        let mut standing_orders = self.standing_orders.write().unwrap();
+
+        self.append_to_log(Command::ImportSnapshot {
+            snapshot: snapshot.clone(),
+        })?;
+        *index = ChainIndex::build(&blocks);
+        *chain = blocks;
+        *pending_transactions = mempool;
+        *wallets = snapshot.wallets;
+        *proposals = snapshot.proposals;
+        *standing_orders = snapshot.standing_orders;
+        //THE BLOCKS OF THE SNAPSHOT WERE CHECKED BEFORE THEY REPLACED THE CHAIN
+        self.chain_valid.store(true, Ordering::Relaxed);
+
+        //LET THE SUBSCRIBERS KNOW ABOUT THE NEW CHAIN AND THE BALANCES OF ALL THE WALLETS
+        let height = chain.len() - 1;
+        self.events.publish(Event::ChainReplaced {
+            block: chain[height].details(),
+        });
+        for wallet in wallets.iter() {
+            self.publish_balance(wallet, chain.len());
+        }
+        info!(
+            block_height = height,
+            wallets = wallets.len(),
+            "snapshot imported"
+        );
+
+        Ok(height)
+    }
+
+    //REPLAY THE LOG AT THE GIVEN PATH AND WRITE ALL THE NEXT CHANGES TO IT, RETURN HOW MANY ENTRIES WERE REPLAYED
+    //IT MUST BE OPENED BEFORE ANYTHING CHANGES. A NEW LOG STARTS WITH THE GENESIS BLOCK OF THIS BLOCKCHAIN
+    #[tracing::instrument(skip(self))]
+    pub fn open_log(&self, path: &Path) -> Result<usize, BlockChainError> {
+        self.storage_loaded.store(false, Ordering::Relaxed);
+        let (mut log, entries) = WriteAheadLog::open(path)?;
+
+        match entries.is_empty() {
+            true => {
+                let genesis = &self.chain.read().unwrap()[0];
+                log.append(Command::Genesis {
+                    timestamp: genesis.timestamp,
+                    hash: genesis.hash.clone(),
+                })?;
+            }
+            false => self.replay(&entries)?,
+        }
+        *self.log.lock().unwrap() = Some(log);
+
+        //THE WHOLE CHAIN IS CHECKED ONCE WHEN IT IS LOADED, NOT ON EVERY READINESS CHECK
+        if let Err(error) = self.validate_chain() {
+            warn!(%error, "the chain loaded from the write-ahead log is not valid");
+        }
+        self.storage_loaded.store(true, Ordering::Relaxed);
+
+        info!(entries = entries.len(), "write-ahead log opened");
+        Ok(entries.len())
+    }
+
+    //APPLY THE COMMANDS FROM A LOG TO THIS NEW BLOCKCHAIN, THE RESULT IS THE STATE OF THE BLOCKCHAIN THAT WROTE THE LOG
+    //EVERY COMMAND IS CHECKED AGAIN, A COMMAND THAT FAILS OR A BLOCK WITH ANOTHER HASH MEANS THE STATE IS NOT THE SAME
+    pub fn replay(&self, entries: &[LogEntry]) -> Result<(), BlockChainError> {
+        let mut entries = entries.iter();
+
+        match entries.next().map(|entry| &entry.command) {
+            Some(Command::Genesis { timestamp, hash }) => {
+                let mut genesis = Block::new(0, &[]);
+                genesis.timestamp = *timestamp;
+                genesis.set_hash();
+                if genesis.hash != *hash {
+                    return Err(BlockChainError::ReplayMismatch);
+                }
+
+                let mut chain = self.chain.write().unwrap();
+                *self.index.write().unwrap() = ChainIndex::build(&[genesis.clone()]);
+                *chain = vec![genesis];
+            }
+            _ => return Err(BlockChainError::InvalidLog),
+        }
+
+        for entry in entries {
+            self.apply(entry.command.clone()).map_err(|error| {
+                warn!(sequence = entry.sequence, %error, "replay failed");
+                match error {
+                    BlockChainError::InvalidLog => error,
+                    _ => BlockChainError::ReplayMismatch,
+                }
+            })?;
+        }
+
+        Ok(())
+    }
+
+    //APPLY A COMMAND FROM THE LOG WITH THE VALUES THAT WERE RECORDED FOR IT
+    fn apply(&self, command: Command) -> Result<(), BlockChainError> {
+        match command {
+            Command::Genesis { .. } => return Err(BlockChainError::InvalidLog),
+            Command::CreateWallet(wallet) => {
+                self.add_wallet(wallet)?;
+            }
+            Command::AddCoins { address, coins } => {
+                self.deposit(&address, None, coins)?;
+            }
+            Command::SubmitTransaction {
+                transaction,
+                id,
+                timestamp,
+            } => {
+                self.submit(&transaction.into(), None, Some((id, timestamp)))?;
+            }
+            Command::SubmitBatch { transactions } => {
+                let transactions = transactions
+                    .into_iter()
+                    .map(|recorded| {
+                        (
+                            recorded.transaction.into(),
+                            Some((recorded.id, recorded.timestamp)),
+                        )
+                    })
+                    .collect();
+                let items = self.batch(transactions, BatchMode::Atomic)?;
+                if items
+                    .iter()
+                    .any(|item| item.status != BatchItemStatus::Accepted)
+                {
+                    return Err(BlockChainError::ReplayMismatch);
+                }
+            }
+            Command::CreateMultisigWallet(wallet) => {
+                self.create_multisig_wallet(wallet)?;
+            }
+            Command::ProposeTransfer {
+                wallet,
+                proposal,
+                id,
+                timestamp,
+                transaction_id,
+            } => {
+                self.propose(
+                    &wallet,
+                    &proposal.into(),
+                    None,
+                    Some((id, timestamp, transaction_id)),
+                )?;
+            }
+            Command::ApproveProposal {
+                id,
+                owner,
+                timestamp,
+                transaction_id,
+            } => {
+                self.approve(&id, &owner, None, Some((timestamp, transaction_id)))?;
+            }
+            Command::SettleEscrow {
+                escrow,
+                preimage,
+                id,
+                timestamp,
+            } => {
+                self.settle(&escrow, None, preimage, Some((id, timestamp)))?;
+            }
+            Command::CreateStandingOrder {
+                order,
+                id,
+                timestamp,
+            } => {
+                self.schedule(&order.into(), None, Some((id, timestamp)))?;
+            }
+            Command::RevokeStandingOrder { id, timestamp } => {
+                self.revoke(&id, None, Some(timestamp))?;
+            }
+            Command::PayStandingOrders {
+                timestamp,
+                payments,
+            } => {
+                self.pay(Some((timestamp, payments)))?;
+            }
+            Command::MineBlock(block) => {
+                self.mine(&block.reward_address, Some(&block))?;
+            }
+            Command::EvictTransactions { evictions } => {
+                let chain = self.chain.read().unwrap();
+                let mut pending_transactions = self.pending_transactions.lock().unwrap();
+                let mut wallets = self.wallets.write().unwrap();
+                self.evict_transactions(
+                    &mut pending_transactions,
+                    &mut wallets,
+                    &evictions,
+                    chain.len(),
+                )?;
+            }
+            Command::ImportSnapshot { snapshot } => {
+                self.import_snapshot(snapshot)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    //WRITE THE COMMAND TO THE LOG, IF THERE IS ONE. THE COMMAND IS ONLY APPLIED IF THIS SUCCEEDS
+    fn append_to_log(&self, command: Command) -> Result<(), BlockChainError> {
+        match self.log.lock().unwrap().as_mut() {
+            Some(log) => log.append(command),
+            None => Ok(()),
+        }
+    }
+
+    //REBUILD THE INDEXES FROM THE BLOCKS OF THE CHAIN
+    pub fn rebuild_index(&self) {
+        let chain = self.chain.read().unwrap();
+        *self.index.write().unwrap() = ChainIndex::build(&chain);
+    }
+
+    //UPDATE THE WALLET FROM THE BLOCKCHAIN OR RETURN AN ERROR IF IS NOT FOUND OR IF IT WOULD HOLD MORE THAN u32::MAX COINS
+    pub fn update_wallet(&self, wallet: Wallet) -> Result<(), BlockChainError> {
+        let pending_transactions = self.pending_transactions.lock().unwrap();
+        wallet.check_credit(pending_transactions.pending_spend(&wallet.address), 0)?;
+        replace_wallet(&mut self.wallets.write().unwrap(), wallet)
+    }
+}
+
+//CHECK THAT EVERY BLOCK IS VALID, IS AT THE RIGHT HEIGHT AND POINTS TO THE HASH OF THE BLOCK BEFORE IT
+//AND THAT EVERY CLAIM AND REFUND SETTLES AN ESCROW OF AN EARLIER BLOCK ACCORDING TO ITS HASH LOCK
+pub fn validate_blocks(blocks: &[Block], difficulty: usize) -> Result<(), BlockChainError> {
+    if blocks.is_empty() {
+        return Err(BlockChainError::ChainIsEmpty);
+    }
+
+    for (height, block) in blocks.iter().enumerate() {
+        let linked = match height {
+            0 => true,
+            _ => block.previous_hash == blocks[height - 1].hash,
+        };
+
+        if block.index != height || !linked || !block.is_valid(difficulty) {
+            warn!(block_height = height, "invalid block");
+            return Err(BlockChainError::InvalidChain);
+        }
+    }
+
+    validate_settlements(blocks)
+}
+
+//FIND THE PENDING TRANSACTIONS THAT CAN'T BE MINED INTO THE BLOCK AT THE GIVEN HEIGHT AND WHY, CHECKING THEM IN THE ORDER THEY WOULD BE MINED
+fn invalid_transactions(
+    pending_transactions: &Mempool,
+    wallets: &[Wallet],
+    index: &ChainIndex,
+    mining_reward: u32,
+    now: u64,
+    height: usize,
+) -> Vec<Eviction> {
+    let senders: HashMap<&str, &Wallet> = wallets
+        .iter()
+        .map(|wallet| (wallet.address.as_str(), wallet))
+        .collect();
+    let exists = |address: &str| senders.contains_key(address);
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut settling: HashSet<&str> = HashSet::new();
+
+    //THE COINS EVERY SENDER CAN PAY ITS PENDING TRANSACTIONS WITH: WHAT IT CAN SPEND NOW AND WHAT IT WAS ALREADY DEBITED FOR THEM
+    //A PENDING TRANSACTION WAS DEBITED FROM THE WALLET WHEN ITS NONCE IS BELOW THE ONE OF THE WALLET, ONE THAT WASN'T, FOR EXAMPLE BECAUSE THE WALLET CHANGED, IS NOT COVERED
+    let mut remaining_spend: HashMap<&str, u64> = HashMap::new();
+    //THE COINS EVERY RECIPIENT WILL HOLD, NONE CAN HOLD MORE THAN u32::MAX COINS. SEE 'Wallet::check_credit'
+    let mut holdings: HashMap<&str, u64> = HashMap::new();
+    for transaction in pending_transactions.iter() {
+        let address = transaction.from_wallet.address.as_str();
+        if let Some(sender) = senders.get(address) {
+            let remaining = remaining_spend
+                .entry(address)
+                .or_insert_with(|| sender.balance(now, height).balance as u64);
+            if transaction.nonce < sender.nonce {
+                *remaining += transaction.cost() as u64;
+            }
+        }
+    }
+
+    pending_transactions
+        .iter()
+        .filter_map(|transaction| {
+            let sender = transaction.from_wallet.address.as_str();
+            let is_reward = sender == MINING_ADDRESS;
+            //THE REWARDS AND THE CLAIMS AND REFUNDS OF THE ESCROWS ARE NOT PAID BY A WALLET
+            let is_paid = !is_reward && sender != ESCROW_ADDRESS;
+            //AN ESCROW CAN ONLY BE SETTLED ONCE
+            let settled = transaction.settlement.as_ref().is_some_and(|settlement| {
+                index.settlement(&settlement.escrow).is_some()
+                    || !settling.insert(&settlement.escrow)
+            });
+            let remaining = remaining_spend.entry(sender).or_default();
+            let recipient = transaction.to_wallet.address.as_str();
+            let credit = match transaction.hash_lock {
+                Some(_) => 0,
+                None => transaction.amount as u64,
+            };
+            let holding = senders.get(recipient).map(|wallet| {
+                holdings.entry(recipient).or_insert_with(|| {
+                    wallet.holdings(pending_transactions.pending_spend(recipient))
+                })
+            });
+
+            let reason = if !seen.insert(&transaction.id)
+                || index.block_height(&transaction.id).is_some()
+                || settled
+            {
+                Some(EvictionReason::Duplicate)
+            } else if transaction.amount == 0 {
+                Some(EvictionReason::InvalidAmount)
+            } else if is_reward && transaction.amount < mining_reward {
+                Some(EvictionReason::InvalidReward)
+            } else if transaction.is_expired(now, height) {
+                Some(EvictionReason::Expired)
+            } else if is_paid && !exists(sender) {
+                Some(EvictionReason::UnknownSender)
+            } else if !exists(&transaction.to_wallet.address) {
+                Some(EvictionReason::UnknownRecipient)
+            } else if is_paid && *remaining < transaction.cost() as u64 {
+                Some(EvictionReason::Overdraft)
+            } else if holding
+                .as_ref()
+                .is_some_and(|holding| **holding + credit > u32::MAX as u64)
+            {
+                Some(EvictionReason::BalanceOverflow)
+            } else {
+                None
+            };
+
+            match reason {
+                Some(reason) => Some(Eviction {
+                    transaction: transaction.details(),
+                    reason,
+                }),
+                None => {
+                    if is_paid {
+                        *remaining -= transaction.cost() as u64;
+                    }
+                    if let Some(holding) = holding {
+                        *holding += credit;
+                    }
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+//CHECK IF THE COINS GIVEN BACK FOR THE EVICTED TRANSACTIONS FIT IN THE BALANCES OF THEIR SENDERS, THE SAME WAY 'evict_transactions' GIVES THEM BACK
+fn check_refunds(
+    pending_transactions: &Mempool,
+    wallets: &[Wallet],
+    evictions: &[Eviction],
+) -> Result<(), BlockChainError> {
+    //THE BALANCE AND THE COINS WAITING TO BE MINED OF EVERY SENDER, AFTER THE EVICTIONS CHECKED SO FAR
+    let mut senders: HashMap<&str, (u64, u64)> = HashMap::new();
+
+    for eviction in evictions {
+        let Some(transaction) = pending_transactions
+            .iter()
+            .rev()
+            .find(|transaction| transaction.id == eviction.transaction.id)
+        else {
+            continue;
+        };
+        let address = transaction.from_wallet.address.as_str();
+        let Some(sender) = wallets.iter().find(|wallet| wallet.address == address) else {
+            continue;
+        };
+
+        let (balance, spend) = senders.entry(address).or_insert_with(|| {
+            (
+                sender.balance as u64,
+                pending_transactions.pending_spend(address),
+            )
+        });
+        let refund = (*spend).min(transaction.cost() as u64);
+        *spend -= refund;
+        if refund > 0 && transaction.nonce < sender.nonce {
+            *balance += refund;
+            if *balance > u32::MAX as u64 {
+                return Err(BlockChainError::BalanceOverflow);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//A NEW TRANSACTION THAT PASSED ALL THE CHECKS, ITS SENDER IS ALREADY DEBITED. 'replaces' IS THE ID OF THE PENDING TRANSACTION IT REPLACES
+struct StagedTransaction {
+    transaction: Transaction,
+    replaces: Option<String>,
+    debited: bool,
+}
+
+//A TRANSACTION ADDED TO PENDING TRANSACTIONS, THE TRANSACTION IT REPLACED AND THE SENDER IF IT WAS DEBITED
+struct Submitted {
+    details: TransactionDetails,
+    replaced: Option<TransactionDetails>,
+    sender: Option<Wallet>,
+}
+
+//CHECK THE TRANSACTION AGAINST THE WALLETS AND THE PENDING TRANSACTIONS AND CREATE IT, THE NEXT BLOCK IS AT THE GIVEN HEIGHT
+//NOTHING IS CHANGED, THE TRANSACTION IS ADDED WITH 'apply_staged'
+//'password' IS THE ONE THE SENDER GAVE, NOTHING WHEN THE TRANSACTION WAS ALREADY AUTHORISED: BY THE OWNERS OF A MULTISIGNATURE WALLET,
+//BY THE PAYER OF A STANDING ORDER OR BEFORE IT WAS WRITTEN TO THE LOG. A MULTISIGNATURE WALLET CAN'T SEND A TRANSACTION WITH ITS PASSWORD
+fn stage_transaction(
+    transaction: &TransactionInfo,
+    password: Option<&str>,
+    id: Option<String>,
+    timestamp: u64,
+    height: usize,
+    pending_transactions: &Mempool,
+    wallets: &[Wallet],
+) -> Result<StagedTransaction, BlockChainError> {
+    //CHECK IF THE TRANSACTION IS VALID
+    transaction.check_transaction_info()?;
+
+    //GET THE 'FROM' WALLET AND RETURN AN ERROR IF IS NOT FOUND
+    let mut from_wallet = find_wallet(wallets, &transaction.from_address, AddressType::FROM)?;
+
+    //RETURN AN ERROR IF THE PASSWORD IS WRONG
+    if let Some(password) = password {
+        if from_wallet.password != password {
+            return Err(BlockChainError::WrongPassword);
+        }
+        if from_wallet.multisig.is_some() {
+            return Err(BlockChainError::MultisigApprovalRequired);
+        }
+    }
+
+    //THE LOCKED COINS WHOSE LOCK HAS PASSED AT THE TIME OF THE TRANSACTION CAN BE SPENT, SO A REPLAY GIVES THE SAME RESULT
+    from_wallet.release_locked(timestamp, height);
+
+    //GET THE 'TO' WALLET AND RETURN AN ERROR IF IS NOT FOUND
+    let to_wallet = find_wallet(wallets, &transaction.to_address, AddressType::TO)?;
+
+    //CHECK IF THE 'TO' WALLET CAN HOLD THE COINS, THE ONES SENT TO THE SAME WALLET OR TO AN ESCROW DON'T ADD TO IT
+    if to_wallet.address != from_wallet.address && transaction.hash_lock.is_none() {
+        to_wallet.check_credit(
+            pending_transactions.pending_spend(&to_wallet.address),
+            transaction.amount as u64,
+        )?;
+    }
+
+    //FIND THE PENDING TRANSACTION WITH THE SAME NONCE, IT IS REPLACED IF THE NEW FEE IS HIGHER
+    //ANY OTHER NONCE MUST BE THE NEXT ONE OF THE SENDER
+    let replaced = transaction.nonce.and_then(|nonce| {
+        pending_transactions
+            .find_by_nonce(&from_wallet.address, nonce)
+            .map(|pending| (pending.id.clone(), pending.fee, pending.cost()))
+    });
+    let nonce = match (transaction.nonce, &replaced) {
+        (Some(nonce), Some((_, fee, _))) => match transaction.fee > *fee {
+            true => nonce,
+            false => return Err(BlockChainError::ReplacementFeeTooLow),
+        },
+        (Some(nonce), None) if nonce != from_wallet.nonce => {
+            return Err(BlockChainError::InvalidNonce)
+        }
+        _ => from_wallet.nonce,
+    };
+
+    //CHECK IF THE 'FROM' WALLET HAVE ENOUGH COINS FOR THIS TRANSACTION AND ITS FEE, THE COINS OF A REPLACED TRANSACTION COME BACK FIRST
+    //WHEN THE COINS ARE THERE BUT ARE ALREADY SPENT BY PENDING TRANSACTIONS, THE TRANSACTION CONFLICTS WITH THEM
+    //THE COINS ARE COUNTED IN u64 SO NO SUM CAN OVERFLOW, THE NEW BALANCE MUST STILL FIT IN THE WALLET
+    let cost = transaction.amount as u64 + transaction.fee as u64;
+    let refund = replaced.as_ref().map_or(0, |(_, _, cost)| *cost as u64);
+    let available = from_wallet.balance as u64 + refund;
+    if available < cost {
+        let pending_spend = pending_transactions
+            .pending_spend(&from_wallet.address)
+            .saturating_sub(refund);
+        return match pending_spend > 0 && available + pending_spend >= cost {
+            true => Err(BlockChainError::PendingSpendConflict),
+            false => Err(BlockChainError::NotEnoughCoins),
+        };
+    };
+
+    //SUBTRACT THE AMOUNT SEND FROM THE 'FROM WALLET, A NEW TRANSACTION USES THE NONCE OF THE WALLET
+    let debited = from_wallet.address != MINING_ADDRESS;
+    if debited {
+        from_wallet.balance =
+            u32::try_from(available - cost).map_err(|_| BlockChainError::BalanceOverflow)?;
+        if replaced.is_none() {
+            from_wallet.nonce += 1;
+        }
+    }
+
+    //CREATE THE TRANSACTION
+    let mut new_transaction = Transaction::new(from_wallet, to_wallet, transaction.amount);
+    new_transaction.fee = transaction.fee;
+    new_transaction.expiry = transaction.expiry;
+    new_transaction.nonce = nonce;
+    new_transaction.memo = transaction.memo.clone();
+    new_transaction.metadata = transaction.metadata.clone();
+    new_transaction.lock = transaction.lock;
+    new_transaction.hash_lock = transaction.hash_lock.clone();
+    new_transaction.timestamp = timestamp;
+    if let Some(id) = id {
+        new_transaction.id = id;
+    }
+
+    //THE EXPIRY IS CHECKED AT THE TIME OF THE TRANSACTION, SO A REPLAY GIVES THE SAME RESULT
+    if new_transaction.is_expired(new_transaction.timestamp, height) {
+        return Err(BlockChainError::TransactionExpired);
+    }
+
+    //AN ESCROW THAT COULD BE REFUNDED RIGHT AWAY GIVES THE RECIPIENT NO TIME TO CLAIM IT
+    if transaction
+        .hash_lock
+        .as_ref()
+        .is_some_and(|hash_lock| hash_lock.is_timed_out(timestamp, height))
+    {
+        return Err(BlockChainError::HashLockTimedOut);
+    }
+
+    Ok(StagedTransaction {
+        transaction: new_transaction,
+        replaces: replaced.map(|(id, _, _)| id),
+        debited,
+    })
+}
+
+//DEBIT THE 'FROM' WALLET AND ADD THE TRANSACTION TO PENDING TRANSACTIONS, IN THE PLACE OF THE ONE IT REPLACES
+fn apply_staged(
+    staged: StagedTransaction,
+    pending_transactions: &mut Mempool,
+    wallets: &mut [Wallet],
+) -> Result<Submitted, BlockChainError> {
+    let details = staged.transaction.details();
+    let sender = staged.transaction.from_wallet.clone();
+    if staged.debited {
+        replace_wallet(wallets, sender.clone())?;
+    }
+
+    let replaced = match staged.replaces {
+        Some(id) => {
+            let (replaced, _) = pending_transactions
+                .replace(&id, staged.transaction)
+                .ok_or(BlockChainError::TransactionNotFound)?;
+            Some(replaced.details())
+        }
+        None => {
+            pending_transactions.push(staged.transaction);
+            None
+        }
+    };
+
+    Ok(Submitted {
+        details,
+        replaced,
+        sender: staged.debited.then_some(sender),
+    })
+}
+
+//FIND THE SENDERS THAT CAN'T PAY FOR ALL THEIR TRANSACTIONS OF A BATCH, THE COINS OF THE PENDING TRANSACTIONS THE BATCH REPLACES COUNT AS THEIRS
+//SO DO THE LOCKED COINS THAT CAN BE SPENT NOW, EVERY TRANSACTION CHECKS THEM AGAIN AT ITS OWN TIME
+fn overdrawn_senders(
+    transactions: &[(TransactionInfo, Option<(String, u64)>)],
+    pending_transactions: &Mempool,
+    wallets: &[Wallet],
+    height: usize,
+) -> HashSet<String> {
+    let now = unix_time();
+    let mut totals: HashMap<&str, (u64, u64)> = HashMap::new();
+
+    for (transaction, _) in transactions {
+        let sender = transaction.from_address.as_str();
+        let refund = transaction
+            .nonce
+            .and_then(|nonce| pending_transactions.find_by_nonce(sender, nonce))
+            .map_or(0, |pending| pending.cost() as u64);
+        let (cost, refunds) = totals.entry(sender).or_default();
+        *cost += transaction.amount as u64 + transaction.fee as u64;
+        *refunds += refund;
+    }
+
+    totals
+        .into_iter()
+        .filter(|(sender, (cost, refunds))| {
+            wallets
+                .iter()
+                .find(|wallet| wallet.address == *sender && wallet.address != MINING_ADDRESS)
+                .is_some_and(|wallet| wallet.balance(now, height).balance as u64 + refunds < *cost)
+        })
+        .map(|(sender, _)| sender.to_string())
+        .collect()
+}
+
+//SEARCH FOR THE WALLET WITH THE GIVEN ADDRESS, THE ERROR RETURNED DEPENDS ON THE ADDRESS TYPE
+fn find_wallet(
+    wallets: &[Wallet],
+    address: &str,
+    address_type: AddressType,
+) -> Result<Wallet, BlockChainError> {
+    match wallets.iter().find(|wallet| wallet.address == address) {
+        Some(wallet) => Ok(wallet.clone()),
+        None => match address_type {
+            AddressType::TO => Err(BlockChainError::InvalidToAddress),
+            AddressType::FROM => Err(BlockChainError::InvalidFromAddress),
+            AddressType::REWARD => Err(BlockChainError::InvalidRewardAddress),
+            AddressType::GENERIC => Err(BlockChainError::WalletNotFound),
+        },
+    }
+}
+
+//STAGE THE TRANSACTION OF A PROPOSAL WHEN ITS APPROVALS REACH THE THRESHOLD, IT GETS THE TIME OF THE APPROVAL THAT EXECUTES IT
+//RETURN NOTHING WHILE THE PROPOSAL NEEDS MORE APPROVALS
+fn stage_proposal(
+    proposal: &Proposal,
+    wallet: &Wallet,
+    transaction_id: Option<String>,
+    height: usize,
+    pending_transactions: &Mempool,
+    wallets: &[Wallet],
+) -> Result<Option<StagedTransaction>, BlockChainError> {
+    let Some(approval) = proposal.approvals.last() else {
+        return Ok(None);
+    };
+    if proposal.approvals.len() < proposal.threshold {
+        return Ok(None);
+    }
+
+    let staged = stage_transaction(
+        &proposal.transaction(wallet),
+        None,
+        transaction_id,
+        approval.timestamp,
+        height,
+        pending_transactions,
+        wallets,
+    )?;
+
+    Ok(Some(staged))
+}
+
+//SEARCH FOR THE MULTISIGNATURE WALLET WITH THE GIVEN ADDRESS AND RETURN IT WITH ITS OWNERS
+fn find_multisig_wallet(
+    wallets: &[Wallet],
+    address: &str,
+) -> Result<(Wallet, Multisig), BlockChainError> {
+    let wallet = find_wallet(wallets, address, AddressType::GENERIC)?;
+    let multisig = wallet
+        .multisig
+        .clone()
+        .ok_or(BlockChainError::NotMultisigWallet)?;
+
+    Ok((wallet, multisig))
+}
+
+//CHECK IF AN ENTRY FROM THE HISTORY OF A WALLET MATCHES THE FILTERS FROM THE QUERY
+fn history_entry_matches(entry: &HistoryEntry, query: &HistoryQuery) -> bool {
+    let transaction = &entry.transaction;
+    let counterparty = match entry.direction {
+        Direction::Out => &transaction.to_address,
+        Direction::In => &transaction.from_address,
+    };
+
+    query.from_time.is_none_or(|from| entry.timestamp >= from)
+        && query.to_time.is_none_or(|to| entry.timestamp <= to)
+        && query.min_amount.is_none_or(|min| transaction.amount >= min)
+        && query.max_amount.is_none_or(|max| transaction.amount <= max)
+        && query
+            .counterparty
+            .as_ref()
+            .is_none_or(|address| counterparty == address)
+        && query
+            .direction
+            .is_none_or(|direction| entry.direction == direction)
+        && query.status.is_none_or(|status| entry.status == status)
+        && query
+            .notes()
+            .matches(transaction.memo.as_deref(), &transaction.metadata)
+}
+
+//REPLACE THE WALLET THAT HAS THE SAME ADDRESS OR RETURN AN ERROR IF IS NOT FOUND
+fn replace_wallet(wallets: &mut [Wallet], wallet: Wallet) -> Result<(), BlockChainError> {
+    match wallets.iter().position(|w| w.address == wallet.address) {
+        Some(index) => wallets[index] = wallet,
+        None => return Err(BlockChainError::WalletNotFound),
+    }
+
+    Ok(())
+}
+
+//RETURN THE CURRENT UNIX TIME IN SECONDS
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
 }
@@ -1,7 +1,11 @@
+use actix_web::error::BlockingError;
 use actix_web::http::header::ContentType;
 use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, ResponseError};
 use derive_more::Display;
+use tracing::warn;
+
+use crate::metrics::METRICS;
 
 //POSSIBLE ERRORS
 #[derive(Debug, Display)]
@@ -16,6 +20,8 @@ pub enum BlockChainError {
     EmptyAddress,
     #[display(fmt = "Address cannot be 'MINING'!")]
     MiningAddress,
+    #[display(fmt = "Address cannot be 'ESCROW'!")]
+    EscrowAddress,
     #[display(fmt = "Balance is less than 0!")]
     NegativeBalance,
     #[display(fmt = "Password is empty!")]
@@ -32,6 +38,68 @@ pub enum BlockChainError {
     InvalidFromAddress,
     #[display(fmt = "Not enough coins!")]
     NotEnoughCoins,
+    #[display(fmt = "The coins are already spent by pending transactions!")]
+    PendingSpendConflict,
+    #[display(fmt = "The wallet can't hold that many coins!")]
+    BalanceOverflow,
+    #[display(fmt = "The memo is too long!")]
+    MemoTooLong,
+    #[display(
+        fmt = "The metadata has too many entries, an empty key or a key or value that is too long!"
+    )]
+    InvalidMetadata,
+    #[display(fmt = "The transaction has already expired!")]
+    TransactionExpired,
+    #[display(
+        fmt = "The nonce is not the next one of the sender or of one of its pending transactions!"
+    )]
+    InvalidNonce,
+    #[display(fmt = "The fee must be higher than the one of the replaced transaction!")]
+    ReplacementFeeTooLow,
+    #[display(fmt = "The transaction is not pending!")]
+    TransactionNotPending,
+    #[display(fmt = "The batch has no transactions!")]
+    EmptyBatch,
+    #[display(fmt = "The batch has too many transactions!")]
+    BatchTooLarge,
+    #[display(
+        fmt = "The owners must be different existing wallets and the threshold between 1 and their number!"
+    )]
+    InvalidMultisig,
+    #[display(fmt = "The wallet is not a multisignature wallet!")]
+    NotMultisigWallet,
+    #[display(fmt = "Transfers from a multisignature wallet must be approved by its owners!")]
+    MultisigApprovalRequired,
+    #[display(fmt = "The address is not an owner of the wallet!")]
+    NotAnOwner,
+    #[display(fmt = "The lifetime of the proposal is invalid!")]
+    InvalidProposalLifetime,
+    #[display(fmt = "The owner already approved the proposal!")]
+    AlreadyApproved,
+    #[display(fmt = "The proposal was already executed!")]
+    ProposalNotPending,
+    #[display(fmt = "The proposal has expired!")]
+    ProposalExpired,
+    #[display(
+        fmt = "The hash lock must have a hex SHA-256 hash and can't be used together with a lock!"
+    )]
+    InvalidHashLock,
+    #[display(fmt = "The timeout of the hash lock has already passed!")]
+    HashLockTimedOut,
+    #[display(fmt = "The escrow is not mined yet!")]
+    EscrowNotMined,
+    #[display(fmt = "The escrow is already claimed or refunded!")]
+    EscrowSettled,
+    #[display(fmt = "The preimage doesn't match the hash of the escrow!")]
+    WrongPreimage,
+    #[display(fmt = "The escrow can't be refunded before its timeout!")]
+    EscrowNotTimedOut,
+    #[display(
+        fmt = "The interval must be between a minute and a year, the first payment can't be in the past and the end can't be before it!"
+    )]
+    InvalidStandingOrder,
+    #[display(fmt = "The standing order was already completed or revoked!")]
+    StandingOrderNotActive,
     #[display(fmt = "Amount is less than 0!")]
     NegativeAmount,
     #[display(fmt = "A wallet with this address already exists!")]
@@ -40,11 +108,64 @@ pub enum BlockChainError {
     WalletNotFound,
     #[display(fmt = "Chain is empty!")]
     ChainIsEmpty,
+    #[display(fmt = "Block not found!")]
+    BlockNotFound,
+    #[display(fmt = "Transaction not found!")]
+    TransactionNotFound,
+    #[display(fmt = "Proposal not found!")]
+    ProposalNotFound,
+    #[display(fmt = "Escrow not found!")]
+    EscrowNotFound,
+    #[display(fmt = "Standing order not found!")]
+    StandingOrderNotFound,
+    #[display(fmt = "The start of the block range is after the end!")]
+    InvalidBlockRange,
+    #[display(fmt = "Invalid cursor!")]
+    InvalidCursor,
+    #[display(fmt = "Page size must be between 1 and 100!")]
+    InvalidPageSize,
+    #[display(fmt = "Unknown event type!")]
+    InvalidEventType,
+    #[display(fmt = "Chain is invalid!")]
+    InvalidChain,
+    #[display(fmt = "The node is shutting down!")]
+    ShuttingDown,
+    #[display(fmt = "Snapshot is invalid!")]
+    InvalidSnapshot,
+    #[display(fmt = "Snapshot version is not supported!")]
+    UnsupportedSnapshotVersion,
+    #[display(fmt = "Snapshot is from another chain!")]
+    ChainIdMismatch,
+    #[display(fmt = "Wrong or missing admin token!")]
+    Unauthorized,
+    #[display(fmt = "The idempotency key is invalid!")]
+    InvalidIdempotencyKey,
+    #[display(fmt = "The idempotency key was already used for another request!")]
+    IdempotencyKeyReused,
+    #[display(fmt = "A request with this idempotency key is still in progress!")]
+    IdempotencyKeyInProgress,
+    #[display(fmt = "Log is invalid!")]
+    InvalidLog,
+    #[display(fmt = "Replayed state doesn't match the log!")]
+    ReplayMismatch,
+    #[display(fmt = "The write-ahead log could not be read or written!")]
+    LogFailed,
+    #[display(fmt = "The request could not be run to the end!")]
+    BlockingFailed,
+}
+
+//A BLOCKING CALL MOVED OFF THE WORKER THREAD FAILS WHEN IT PANICKED OR THE THREAD POOL IS GONE
+impl From<BlockingError> for BlockChainError {
+    fn from(_: BlockingError) -> Self {
+        BlockChainError::BlockingFailed
+    }
 }
 
 impl ResponseError for BlockChainError {
     //DISPLAY THE ERROR MESSAGE IN AN 'HttpResponse'
     fn error_response(&self) -> HttpResponse {
+        METRICS.record_error(&format!("{:?}", self));
+        warn!(error = ?self, status = self.status_code().as_u16(), "request failed");
         HttpResponse::build(self.status_code())
             .insert_header(ContentType::html())
             .body(self.to_string())
@@ -58,6 +179,7 @@ impl ResponseError for BlockChainError {
             BlockChainError::InvalidAmount => StatusCode::FAILED_DEPENDENCY,
             BlockChainError::EmptyAddress => StatusCode::NOT_FOUND,
             BlockChainError::MiningAddress => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::EscrowAddress => StatusCode::FAILED_DEPENDENCY,
             BlockChainError::NegativeBalance => StatusCode::FAILED_DEPENDENCY,
             BlockChainError::EmptyPassword => StatusCode::FAILED_DEPENDENCY,
             BlockChainError::NoPendingTransactions => StatusCode::FAILED_DEPENDENCY,
@@ -66,10 +188,58 @@ impl ResponseError for BlockChainError {
             BlockChainError::InvalidToAddress => StatusCode::FAILED_DEPENDENCY,
             BlockChainError::InvalidFromAddress => StatusCode::FAILED_DEPENDENCY,
             BlockChainError::NotEnoughCoins => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::PendingSpendConflict => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::BalanceOverflow => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::MemoTooLong => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidMetadata => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::TransactionExpired => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidNonce => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::ReplacementFeeTooLow => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::TransactionNotPending => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::EmptyBatch => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::BatchTooLarge => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidMultisig => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::NotMultisigWallet => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::MultisigApprovalRequired => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::NotAnOwner => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidProposalLifetime => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::AlreadyApproved => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::ProposalNotPending => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::ProposalExpired => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidHashLock => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::HashLockTimedOut => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::EscrowNotMined => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::EscrowSettled => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::WrongPreimage => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::EscrowNotTimedOut => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidStandingOrder => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::StandingOrderNotActive => StatusCode::FAILED_DEPENDENCY,
             BlockChainError::NegativeAmount => StatusCode::FAILED_DEPENDENCY,
             BlockChainError::WalletAlreadyExists => StatusCode::FAILED_DEPENDENCY,
             BlockChainError::WalletNotFound => StatusCode::NOT_FOUND,
             BlockChainError::ChainIsEmpty => StatusCode::NOT_FOUND,
+            BlockChainError::BlockNotFound => StatusCode::NOT_FOUND,
+            BlockChainError::TransactionNotFound => StatusCode::NOT_FOUND,
+            BlockChainError::ProposalNotFound => StatusCode::NOT_FOUND,
+            BlockChainError::EscrowNotFound => StatusCode::NOT_FOUND,
+            BlockChainError::StandingOrderNotFound => StatusCode::NOT_FOUND,
+            BlockChainError::InvalidBlockRange => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidCursor => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidPageSize => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidEventType => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::InvalidChain => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            BlockChainError::InvalidSnapshot => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::UnsupportedSnapshotVersion => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::ChainIdMismatch => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::Unauthorized => StatusCode::UNAUTHORIZED,
+            BlockChainError::InvalidIdempotencyKey => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::IdempotencyKeyReused => StatusCode::CONFLICT,
+            BlockChainError::IdempotencyKeyInProgress => StatusCode::CONFLICT,
+            BlockChainError::InvalidLog => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::ReplayMismatch => StatusCode::FAILED_DEPENDENCY,
+            BlockChainError::LogFailed => StatusCode::INTERNAL_SERVER_ERROR,
+            BlockChainError::BlockingFailed => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -0,0 +1,68 @@
+use super::block::Block;
+use super::query::HistoryPosition;
+use std::collections::HashMap;
+
+//SECONDARY INDEXES OVER THE MINED BLOCKS. THEY ONLY CONTAIN INFORMATION THAT CAN BE FOUND IN THE CHAIN, SO THEY CAN ALWAYS BE REBUILT FROM IT
+#[derive(Default)]
+pub struct ChainIndex {
+    //THE HEIGHT OF THE BLOCK THAT CONTAINS EACH TRANSACTION
+    transactions: HashMap<String, usize>,
+    //THE POSITIONS OF THE TRANSACTIONS THAT SENT OR RECEIVED COINS FROM EACH ADDRESS, IN THE ORDER OF THE CHAIN
+    addresses: HashMap<String, Vec<HistoryPosition>>,
+    //THE ID OF THE CLAIM OR THE REFUND THAT SETTLED EACH ESCROW
+    settlements: HashMap<String, String>,
+}
+
+impl ChainIndex {
+    //BUILD THE INDEXES FROM ALL THE BLOCKS OF A CHAIN
+    pub fn build(chain: &[Block]) -> Self {
+        let mut index = ChainIndex::default();
+        for block in chain {
+            index.add_block(block);
+        }
+        index
+    }
+
+    //ADD THE TRANSACTIONS OF A NEWLY MINED BLOCK TO THE INDEXES
+    pub fn add_block(&mut self, block: &Block) {
+        for (position, transaction) in block.transactions.iter().enumerate() {
+            let position = HistoryPosition {
+                block_height: block.index,
+                index: position,
+            };
+
+            self.transactions
+                .insert(transaction.id.clone(), block.index);
+            if let Some(settlement) = &transaction.settlement {
+                self.settlements
+                    .insert(settlement.escrow.clone(), transaction.id.clone());
+            }
+
+            self.addresses
+                .entry(transaction.from_wallet.address.clone())
+                .or_default()
+                .push(position);
+            if transaction.to_wallet.address != transaction.from_wallet.address {
+                self.addresses
+                    .entry(transaction.to_wallet.address.clone())
+                    .or_default()
+                    .push(position);
+            }
+        }
+    }
+
+    //RETURN THE HEIGHT OF THE BLOCK THAT CONTAINS THE TRANSACTION
+    pub fn block_height(&self, id: &str) -> Option<usize> {
+        self.transactions.get(id).copied()
+    }
+
+    //RETURN THE ID OF THE MINED CLAIM OR REFUND OF THE ESCROW
+    pub fn settlement(&self, escrow: &str) -> Option<&str> {
+        self.settlements.get(escrow).map(String::as_str)
+    }
+
+    //RETURN THE POSITIONS OF THE MINED TRANSACTIONS THAT TOUCH THE ADDRESS, OLDEST FIRST
+    pub fn positions(&self, address: &str) -> &[HistoryPosition] {
+        self.addresses.get(address).map_or(&[], Vec::as_slice)
+    }
+}
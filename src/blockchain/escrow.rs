@@ -0,0 +1,132 @@
+use super::block::Block;
+use super::block_chain_errors::BlockChainError;
+use super::transaction::{LockTime, Transaction};
+use crate::logging::REDACTED;
+use crypto_hash::{hex_digest, Algorithm};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use tracing::warn;
+use utoipa::ToSchema;
+
+//THE ADDRESS RESERVED FOR THE CLAIMS AND THE REFUNDS OF THE ESCROWS, THE COINS OF AN ESCROW ARE HELD THERE UNTIL IT IS SETTLED
+pub const ESCROW_ADDRESS: &str = "ESCROW";
+
+//THE CONDITION OF AN ESCROW. THE RECIPIENT CAN CLAIM THE COINS WITH THE PREIMAGE OF 'hash', THE HEX SHA-256 DIGEST OF IT
+//ONCE 'timeout' HAS PASSED THE SENDER CAN TAKE THE COINS BACK, AS LONG AS THE RECIPIENT HAS NOT CLAIMED THEM
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct HashLock {
+    pub hash: String,
+    pub timeout: LockTime,
+}
+
+//WHAT A CLAIM OR A REFUND SETTLES. 'escrow' IS THE ID OF THE ESCROW TRANSACTION, A CLAIM HAS THE PREIMAGE AND A REFUND DOESN'T
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Settlement {
+    pub escrow: String,
+    pub preimage: Option<String>,
+}
+
+//CONTAINS THE INFORMATION THAT ARE REQUIRED WHEN THE RECIPIENT OF AN ESCROW CLAIMS IT
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct ClaimInfo {
+    pub password: String,
+    pub preimage: String,
+}
+
+//RETURN THE HEX SHA-256 DIGEST OF A PREIMAGE
+pub fn hash_of(preimage: &str) -> String {
+    hex_digest(Algorithm::SHA256, preimage.as_bytes())
+}
+
+impl HashLock {
+    //CHECK IF THE HASH IS A HEX SHA-256 DIGEST
+    pub fn is_valid(&self) -> bool {
+        self.hash.len() == 64
+            && self
+                .hash
+                .chars()
+                .all(|char| char.is_ascii_digit() || ('a'..='f').contains(&char))
+    }
+
+    //CHECK IF THE PREIMAGE OPENS THE LOCK
+    pub fn is_opened_by(&self, preimage: &str) -> bool {
+        hash_of(preimage) == self.hash
+    }
+
+    //CHECK IF THE SENDER CAN TAKE THE COINS BACK AT THE GIVEN TIME WITH A REFUND THAT GOES INTO THE BLOCK AT THE GIVEN HEIGHT
+    pub fn is_timed_out(&self, now: u64, height: usize) -> bool {
+        self.timeout.is_unlocked(now, height)
+    }
+}
+
+impl Settlement {
+    //CHECK IF THE TRANSACTION THAT CARRIES THE SETTLEMENT, MINED AT THE GIVEN HEIGHT, SETTLES THE ESCROW
+    //IT PAYS THE WHOLE AMOUNT TO THE RECIPIENT WITH THE PREIMAGE, OR BACK TO THE SENDER AFTER THE TIMEOUT
+    pub fn settles(&self, escrow: &Transaction, transaction: &Transaction, height: usize) -> bool {
+        let Some(hash_lock) = &escrow.hash_lock else {
+            return false;
+        };
+        let paid = transaction.amount == escrow.amount
+            && transaction.fee == 0
+            && transaction.lock.is_none()
+            && transaction.hash_lock.is_none();
+
+        paid && match &self.preimage {
+            Some(preimage) => {
+                transaction.to_wallet.address == escrow.to_wallet.address
+                    && hash_lock.is_opened_by(preimage)
+            }
+            None => {
+                transaction.to_wallet.address == escrow.from_wallet.address
+                    && hash_lock.is_timed_out(transaction.timestamp, height)
+            }
+        }
+    }
+}
+
+//CHECK THAT EVERY TRANSACTION FROM THE ESCROW ADDRESS SETTLES AN ESCROW MINED IN AN EARLIER BLOCK, AND THAT EVERY ESCROW IS SETTLED AT MOST ONCE
+pub fn validate_settlements(blocks: &[Block]) -> Result<(), BlockChainError> {
+    let mut escrows: HashMap<&str, &Transaction> = HashMap::new();
+    let mut settled: HashSet<&str> = HashSet::new();
+
+    for block in blocks {
+        for transaction in &block.transactions {
+            let from_escrow = transaction.from_wallet.address == ESCROW_ADDRESS;
+            let valid = match (&transaction.settlement, from_escrow) {
+                (None, false) => true,
+                (Some(settlement), true) => {
+                    escrows
+                        .get(settlement.escrow.as_str())
+                        .is_some_and(|escrow| settlement.settles(escrow, transaction, block.index))
+                        && settled.insert(&settlement.escrow)
+                }
+                _ => false,
+            };
+
+            if !valid {
+                warn!(block_height = block.index, id = %transaction.id, "invalid settlement");
+                return Err(BlockChainError::InvalidChain);
+            }
+        }
+
+        //THE ESCROWS OF A BLOCK CAN BE SETTLED FROM THE NEXT ONE
+        for transaction in &block.transactions {
+            if transaction.hash_lock.is_some() {
+                escrows.insert(&transaction.id, transaction);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//THE PASSWORD IS NEVER SHOWN WHEN THE CLAIM IS PRINTED OR LOGGED
+impl fmt::Debug for ClaimInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClaimInfo")
+            .field("password", &REDACTED)
+            .field("preimage", &self.preimage)
+            .finish()
+    }
+}
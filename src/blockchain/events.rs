@@ -0,0 +1,159 @@
+use super::block::BlockDetails;
+use super::block_chain_errors::BlockChainError;
+use super::mempool::EvictionReason;
+use super::transaction::TransactionDetails;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+//THE NUMBER OF EVENTS KEPT FOR SLOW SUBSCRIBERS BEFORE THEY START MISSING EVENTS
+pub const EVENT_BUFFER_SIZE: usize = 1024;
+
+//THE THINGS THAT HAPPEN IN THE BLOCKCHAIN AND ARE SENT TO THE SUBSCRIBERS
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    PendingTransaction {
+        transaction: TransactionDetails,
+    },
+    BlockMined {
+        block: BlockDetails,
+    },
+    BalanceChanged {
+        address: String,
+        balance: u32,
+    },
+    TransactionEvicted {
+        transaction: TransactionDetails,
+        reason: EvictionReason,
+    },
+    //THE WHOLE STATE WAS REPLACED BY AN IMPORTED SNAPSHOT, 'block' IS THE NEW LAST BLOCK
+    ChainReplaced {
+        block: BlockDetails,
+    },
+}
+
+//THE TYPES OF EVENTS, USED TO CHOOSE WHAT A SUBSCRIPTION RECEIVES
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    PendingTransaction,
+    BlockMined,
+    BalanceChanged,
+    TransactionEvicted,
+    ChainReplaced,
+}
+
+//THE EVENTS THAT A SUBSCRIPTION RECEIVES
+//WITHOUT AN ADDRESS ALL THE EVENTS ARE RECEIVED. BALANCES ARE ONLY SENT FOR THE ADDRESS THE SUBSCRIBER AUTHENTICATED WITH
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    pub address: Option<String>,
+    pub authenticated: bool,
+    pub types: Option<Vec<EventType>>,
+}
+
+//SENDS THE EVENTS TO ALL THE SUBSCRIBERS
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+        EventBus { sender }
+    }
+}
+
+impl EventBus {
+    //SEND AN EVENT, IT IS DROPPED IF NOBODY IS SUBSCRIBED
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    //RETURN A RECEIVER FOR ALL THE EVENTS PUBLISHED FROM NOW ON
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Event {
+    //RETURN THE TYPE OF THE EVENT
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::PendingTransaction { .. } => EventType::PendingTransaction,
+            Event::BlockMined { .. } => EventType::BlockMined,
+            Event::BalanceChanged { .. } => EventType::BalanceChanged,
+            Event::TransactionEvicted { .. } => EventType::TransactionEvicted,
+            Event::ChainReplaced { .. } => EventType::ChainReplaced,
+        }
+    }
+
+    //CHECK IF THE EVENT IS ABOUT THE GIVEN ADDRESS, A REPLACED CHAIN IS ABOUT ALL OF THEM
+    pub fn touches(&self, address: &str) -> bool {
+        let touches = |transaction: &TransactionDetails| {
+            transaction.from_address == address || transaction.to_address == address
+        };
+
+        match self {
+            Event::PendingTransaction { transaction } => touches(transaction),
+            Event::TransactionEvicted { transaction, .. } => touches(transaction),
+            Event::BlockMined { block } => block.transactions.iter().any(touches),
+            Event::BalanceChanged {
+                address: changed, ..
+            } => changed == address,
+            Event::ChainReplaced { .. } => true,
+        }
+    }
+}
+
+impl EventType {
+    //RETURN THE NAME OF THE TYPE, THE SAME ONE THAT IS USED IN THE 'type' FIELD OF THE EVENTS
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventType::PendingTransaction => "pending_transaction",
+            EventType::BlockMined => "block_mined",
+            EventType::BalanceChanged => "balance_changed",
+            EventType::TransactionEvicted => "transaction_evicted",
+            EventType::ChainReplaced => "chain_replaced",
+        }
+    }
+}
+
+impl FromStr for EventType {
+    type Err = BlockChainError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        [
+            EventType::PendingTransaction,
+            EventType::BlockMined,
+            EventType::BalanceChanged,
+            EventType::TransactionEvicted,
+            EventType::ChainReplaced,
+        ]
+        .into_iter()
+        .find(|event_type| event_type.name() == name)
+        .ok_or(BlockChainError::InvalidEventType)
+    }
+}
+
+impl EventFilter {
+    //CHECK IF THE EVENT SHOULD BE SENT TO THE SUBSCRIBER
+    pub fn matches(&self, event: &Event) -> bool {
+        if let Some(types) = &self.types {
+            if !types.contains(&event.event_type()) {
+                return false;
+            }
+        }
+
+        match (event, &self.address) {
+            (Event::BalanceChanged { .. }, Some(address)) => {
+                self.authenticated && event.touches(address)
+            }
+            (Event::BalanceChanged { .. }, None) => false,
+            (_, Some(address)) => event.touches(address),
+            (_, None) => true,
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use super::block_chain::MINING_ADDRESS;
+use super::transaction::{Transaction, TransactionDetails};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::slice;
+use tracing::warn;
+use utoipa::ToSchema;
+
+//THE NUMBER OF EVICTED TRANSACTIONS THAT ARE REMEMBERED, THE OLDEST ARE FORGOTTEN FIRST
+pub const MAX_EVICTIONS: usize = 1000;
+
+//THE NUMBER OF PENDING TRANSACTIONS KEPT WHEN NO LIMIT IS GIVEN
+pub const DEFAULT_MAX_TRANSACTIONS: usize = 10_000;
+
+//THE MEMORY THE PENDING TRANSACTIONS CAN USE WHEN NO LIMIT IS GIVEN, 16 MiB
+pub const DEFAULT_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+//HOW BIG THE MEMPOOL CAN GROW BEFORE THE TRANSACTIONS WITH THE LOWEST FEES ARE EVICTED
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MempoolLimits {
+    pub max_transactions: usize,
+    pub max_bytes: usize,
+}
+
+//WHY A PENDING TRANSACTION WAS REMOVED WITHOUT BEING MINED
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionReason {
+    //THE AMOUNT IS 0
+    InvalidAmount,
+    //THE 'FROM' WALLET DOESN'T EXIST
+    UnknownSender,
+    //THE 'TO' WALLET DOESN'T EXIST
+    UnknownRecipient,
+    //THE COINS TAKEN FROM THE SENDER FOR ITS PENDING TRANSACTIONS DON'T COVER THIS ONE
+    Overdraft,
+    //A TRANSACTION WITH THE SAME ID WAS ALREADY MINED OR COMES BEFORE IT
+    Duplicate,
+    //A MINER REWARD WITH LESS THAN THE MINING REWARD
+    InvalidReward,
+    //THE EXPIRY OF THE TRANSACTION WAS REACHED BEFORE IT WAS MINED
+    Expired,
+    //THE MEMPOOL WAS FULL AND THE TRANSACTION HAD ONE OF THE LOWEST FEES
+    LowFee,
+    //THE SENDER CANCELLED THE TRANSACTION
+    Cancelled,
+    //THE SENDER REPLACED THE TRANSACTION WITH ONE WITH THE SAME NONCE AND A HIGHER FEE
+    Replaced,
+    //THE 'TO' WALLET WOULD HOLD MORE THAN u32::MAX COINS
+    BalanceOverflow,
+}
+
+//A TRANSACTION THAT WAS REMOVED FROM PENDING TRANSACTIONS AND WHY
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Eviction {
+    pub transaction: TransactionDetails,
+    pub reason: EvictionReason,
+}
+
+//THE PENDING TRANSACTIONS, IN THE ORDER THEY WILL BE MINED
+//THE COINS OF A TRANSACTION ARE TAKEN FROM THE SENDER WHEN IT IS CREATED, 'pending_spend' IS HOW MANY COINS OF EVERY SENDER ARE WAITING TO BE MINED
+#[derive(Clone, Default)]
+pub struct Mempool {
+    transactions: Vec<Transaction>,
+    pending_spend: HashMap<String, u64>,
+    evictions: VecDeque<Eviction>,
+    bytes: usize,
+    limits: MempoolLimits,
+}
+
+impl Default for MempoolLimits {
+    fn default() -> Self {
+        MempoolLimits {
+            max_transactions: DEFAULT_MAX_TRANSACTIONS,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+impl MempoolLimits {
+    //READ THE LIMITS FROM 'MEMPOOL_MAX_TRANSACTIONS' AND 'MEMPOOL_MAX_BYTES', THE DEFAULTS ARE USED FOR THE MISSING ONES
+    pub fn from_env() -> Self {
+        let limit = |name: &str, default: usize| match env::var(name) {
+            Ok(value) => value.parse().unwrap_or_else(|_| {
+                warn!(name, value, "invalid mempool limit, using the default one");
+                default
+            }),
+            Err(_) => default,
+        };
+
+        MempoolLimits {
+            max_transactions: limit("MEMPOOL_MAX_TRANSACTIONS", DEFAULT_MAX_TRANSACTIONS),
+            max_bytes: limit("MEMPOOL_MAX_BYTES", DEFAULT_MAX_BYTES),
+        }
+    }
+}
+
+impl Mempool {
+    //CREATE A MEMPOOL WITH TRANSACTIONS WHOSE COINS WERE ALREADY TAKEN FROM THEIR SENDERS
+    pub fn new(transactions: Vec<Transaction>, limits: MempoolLimits) -> Self {
+        let mut mempool = Mempool {
+            limits,
+            ..Mempool::default()
+        };
+        for transaction in transactions {
+            mempool.push(transaction);
+        }
+        mempool
+    }
+
+    //RETURN HOW BIG THE MEMPOOL CAN GROW
+    pub fn limits(&self) -> MempoolLimits {
+        self.limits
+    }
+
+    //CHANGE HOW BIG THE MEMPOOL CAN GROW, THE TRANSACTIONS OVER THE NEW LIMITS ARE EVICTED BY THE NEXT SWEEP
+    pub fn set_limits(&mut self, limits: MempoolLimits) {
+        self.limits = limits;
+    }
+
+    //RETURN THE ESTIMATED MEMORY USED BY THE PENDING TRANSACTIONS
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    //RETURN THE NUMBER OF PENDING TRANSACTIONS
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    //CHECK IF THERE ARE NO PENDING TRANSACTIONS
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    //RETURN THE PENDING TRANSACTIONS, OLDEST FIRST
+    pub fn iter(&self) -> slice::Iter<'_, Transaction> {
+        self.transactions.iter()
+    }
+
+    //RETURN THE PENDING TRANSACTION SENT FROM THE ADDRESS WITH THE GIVEN NONCE, THE MINER REWARDS HAVE NO NONCE
+    pub fn find_by_nonce(&self, address: &str, nonce: u64) -> Option<&Transaction> {
+        self.transactions.iter().find(|transaction| {
+            transaction.from_wallet.address == address
+                && address != MINING_ADDRESS
+                && transaction.nonce == nonce
+        })
+    }
+
+    //RETURN THE COINS OF THE ADDRESS THAT ARE WAITING TO BE MINED
+    pub fn pending_spend(&self, address: &str) -> u64 {
+        self.pending_spend.get(address).copied().unwrap_or(0)
+    }
+
+    //ADD A TRANSACTION AT THE END
+    pub fn push(&mut self, transaction: Transaction) {
+        self.reserve(&transaction);
+        self.bytes += transaction.size();
+        self.transactions.push(transaction);
+    }
+
+    //ADD A TRANSACTION THAT IS MINED BEFORE ALL THE OTHERS, USED FOR THE MINER REWARD
+    pub fn push_front(&mut self, transaction: Transaction) {
+        self.reserve(&transaction);
+        self.bytes += transaction.size();
+        self.transactions.insert(0, transaction);
+    }
+
+    //REMOVE THE FIRST TRANSACTIONS, THEY WERE MINED
+    pub fn take(&mut self, count: usize) -> Vec<Transaction> {
+        let taken: Vec<Transaction> = self.transactions.drain(..count).collect();
+        for transaction in &taken {
+            self.release(transaction);
+            self.bytes -= transaction.size();
+        }
+        taken
+    }
+
+    //REMOVE A TRANSACTION WITHOUT MINING IT AND REMEMBER WHY
+    //RETURN THE TRANSACTION AND THE COINS THAT WERE TAKEN FROM ITS SENDER FOR IT, THEY HAVE TO BE GIVEN BACK
+    pub fn evict(&mut self, id: &str, reason: EvictionReason) -> Option<(Transaction, u32)> {
+        //THE LAST TRANSACTION WITH THE ID IS THE ONE EVICTED, SO A DUPLICATE DOESN'T REMOVE THE ORIGINAL
+        let position = self
+            .transactions
+            .iter()
+            .rposition(|transaction| transaction.id == id)?;
+        let transaction = self.transactions.remove(position);
+        let refund = self.release(&transaction);
+        self.bytes -= transaction.size();
+        self.remember(transaction.details(), reason);
+
+        Some((transaction, refund))
+    }
+
+    //PUT A TRANSACTION IN THE PLACE OF THE PENDING ONE WITH THE GIVEN ID, THE OLD ONE IS REMEMBERED AS REPLACED
+    //RETURN THE OLD TRANSACTION AND THE COINS THAT WERE TAKEN FROM ITS SENDER FOR IT
+    pub fn replace(&mut self, id: &str, transaction: Transaction) -> Option<(Transaction, u32)> {
+        let position = self
+            .transactions
+            .iter()
+            .position(|pending| pending.id == id)?;
+
+        self.reserve(&transaction);
+        self.bytes += transaction.size();
+        let replaced = std::mem::replace(&mut self.transactions[position], transaction);
+        let refund = self.release(&replaced);
+        self.bytes -= replaced.size();
+        self.remember(replaced.details(), EvictionReason::Replaced);
+
+        Some((replaced, refund))
+    }
+
+    //CHOOSE THE TRANSACTIONS TO EVICT SO THE MEMPOOL IS BACK UNDER ITS LIMITS, WITHOUT COUNTING THE ONES ALREADY BEING EVICTED
+    //THE LOWEST FEES GO FIRST AND, FOR THE SAME FEE, THE NEWEST. THE MINER REWARDS ARE NEVER CHOSEN
+    pub fn over_limits(&self, evicted: &[Eviction]) -> Vec<Eviction> {
+        let evicted: HashSet<&str> = evicted
+            .iter()
+            .map(|eviction| eviction.transaction.id.as_str())
+            .collect();
+        let kept = self
+            .transactions
+            .iter()
+            .filter(|transaction| !evicted.contains(transaction.id.as_str()));
+
+        let mut count = 0;
+        let mut bytes = 0;
+        let mut candidates = vec![];
+        for (position, transaction) in kept.enumerate() {
+            count += 1;
+            bytes += transaction.size();
+            if transaction.from_wallet.address != MINING_ADDRESS {
+                candidates.push((position, transaction));
+            }
+        }
+        candidates.sort_by_key(|(position, transaction)| (transaction.fee, usize::MAX - position));
+
+        let mut evictions = vec![];
+        for (_, transaction) in candidates {
+            if count <= self.limits.max_transactions && bytes <= self.limits.max_bytes {
+                break;
+            }
+            count -= 1;
+            bytes -= transaction.size();
+            evictions.push(Eviction {
+                transaction: transaction.details(),
+                reason: EvictionReason::LowFee,
+            });
+        }
+        evictions
+    }
+
+    //RETURN WHY THE TRANSACTION WAS EVICTED, IF IT WAS
+    pub fn eviction(&self, id: &str) -> Option<&Eviction> {
+        self.evictions
+            .iter()
+            .rev()
+            .find(|eviction| eviction.transaction.id == id)
+    }
+
+    //REMEMBER WHY THE TRANSACTION WAS REMOVED, THE OLDEST EVICTIONS ARE FORGOTTEN FIRST
+    fn remember(&mut self, transaction: TransactionDetails, reason: EvictionReason) {
+        if self.evictions.len() == MAX_EVICTIONS {
+            self.evictions.pop_front();
+        }
+        self.evictions.push_back(Eviction {
+            transaction,
+            reason,
+        });
+    }
+
+    //COUNT THE COINS OF THE TRANSACTION AS WAITING TO BE MINED, THE MINER REWARDS DON'T COME FROM A WALLET
+    fn reserve(&mut self, transaction: &Transaction) {
+        if transaction.from_wallet.address != MINING_ADDRESS {
+            *self
+                .pending_spend
+                .entry(transaction.from_wallet.address.clone())
+                .or_insert(0) += transaction.cost() as u64;
+        }
+    }
+
+    //STOP COUNTING THE COINS OF THE TRANSACTION AND RETURN HOW MANY WERE COUNTED
+    fn release(&mut self, transaction: &Transaction) -> u32 {
+        let address = &transaction.from_wallet.address;
+        let Some(spend) = self.pending_spend.get_mut(address) else {
+            return 0;
+        };
+
+        //NEVER MORE THAN THE COST OF THE TRANSACTION, SO IT FITS IN A u32
+        let released = (*spend).min(transaction.cost() as u64);
+        *spend -= released;
+        if *spend == 0 {
+            self.pending_spend.remove(address);
+        }
+        released as u32
+    }
+}
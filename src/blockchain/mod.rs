@@ -1,5 +1,15 @@
+pub mod batch;
 pub mod block;
 pub mod block_chain;
+pub mod block_chain_errors;
+pub mod chain_index;
+pub mod escrow;
+pub mod events;
+pub mod mempool;
+pub mod multisig;
+pub mod query;
+pub mod snapshot;
+pub mod standing_order;
 pub mod transaction;
 pub mod wallet;
-pub mod block_chain_errors;
\ No newline at end of file
+pub mod write_ahead_log;
@@ -0,0 +1,320 @@
+use super::block_chain::MINING_ADDRESS;
+use super::block_chain_errors::BlockChainError;
+use super::escrow::ESCROW_ADDRESS;
+use super::transaction::{Metadata, TransactionInfo};
+use super::wallet::Wallet;
+use crate::logging::REDACTED;
+use crypto_hash::{hex_digest, Algorithm};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use utoipa::{IntoParams, ToSchema};
+
+//THE MOST OWNERS A MULTISIGNATURE WALLET CAN HAVE
+pub const MAX_OWNERS: usize = 20;
+
+//HOW LONG THE OWNERS HAVE TO APPROVE A PROPOSAL BY DEFAULT AND AT MOST, IN SECONDS
+pub const DEFAULT_PROPOSAL_LIFETIME: u64 = 24 * 60 * 60;
+pub const MAX_PROPOSAL_LIFETIME: u64 = 30 * 24 * 60 * 60;
+
+//COUNTER USED TO KEEP THE IDS UNIQUE WHEN TWO IDENTICAL PROPOSALS ARE CREATED AT THE SAME TIME
+static PROPOSAL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+//THE OWNERS OF A MULTISIGNATURE WALLET AND HOW MANY OF THEM MUST APPROVE A TRANSFER FROM IT
+//THE OWNERS ARE OTHER WALLETS, THEY APPROVE WITH THEIR OWN PASSWORDS
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Multisig {
+    pub owners: Vec<String>,
+    pub threshold: usize,
+}
+
+//CONTAINS THE INFORMATION THAT ARE REQUIRED WHEN CREATING A MULTISIGNATURE WALLET
+//THE PASSWORD OF THE WALLET CAN SEE ITS BALANCE AND HISTORY AND ADD COINS TO IT, BUT NOT SEND COINS FROM IT
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct MultisigWalletInfo {
+    pub address: String,
+    pub password: String,
+    pub owners: Vec<String>,
+    pub threshold: usize,
+}
+
+//THE ADDRESS, THE BALANCE, THE OWNERS AND THE THRESHOLD OF A MULTISIGNATURE WALLET
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct MultisigWalletDetails {
+    pub address: String,
+    pub balance: u32,
+    pub owners: Vec<String>,
+    pub threshold: usize,
+}
+
+//AN OWNER OF A MULTISIGNATURE WALLET AND THE PASSWORD OF ITS OWN WALLET
+#[derive(Deserialize, Serialize, Clone, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OwnerCredentials {
+    pub owner: String,
+    pub password: String,
+}
+
+//CONTAINS THE INFORMATION THAT ARE REQUIRED WHEN AN OWNER PROPOSES A TRANSFER FROM A MULTISIGNATURE WALLET
+//THE PROPOSAL COUNTS AS THE APPROVAL OF THE OWNER. 'expires_in' IS HOW MANY SECONDS THE OTHER OWNERS HAVE TO APPROVE IT
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct ProposalInfo {
+    pub owner: String,
+    pub password: String,
+    pub to_address: String,
+    pub amount: u32,
+    #[serde(default)]
+    pub fee: u32,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+}
+
+//AN APPROVAL OF A PROPOSAL, WHICH OWNER GAVE IT AND WHEN
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Approval {
+    pub owner: String,
+    pub timestamp: u64,
+}
+
+//WHERE A PROPOSAL IS. A PENDING PROPOSAL THAT WAS NOT EXECUTED BEFORE 'expires_at' IS EXPIRED
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ProposalStatus {
+    Pending,
+    Executed,
+    Expired,
+}
+
+//A TRANSFER PROPOSED BY AN OWNER OF A MULTISIGNATURE WALLET AND THE APPROVALS IT COLLECTED, IN THE ORDER THEY WERE GIVEN
+//WHEN THE APPROVALS REACH THE THRESHOLD THE TRANSFER IS ADDED TO PENDING TRANSACTIONS AND 'transaction_id' IS ITS ID
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Proposal {
+    pub id: String,
+    pub wallet: String,
+    pub proposer: String,
+    pub to_address: String,
+    pub amount: u32,
+    pub fee: u32,
+    pub memo: Option<String>,
+    pub metadata: Metadata,
+    pub threshold: usize,
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub status: ProposalStatus,
+    pub approvals: Vec<Approval>,
+    pub transaction_id: Option<String>,
+}
+
+impl MultisigWalletInfo {
+    //CHECK IF THE WALLET INFORMATION ARE VALID, THE OWNERS MUST BE DIFFERENT AND THE THRESHOLD CAN'T BE MORE THAN THEIR NUMBER
+    #[tracing::instrument(level = "debug", skip_all, err(level = "debug", Display))]
+    pub fn check_multisig_wallet_info(&self) -> Result<(), BlockChainError> {
+        if self.address.is_empty() {
+            return Err(BlockChainError::EmptyAddress);
+        }
+
+        if self.address == MINING_ADDRESS {
+            return Err(BlockChainError::MiningAddress);
+        }
+
+        if self.address == ESCROW_ADDRESS {
+            return Err(BlockChainError::EscrowAddress);
+        }
+
+        if self.password.is_empty() {
+            return Err(BlockChainError::EmptyPassword);
+        }
+
+        let repeated = self
+            .owners
+            .iter()
+            .enumerate()
+            .any(|(position, owner)| self.owners[..position].contains(owner));
+        if self.owners.is_empty()
+            || self.owners.len() > MAX_OWNERS
+            || repeated
+            || self.owners.contains(&self.address)
+            || self.threshold == 0
+            || self.threshold > self.owners.len()
+        {
+            return Err(BlockChainError::InvalidMultisig);
+        }
+
+        Ok(())
+    }
+}
+
+impl MultisigWalletDetails {
+    //RETURN THE DETAILS OF THE WALLET, IF IT IS A MULTISIGNATURE WALLET, WITH WHAT IT CAN SPEND AT THE GIVEN TIME AND HEIGHT
+    pub fn of(wallet: &Wallet, now: u64, height: usize) -> Option<Self> {
+        wallet
+            .multisig
+            .as_ref()
+            .map(|multisig| MultisigWalletDetails {
+                address: wallet.address.clone(),
+                balance: wallet.balance(now, height).balance,
+                owners: multisig.owners.clone(),
+                threshold: multisig.threshold,
+            })
+    }
+}
+
+impl ProposalInfo {
+    //RETURN HOW LONG THE OWNERS HAVE TO APPROVE THE PROPOSAL
+    pub fn lifetime(&self) -> Result<u64, BlockChainError> {
+        match self.expires_in {
+            Some(seconds) if seconds == 0 || seconds > MAX_PROPOSAL_LIFETIME => {
+                Err(BlockChainError::InvalidProposalLifetime)
+            }
+            Some(seconds) => Ok(seconds),
+            None => Ok(DEFAULT_PROPOSAL_LIFETIME),
+        }
+    }
+}
+
+impl Proposal {
+    //CREATE A NEW PROPOSAL WITH A UNIQUE ID, APPROVED BY THE OWNER THAT PROPOSED IT AT THE GIVEN TIME
+    pub fn new(
+        wallet: &Wallet,
+        threshold: usize,
+        info: &ProposalInfo,
+        timestamp: u64,
+    ) -> Result<Self, BlockChainError> {
+        let lifetime = info.lifetime()?;
+        let created_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let counter = PROPOSAL_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let id = hex_digest(
+            Algorithm::SHA256,
+            format!(
+                "{}{}{}{}{}{}",
+                wallet.address,
+                info.owner,
+                info.to_address,
+                info.amount,
+                created_at.as_nanos(),
+                counter
+            )
+            .as_bytes(),
+        );
+
+        Ok(Proposal {
+            id,
+            wallet: wallet.address.clone(),
+            proposer: info.owner.clone(),
+            to_address: info.to_address.clone(),
+            amount: info.amount,
+            fee: info.fee,
+            memo: info.memo.clone(),
+            metadata: info.metadata.clone(),
+            threshold,
+            created_at: timestamp,
+            expires_at: timestamp + lifetime,
+            status: ProposalStatus::Pending,
+            approvals: vec![Approval {
+                owner: info.owner.clone(),
+                timestamp,
+            }],
+            transaction_id: None,
+        })
+    }
+
+    //CHECK IF THE PROPOSAL CAN NO LONGER BE APPROVED AT THE GIVEN TIME
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.status == ProposalStatus::Pending && now >= self.expires_at
+    }
+
+    //THE PROPOSAL AS IT IS SHOWN AT THE GIVEN TIME, A PENDING PROPOSAL PAST ITS EXPIRY IS SHOWN AS EXPIRED
+    pub fn as_of(&self, now: u64) -> Proposal {
+        let mut proposal = self.clone();
+        if proposal.is_expired(now) {
+            proposal.status = ProposalStatus::Expired;
+        }
+        proposal
+    }
+
+    //CHECK IF THE GIVEN OWNER ALREADY APPROVED THE PROPOSAL
+    pub fn is_approved_by(&self, owner: &str) -> bool {
+        self.approvals
+            .iter()
+            .any(|approval| approval.owner == owner)
+    }
+
+    //THE TRANSACTION THAT IS ADDED WHEN THE PROPOSAL IS APPROVED, IT IS CHECKED LIKE ANY OTHER TRANSACTION
+    pub fn transaction(&self, wallet: &Wallet) -> TransactionInfo {
+        TransactionInfo {
+            from_address: wallet.address.clone(),
+            from_password: wallet.password.clone(),
+            to_address: self.to_address.clone(),
+            amount: self.amount,
+            fee: self.fee,
+            expiry: None,
+            nonce: None,
+            memo: self.memo.clone(),
+            metadata: self.metadata.clone(),
+            lock: None,
+            hash_lock: None,
+        }
+    }
+}
+
+//CHECK THAT THE ADDRESS IS AN OWNER OF THE MULTISIGNATURE WALLET AND THAT THE PASSWORD IS THE ONE OF ITS OWN WALLET
+pub fn authenticate_owner(
+    multisig: &Multisig,
+    wallets: &[Wallet],
+    owner: &str,
+    password: &str,
+) -> Result<(), BlockChainError> {
+    if !multisig.owners.iter().any(|address| address == owner) {
+        return Err(BlockChainError::NotAnOwner);
+    }
+
+    match wallets.iter().find(|wallet| wallet.address == owner) {
+        Some(wallet) if wallet.password == password => Ok(()),
+        Some(_) => Err(BlockChainError::WrongPassword),
+        None => Err(BlockChainError::NotAnOwner),
+    }
+}
+
+//THE PASSWORDS ARE NEVER SHOWN WHEN THE REQUESTS ARE PRINTED OR LOGGED
+impl fmt::Debug for MultisigWalletInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultisigWalletInfo")
+            .field("address", &self.address)
+            .field("password", &REDACTED)
+            .field("owners", &self.owners)
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+impl fmt::Debug for OwnerCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnerCredentials")
+            .field("owner", &self.owner)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ProposalInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProposalInfo")
+            .field("owner", &self.owner)
+            .field("password", &REDACTED)
+            .field("to_address", &self.to_address)
+            .field("amount", &self.amount)
+            .field("fee", &self.fee)
+            .field("memo", &self.memo)
+            .field("metadata", &self.metadata)
+            .field("expires_in", &self.expires_in)
+            .finish()
+    }
+}
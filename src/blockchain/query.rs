@@ -0,0 +1,282 @@
+use super::block_chain_errors::BlockChainError;
+use super::transaction::{Metadata, TransactionDetails};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+//THE MAXIMUM NUMBER OF ITEMS THAT CAN BE RETURNED IN A PAGE
+pub const MAX_PAGE_SIZE: usize = 100;
+
+//THE NUMBER OF ITEMS RETURNED IN A PAGE WHEN NO LIMIT IS GIVEN
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+
+//A PAGE OF RESULTS. 'next_cursor' IS MISSING WHEN THERE ARE NO MORE RESULTS
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+//THE ORDER OF THE RESULTS, OLDEST FIRST OR NEWEST FIRST
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+//THE DIRECTION OF A TRANSACTION SEEN FROM A WALLET
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+//THE STATE OF A TRANSACTION, MINED INTO A BLOCK OR STILL WAITING IN PENDING TRANSACTIONS
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionState {
+    Confirmed,
+    Pending,
+}
+
+//THE FILTERS, THE ORDER AND THE PAGE USED WHEN LISTING BLOCKS. ALL THE FIELDS ARE OPTIONAL
+//'from' AND 'to' ARE BLOCK HEIGHTS, THE AMOUNTS, THE ADDRESS, THE MEMO AND THE METADATA MATCH BLOCKS THAT HAVE AT LEAST ONE SUCH TRANSACTION
+#[derive(Deserialize, Serialize, Clone, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BlockQuery {
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+    pub order: Option<SortOrder>,
+    pub from: Option<usize>,
+    pub to: Option<usize>,
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
+    pub min_amount: Option<u32>,
+    pub max_amount: Option<u32>,
+    pub address: Option<String>,
+    pub memo: Option<String>,
+    pub metadata_key: Option<String>,
+    pub metadata_value: Option<String>,
+}
+
+//THE FILTERS, THE ORDER AND THE PAGE USED WHEN LISTING THE TRANSACTIONS OF A WALLET. ALL THE FIELDS ARE OPTIONAL
+#[derive(Deserialize, Serialize, Clone, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+    pub order: Option<SortOrder>,
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
+    pub min_amount: Option<u32>,
+    pub max_amount: Option<u32>,
+    pub counterparty: Option<String>,
+    pub direction: Option<Direction>,
+    pub status: Option<TransactionState>,
+    pub memo: Option<String>,
+    pub metadata_key: Option<String>,
+    pub metadata_value: Option<String>,
+}
+
+//THE FILTERS ON THE MEMO AND THE METADATA OF THE TRANSACTIONS, A MISSING FILTER MATCHES EVERYTHING
+//THE MEMO MATCHES IF IT CONTAINS THE TEXT. THE METADATA MATCHES IF IT HAS THE KEY WITH THE VALUE, OR ONLY ONE OF THEM IF THE OTHER IS MISSING
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NotesFilter<'a> {
+    pub memo: Option<&'a str>,
+    pub metadata_key: Option<&'a str>,
+    pub metadata_value: Option<&'a str>,
+}
+
+impl NotesFilter<'_> {
+    //CHECK IF THERE IS ANY FILTER
+    pub fn is_empty(&self) -> bool {
+        self.memo.is_none() && self.metadata_key.is_none() && self.metadata_value.is_none()
+    }
+
+    //CHECK IF THE MEMO AND THE METADATA OF A TRANSACTION MATCH
+    pub fn matches(&self, memo: Option<&str>, metadata: &Metadata) -> bool {
+        let memo_matches = self
+            .memo
+            .is_none_or(|text| memo.is_some_and(|memo| memo.contains(text)));
+        let metadata_matches = match (self.metadata_key, self.metadata_value) {
+            (Some(key), value) => metadata
+                .get(key)
+                .is_some_and(|found| value.is_none_or(|value| found == value)),
+            (None, Some(value)) => metadata.values().any(|found| found == value),
+            (None, None) => true,
+        };
+
+        memo_matches && metadata_matches
+    }
+}
+
+impl BlockQuery {
+    //THE FILTERS ON THE MEMO AND THE METADATA
+    pub fn notes(&self) -> NotesFilter<'_> {
+        NotesFilter {
+            memo: self.memo.as_deref(),
+            metadata_key: self.metadata_key.as_deref(),
+            metadata_value: self.metadata_value.as_deref(),
+        }
+    }
+}
+
+impl HistoryQuery {
+    //THE FILTERS ON THE MEMO AND THE METADATA
+    pub fn notes(&self) -> NotesFilter<'_> {
+        NotesFilter {
+            memo: self.memo.as_deref(),
+            metadata_key: self.metadata_key.as_deref(),
+            metadata_value: self.metadata_value.as_deref(),
+        }
+    }
+}
+
+//A TRANSACTION FROM THE HISTORY OF A WALLET
+//'timestamp' IS THE TIME OF THE BLOCK FOR CONFIRMED TRANSACTIONS AND THE CREATION TIME FOR PENDING ONES
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct HistoryEntry {
+    pub transaction: TransactionDetails,
+    pub direction: Direction,
+    pub status: TransactionState,
+    pub block_height: Option<usize>,
+    pub timestamp: u64,
+}
+
+//THE POSITION OF A TRANSACTION IN THE HISTORY OF A WALLET. PENDING TRANSACTIONS COME AFTER ALL THE BLOCKS
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HistoryPosition {
+    pub block_height: usize,
+    pub index: usize,
+}
+
+impl HistoryPosition {
+    //THE POSITION OF A PENDING TRANSACTION
+    pub fn pending(index: usize) -> Self {
+        HistoryPosition {
+            block_height: usize::MAX,
+            index,
+        }
+    }
+}
+
+//A CURSOR IN THE HISTORY OF A WALLET. A PENDING TRANSACTION MOVES IN PENDING TRANSACTIONS WHEN BLOCKS ARE MINED OR TRANSACTIONS ARE EVICTED,
+//SO ITS CURSOR KEEPS THE ID OF THE TRANSACTION AND ITS PLACE IS FOUND AGAIN WHEN THE NEXT PAGE IS READ
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HistoryCursor {
+    Mined(HistoryPosition),
+    Pending { index: usize, id: String },
+}
+
+impl HistoryCursor {
+    pub fn encode(&self) -> String {
+        match self {
+            HistoryCursor::Mined(position) => position.encode(),
+            HistoryCursor::Pending { index, id } => format!("pending.{}.{}", index, id),
+        }
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, BlockChainError> {
+        match cursor.strip_prefix("pending.") {
+            Some(pending) => {
+                let (index, id) = pending
+                    .split_once('.')
+                    .ok_or(BlockChainError::InvalidCursor)?;
+                if id.is_empty() {
+                    return Err(BlockChainError::InvalidCursor);
+                }
+
+                Ok(HistoryCursor::Pending {
+                    index: usize::decode(index)?,
+                    id: id.to_string(),
+                })
+            }
+            None => HistoryPosition::decode(cursor).map(HistoryCursor::Mined),
+        }
+    }
+}
+
+//A VALUE THAT CAN BE USED AS A CURSOR
+pub trait Cursor: Sized + Copy + Ord {
+    fn encode(&self) -> String;
+    fn decode(cursor: &str) -> Result<Self, BlockChainError>;
+}
+
+impl Cursor for usize {
+    fn encode(&self) -> String {
+        self.to_string()
+    }
+
+    fn decode(cursor: &str) -> Result<Self, BlockChainError> {
+        cursor.parse().map_err(|_| BlockChainError::InvalidCursor)
+    }
+}
+
+impl Cursor for HistoryPosition {
+    fn encode(&self) -> String {
+        format!("{}.{}", self.block_height, self.index)
+    }
+
+    fn decode(cursor: &str) -> Result<Self, BlockChainError> {
+        let (block_height, index) = cursor
+            .split_once('.')
+            .ok_or(BlockChainError::InvalidCursor)?;
+
+        Ok(HistoryPosition {
+            block_height: usize::decode(block_height)?,
+            index: usize::decode(index)?,
+        })
+    }
+}
+
+//CHECK THAT THE LIMIT IS NOT LARGER THAN THE MAXIMUM PAGE SIZE
+pub fn check_limit(limit: Option<usize>) -> Result<(), BlockChainError> {
+    match limit {
+        Some(0) => Err(BlockChainError::InvalidPageSize),
+        Some(limit) if limit > MAX_PAGE_SIZE => Err(BlockChainError::InvalidPageSize),
+        _ => Ok(()),
+    }
+}
+
+//RETURN THE PAGE THAT STARTS AFTER THE CURSOR. THE ITEMS ARE GIVEN IN ASCENDING ORDER TOGETHER WITH THEIR POSITION
+//WITHOUT A LIMIT ALL THE REMAINING ITEMS ARE RETURNED
+pub fn paginate<T, K: Cursor>(
+    items: impl DoubleEndedIterator<Item = (K, T)>,
+    order: SortOrder,
+    cursor: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Page<T>, BlockChainError> {
+    check_limit(limit)?;
+    let cursor = cursor.map(K::decode).transpose()?;
+
+    //SKIP THE ITEMS UNTIL THE CURSOR
+    let items: Box<dyn Iterator<Item = (K, T)>> = match (order, cursor) {
+        (SortOrder::Asc, Some(cursor)) => {
+            Box::new(items.skip_while(move |(key, _)| *key <= cursor))
+        }
+        (SortOrder::Desc, Some(cursor)) => {
+            Box::new(items.rev().skip_while(move |(key, _)| *key >= cursor))
+        }
+        (SortOrder::Asc, None) => Box::new(items),
+        (SortOrder::Desc, None) => Box::new(items.rev()),
+    };
+
+    //TAKE ONE MORE ITEM THAN NEEDED TO KNOW IF THERE IS ANOTHER PAGE
+    let mut page: Vec<(K, T)> = items
+        .take(limit.map_or(usize::MAX, |limit| limit + 1))
+        .collect();
+    let next_cursor = match limit {
+        Some(limit) if page.len() > limit => {
+            page.truncate(limit);
+            page.last().map(|(key, _)| key.encode())
+        }
+        _ => None,
+    };
+
+    Ok(Page {
+        items: page.into_iter().map(|(_, item)| item).collect(),
+        next_cursor,
+    })
+}
@@ -0,0 +1,192 @@
+use super::block::BlockDetails;
+use super::block_chain_errors::BlockChainError;
+use super::multisig::Proposal;
+use super::standing_order::StandingOrder;
+use super::transaction::TransactionDetails;
+use super::wallet::Wallet;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+//THE VERSION OF THE SNAPSHOT FORMAT, IT CHANGES WHEN THE FILES CAN NO LONGER BE READ BY OLDER NODES
+pub const SNAPSHOT_VERSION: u32 = 9;
+
+//THE BYTES AT THE START OF A BINARY SNAPSHOT
+pub const BINARY_MAGIC: &[u8; 8] = b"BCHNSNAP";
+
+//THE FORMATS A CHAIN CAN BE EXPORTED TO
+//'jsonl' IS ONE JSON RECORD PER LINE, EASY TO READ AND TO PROCESS WITH OTHER TOOLS. 'binary' IS SMALLER
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotFormat {
+    #[default]
+    Jsonl,
+    Binary,
+}
+
+//THE FIRST RECORD OF A SNAPSHOT, IT DESCRIBES THE CHAIN THAT WAS EXPORTED
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SnapshotHeader {
+    pub version: u32,
+    pub chain_name: String,
+    pub chain_id: u32,
+    pub difficulty: usize,
+    pub blocks: usize,
+}
+
+//EVERYTHING THAT IS NEEDED TO START A NODE FROM THE SAME STATE
+//THE BALANCES ARE NOT IN THE BLOCKS, SO THE WALLETS ARE EXPORTED TOO. THEY CONTAIN THE PASSWORDS, SO THE FILES MUST BE KEPT SAFE
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ChainSnapshot {
+    pub header: SnapshotHeader,
+    pub blocks: Vec<BlockDetails>,
+    pub wallets: Vec<Wallet>,
+    pub pending_transactions: Vec<TransactionDetails>,
+    pub proposals: Vec<Proposal>,
+    pub standing_orders: Vec<StandingOrder>,
+}
+
+//A LINE OF A 'jsonl' SNAPSHOT
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SnapshotRecord {
+    Header(SnapshotHeader),
+    Block(BlockDetails),
+    Wallet(Wallet),
+    PendingTransaction(TransactionDetails),
+    Proposal(Proposal),
+    StandingOrder(StandingOrder),
+}
+
+impl SnapshotFormat {
+    //RETURN THE CONTENT TYPE OF THE FILES IN THIS FORMAT
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Jsonl => "application/x-ndjson",
+            SnapshotFormat::Binary => "application/octet-stream",
+        }
+    }
+
+    //RETURN THE EXTENSION OF THE FILES IN THIS FORMAT
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Jsonl => "jsonl",
+            SnapshotFormat::Binary => "bin",
+        }
+    }
+}
+
+impl ChainSnapshot {
+    //WRITE THE SNAPSHOT IN THE GIVEN FORMAT
+    pub fn encode(&self, format: SnapshotFormat) -> Vec<u8> {
+        match format {
+            SnapshotFormat::Jsonl => {
+                let records = [SnapshotRecord::Header(self.header.clone())]
+                    .into_iter()
+                    .chain(self.blocks.iter().cloned().map(SnapshotRecord::Block))
+                    .chain(self.wallets.iter().cloned().map(SnapshotRecord::Wallet))
+                    .chain(
+                        self.pending_transactions
+                            .iter()
+                            .cloned()
+                            .map(SnapshotRecord::PendingTransaction),
+                    )
+                    .chain(self.proposals.iter().cloned().map(SnapshotRecord::Proposal))
+                    .chain(
+                        self.standing_orders
+                            .iter()
+                            .cloned()
+                            .map(SnapshotRecord::StandingOrder),
+                    );
+
+                let mut bytes = vec![];
+                for record in records {
+                    serde_json::to_writer(&mut bytes, &record).unwrap();
+                    bytes.push(b'\n');
+                }
+                bytes
+            }
+            SnapshotFormat::Binary => {
+                let mut bytes = BINARY_MAGIC.to_vec();
+                bytes.extend(SNAPSHOT_VERSION.to_le_bytes());
+                postcard::to_extend(self, bytes).unwrap()
+            }
+        }
+    }
+
+    //READ A SNAPSHOT, THE FORMAT IS FOUND FROM THE FIRST BYTES
+    pub fn decode(bytes: &[u8]) -> Result<Self, BlockChainError> {
+        let snapshot = match bytes.strip_prefix(BINARY_MAGIC) {
+            Some(bytes) => decode_binary(bytes)?,
+            None => decode_jsonl(bytes)?,
+        };
+
+        //A SNAPSHOT THAT WAS CUT SHORT HAS LESS BLOCKS THAN ITS HEADER SAYS
+        if snapshot.blocks.len() != snapshot.header.blocks {
+            return Err(BlockChainError::InvalidSnapshot);
+        }
+
+        Ok(snapshot)
+    }
+}
+
+//CHECK THAT THE SNAPSHOT CAN BE READ BY THIS VERSION OF THE NODE
+fn check_version(version: u32) -> Result<(), BlockChainError> {
+    match version {
+        SNAPSHOT_VERSION => Ok(()),
+        _ => Err(BlockChainError::UnsupportedSnapshotVersion),
+    }
+}
+
+//READ A BINARY SNAPSHOT, WITHOUT THE MAGIC BYTES
+fn decode_binary(bytes: &[u8]) -> Result<ChainSnapshot, BlockChainError> {
+    let (version, payload) = bytes
+        .split_first_chunk::<4>()
+        .ok_or(BlockChainError::InvalidSnapshot)?;
+    check_version(u32::from_le_bytes(*version))?;
+
+    let snapshot: ChainSnapshot =
+        postcard::from_bytes(payload).map_err(|_| BlockChainError::InvalidSnapshot)?;
+    check_version(snapshot.header.version)?;
+
+    Ok(snapshot)
+}
+
+//READ A 'jsonl' SNAPSHOT, THE HEADER MUST BE THE FIRST LINE
+fn decode_jsonl(bytes: &[u8]) -> Result<ChainSnapshot, BlockChainError> {
+    let mut records = bytes
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.trim_ascii().is_empty())
+        .map(|line| {
+            serde_json::from_slice::<SnapshotRecord>(line)
+                .map_err(|_| BlockChainError::InvalidSnapshot)
+        });
+
+    let header = match records.next() {
+        Some(Ok(SnapshotRecord::Header(header))) => header,
+        _ => return Err(BlockChainError::InvalidSnapshot),
+    };
+    check_version(header.version)?;
+
+    let mut snapshot = ChainSnapshot {
+        header,
+        blocks: vec![],
+        wallets: vec![],
+        pending_transactions: vec![],
+        proposals: vec![],
+        standing_orders: vec![],
+    };
+    for record in records {
+        match record? {
+            SnapshotRecord::Header(_) => return Err(BlockChainError::InvalidSnapshot),
+            SnapshotRecord::Block(block) => snapshot.blocks.push(block),
+            SnapshotRecord::Wallet(wallet) => snapshot.wallets.push(wallet),
+            SnapshotRecord::PendingTransaction(transaction) => {
+                snapshot.pending_transactions.push(transaction)
+            }
+            SnapshotRecord::Proposal(proposal) => snapshot.proposals.push(proposal),
+            SnapshotRecord::StandingOrder(order) => snapshot.standing_orders.push(order),
+        }
+    }
+
+    Ok(snapshot)
+}
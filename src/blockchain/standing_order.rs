@@ -0,0 +1,195 @@
+use super::block_chain_errors::BlockChainError;
+use super::transaction::{Metadata, TransactionInfo};
+use super::wallet::Wallet;
+use crate::logging::REDACTED;
+use crypto_hash::{hex_digest, Algorithm};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use utoipa::ToSchema;
+
+//THE SHORTEST AND THE LONGEST INTERVAL BETWEEN TWO PAYMENTS OF A STANDING ORDER, IN SECONDS
+pub const MIN_STANDING_ORDER_INTERVAL: u64 = 60;
+pub const MAX_STANDING_ORDER_INTERVAL: u64 = 366 * 24 * 60 * 60;
+
+//COUNTER USED TO KEEP THE IDS UNIQUE WHEN TWO IDENTICAL STANDING ORDERS ARE CREATED AT THE SAME TIME
+static STANDING_ORDER_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+//CONTAINS THE INFORMATION THAT ARE REQUIRED WHEN THE PAYER CREATES A STANDING ORDER
+//'interval' IS HOW MANY SECONDS THERE ARE BETWEEN TWO PAYMENTS. THE FIRST ONE IS DUE AT 'start_at', OR RIGHT AWAY, AND NONE IS MADE AFTER 'end_at'
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
+pub struct StandingOrderInfo {
+    pub from_address: String,
+    pub password: String,
+    pub to_address: String,
+    pub amount: u32,
+    #[serde(default)]
+    pub fee: u32,
+    pub interval: u64,
+    #[serde(default)]
+    pub start_at: Option<u64>,
+    #[serde(default)]
+    pub end_at: Option<u64>,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+//WHERE A STANDING ORDER IS. AN ACTIVE ORDER IS COMPLETED WHEN ITS NEXT PAYMENT WOULD BE AFTER ITS END
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StandingOrderStatus {
+    Active,
+    Completed,
+    Revoked,
+}
+
+//A PAYMENT THE NODE ADDS TO PENDING TRANSACTIONS EVERY 'interval' SECONDS ON BEHALF OF THE PAYER
+//'payments' COUNTS THE PAYMENTS THAT WERE ADDED AND 'missed' THE ONES THE PAYER COULDN'T PAY OR THAT WERE SKIPPED, 'transaction_id' IS THE ID OF THE LAST PAYMENT
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct StandingOrder {
+    pub id: String,
+    pub from_address: String,
+    pub to_address: String,
+    pub amount: u32,
+    pub fee: u32,
+    pub memo: Option<String>,
+    pub metadata: Metadata,
+    pub interval: u64,
+    pub created_at: u64,
+    pub next_payment_at: u64,
+    pub end_at: Option<u64>,
+    pub status: StandingOrderStatus,
+    pub payments: u64,
+    pub missed: u64,
+    pub transaction_id: Option<String>,
+}
+
+impl StandingOrderInfo {
+    //CHECK IF THE SCHEDULE IS VALID AT THE GIVEN TIME, THE FIRST PAYMENT CAN'T BE IN THE PAST OR AFTER THE END
+    pub fn check_schedule(&self, now: u64) -> Result<(), BlockChainError> {
+        let start_at = self.start_at.unwrap_or(now);
+        if !(MIN_STANDING_ORDER_INTERVAL..=MAX_STANDING_ORDER_INTERVAL).contains(&self.interval)
+            || start_at < now
+            || self.end_at.is_some_and(|end_at| end_at < start_at)
+        {
+            return Err(BlockChainError::InvalidStandingOrder);
+        }
+
+        Ok(())
+    }
+}
+
+impl StandingOrder {
+    //CREATE A NEW ACTIVE STANDING ORDER WITH A UNIQUE ID AT THE GIVEN TIME
+    pub fn new(info: &StandingOrderInfo, timestamp: u64) -> Result<Self, BlockChainError> {
+        info.check_schedule(timestamp)?;
+        let created_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let counter = STANDING_ORDER_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let id = hex_digest(
+            Algorithm::SHA256,
+            format!(
+                "{}{}{}{}{}{}",
+                info.from_address,
+                info.to_address,
+                info.amount,
+                info.interval,
+                created_at.as_nanos(),
+                counter
+            )
+            .as_bytes(),
+        );
+
+        Ok(StandingOrder {
+            id,
+            from_address: info.from_address.clone(),
+            to_address: info.to_address.clone(),
+            amount: info.amount,
+            fee: info.fee,
+            memo: info.memo.clone(),
+            metadata: info.metadata.clone(),
+            interval: info.interval,
+            created_at: timestamp,
+            next_payment_at: info.start_at.unwrap_or(timestamp),
+            end_at: info.end_at,
+            status: StandingOrderStatus::Active,
+            payments: 0,
+            missed: 0,
+            transaction_id: None,
+        })
+    }
+
+    //CHECK IF A PAYMENT OF THE STANDING ORDER IS DUE AT THE GIVEN TIME
+    pub fn is_due(&self, now: u64) -> bool {
+        self.status == StandingOrderStatus::Active && self.next_payment_at <= now
+    }
+
+    //SKIP THE RUNS THAT ARE OVERDUE BY MORE THAN ONE INTERVAL, ONLY THE LATEST ONE DUE AT THE GIVEN TIME IS LEFT
+    //THE SKIPPED RUNS ARE COUNTED AS MISSED AND THEIR NUMBER IS RETURNED
+    pub fn skip_overdue(&mut self, now: u64) -> u64 {
+        if !self.is_due(now) {
+            return 0;
+        }
+        let last_due_at = match self.end_at {
+            Some(end_at) => now.min(end_at),
+            None => now,
+        };
+        let skipped = last_due_at.saturating_sub(self.next_payment_at) / self.interval;
+        self.next_payment_at += skipped * self.interval;
+        self.missed += skipped;
+        skipped
+    }
+
+    //MOVE TO THE NEXT PAYMENT, THE ORDER IS COMPLETED WHEN IT WOULD BE AFTER THE END OR AFTER THE LAST TIME THERE IS
+    pub fn advance(&mut self) {
+        match self.next_payment_at.checked_add(self.interval) {
+            Some(next_payment_at) => {
+                self.next_payment_at = next_payment_at;
+                if self.end_at.is_some_and(|end_at| next_payment_at > end_at) {
+                    self.status = StandingOrderStatus::Completed;
+                }
+            }
+            None => self.status = StandingOrderStatus::Completed,
+        }
+    }
+
+    //THE TRANSACTION OF A PAYMENT, IT IS CHECKED LIKE ANY OTHER TRANSACTION OF THE PAYER
+    pub fn transaction(&self, wallet: &Wallet) -> TransactionInfo {
+        TransactionInfo {
+            from_address: wallet.address.clone(),
+            from_password: wallet.password.clone(),
+            to_address: self.to_address.clone(),
+            amount: self.amount,
+            fee: self.fee,
+            expiry: None,
+            nonce: None,
+            memo: self.memo.clone(),
+            metadata: self.metadata.clone(),
+            lock: None,
+            hash_lock: None,
+        }
+    }
+}
+
+//THE PASSWORD IS NEVER SHOWN WHEN THE REQUEST IS PRINTED OR LOGGED
+impl fmt::Debug for StandingOrderInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StandingOrderInfo")
+            .field("from_address", &self.from_address)
+            .field("password", &REDACTED)
+            .field("to_address", &self.to_address)
+            .field("amount", &self.amount)
+            .field("fee", &self.fee)
+            .field("interval", &self.interval)
+            .field("start_at", &self.start_at)
+            .field("end_at", &self.end_at)
+            .field("memo", &self.memo)
+            .field("metadata", &self.metadata)
+            .finish()
+    }
+}
@@ -1,36 +1,272 @@
-use super::{block_chain_errors::BlockChainError, wallet::Wallet};
+use super::{
+    block_chain_errors::BlockChainError,
+    escrow::{HashLock, Settlement},
+    mempool::EvictionReason,
+    wallet::Wallet,
+};
+use crate::logging::REDACTED;
+use crypto_hash::{hex_digest, Algorithm};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use utoipa::ToSchema;
+
+//THE MAXIMUM LENGTH OF A MEMO, IN BYTES
+pub const MAX_MEMO_LENGTH: usize = 256;
+
+//THE MAXIMUM NUMBER OF METADATA ENTRIES OF A TRANSACTION AND THE MAXIMUM LENGTHS OF THEIR KEYS AND VALUES, IN BYTES
+pub const MAX_METADATA_ENTRIES: usize = 16;
+pub const MAX_METADATA_KEY_LENGTH: usize = 64;
+pub const MAX_METADATA_VALUE_LENGTH: usize = 256;
+
+//KEY/VALUE PAIRS ATTACHED TO A TRANSACTION, FOR EXAMPLE AN INVOICE NUMBER. THE KEYS ARE SORTED SO THE HASH DOESN'T DEPEND ON THEIR ORDER
+pub type Metadata = BTreeMap<String, String>;
+
+//COUNTER USED TO KEEP THE IDS UNIQUE WHEN TWO IDENTICAL TRANSACTIONS ARE CREATED AT THE SAME TIME
+static TRANSACTION_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 //CONTAINS INFORMATION ABOUT A TRANSACTION
+//THE CLAIMS AND THE REFUNDS OF THE ESCROWS ARE SENT FROM THE ESCROW ADDRESS AND HAVE THE SETTLEMENT
 #[derive(Clone)]
 pub struct Transaction {
+    pub id: String,
     pub from_wallet: Wallet,
     pub to_wallet: Wallet,
     pub amount: u32,
+    pub timestamp: u64,
+    pub fee: u32,
+    pub expiry: Option<Expiry>,
+    pub nonce: u64,
+    pub memo: Option<String>,
+    pub metadata: Metadata,
+    pub lock: Option<LockTime>,
+    pub hash_lock: Option<HashLock>,
+    pub settlement: Option<Settlement>,
+}
+
+//WHEN A PENDING TRANSACTION CAN NO LONGER BE MINED. AN EXPIRED TRANSACTION IS EVICTED AND ITS COINS GO BACK TO THE SENDER
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Expiry {
+    //A UNIX TIMESTAMP IN SECONDS, THE TRANSACTION EXPIRES WHEN IT IS REACHED
+    Timestamp(u64),
+    //THE HEIGHT OF THE LAST BLOCK THE TRANSACTION CAN BE MINED IN
+    Height(usize),
+}
+
+//WHEN THE RECIPIENT CAN SPEND THE COINS OF A TRANSACTION. THE TRANSACTION IS MINED AS USUAL, THE COINS STAY LOCKED IN THE WALLET OF THE RECIPIENT UNTIL THEN
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LockTime {
+    //A UNIX TIMESTAMP IN SECONDS, THE COINS CAN BE SPENT ONCE IT IS REACHED
+    Timestamp(u64),
+    //THE HEIGHT OF THE FIRST BLOCK WHOSE TRANSACTIONS CAN SPEND THE COINS
+    Height(usize),
 }
 
 //CONTAINS THE INFORMATION THAT ARE REQUIRED WHEN CREATING A NEW TRANSACTION
-#[derive(Deserialize, Serialize, Clone, Debug)]
+//THE FEE IS PAID BY THE SENDER TOGETHER WITH THE AMOUNT AND GOES TO THE MINER, THE TRANSACTIONS WITH THE LOWEST FEES ARE EVICTED FIRST WHEN THE MEMPOOL IS FULL
+//WITHOUT A NONCE THE TRANSACTION GETS THE NEXT ONE OF THE SENDER. WITH THE NONCE OF A PENDING TRANSACTION AND A HIGHER FEE IT REPLACES THAT TRANSACTION
+//THE MEMO AND THE METADATA ARE KEPT WITH THE TRANSACTION AND CAN BE SEARCHED, FOR EXAMPLE FOR AN INVOICE NUMBER
+//WITH A LOCK THE RECIPIENT CAN'T SPEND THE COINS BEFORE A DATE OR A BLOCK, FOR EXAMPLE FOR A SCHEDULED PAYOUT
+//WITH A HASH LOCK THE TRANSACTION IS AN ESCROW, THE COINS GO TO THE RECIPIENT ONLY WHEN IT CLAIMS THEM WITH THE PREIMAGE OF THE HASH
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
 pub struct TransactionInfo {
     pub from_address: String,
     pub from_password: String,
     pub to_address: String,
     pub amount: u32,
+    #[serde(default)]
+    pub fee: u32,
+    #[serde(default)]
+    pub expiry: Option<Expiry>,
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub lock: Option<LockTime>,
+    #[serde(default)]
+    pub hash_lock: Option<HashLock>,
+}
+
+//THE INFORMATION ABOUT A TRANSACTION THAT IS SHOWN TO THE USERS
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct TransactionDetails {
+    pub id: String,
+    pub from_address: String,
+    pub to_address: String,
+    pub amount: u32,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub fee: u32,
+    #[serde(default)]
+    pub expiry: Option<Expiry>,
+    #[serde(default)]
+    pub nonce: u64,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub lock: Option<LockTime>,
+    #[serde(default)]
+    pub hash_lock: Option<HashLock>,
+    #[serde(default)]
+    pub settlement: Option<Settlement>,
+}
+
+//A TRANSACTION TOGETHER WITH THE PLACE WHERE IT IS FOUND. 'block_height' IS MISSING WHILE THE TRANSACTION IS PENDING
+//AN EVICTED TRANSACTION HAS THE REASON WHY IT WAS EVICTED
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct TransactionStatus {
+    pub transaction: TransactionDetails,
+    pub status: String,
+    pub block_height: Option<usize>,
+    pub confirmations: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eviction_reason: Option<EvictionReason>,
 }
 
 impl Transaction {
-    //CREATE A NEW TRANSACTION
+    //CREATE A NEW TRANSACTION WITH A UNIQUE ID
     pub fn new(from_wallet: Wallet, to_wallet: Wallet, amount: u32) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let counter = TRANSACTION_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let id = hex_digest(
+            Algorithm::SHA256,
+            format!(
+                "{}{}{}{}{}",
+                from_wallet.address,
+                to_wallet.address,
+                amount,
+                created_at.as_nanos(),
+                counter
+            )
+            .as_bytes(),
+        );
+
         Transaction {
+            id,
             amount,
             from_wallet,
             to_wallet,
+            timestamp: created_at.as_secs(),
+            fee: 0,
+            expiry: None,
+            nonce: 0,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+            settlement: None,
+        }
+    }
+
+    //RETURN THE COINS TAKEN FROM THE SENDER, THE AMOUNT AND THE FEE
+    pub fn cost(&self) -> u32 {
+        self.amount + self.fee
+    }
+
+    //CHECK IF THE TRANSACTION CAN NO LONGER BE MINED AT THE GIVEN TIME INTO THE BLOCK AT THE GIVEN HEIGHT
+    pub fn is_expired(&self, now: u64, height: usize) -> bool {
+        match self.expiry {
+            Some(Expiry::Timestamp(timestamp)) => now >= timestamp,
+            Some(Expiry::Height(last_height)) => height > last_height,
+            None => false,
+        }
+    }
+
+    //ESTIMATE THE MEMORY USED BY THE TRANSACTION WHILE IT IS PENDING
+    pub fn size(&self) -> usize {
+        std::mem::size_of::<Transaction>()
+            + self.id.len()
+            + self.from_wallet.address.len()
+            + self.from_wallet.password.len()
+            + self.to_wallet.address.len()
+            + self.to_wallet.password.len()
+            + self.memo.as_ref().map_or(0, String::len)
+            + self
+                .hash_lock
+                .as_ref()
+                .map_or(0, |hash_lock| hash_lock.hash.len())
+            + self.settlement.as_ref().map_or(0, |settlement| {
+                settlement.escrow.len() + settlement.preimage.as_ref().map_or(0, String::len)
+            })
+            + self
+                .metadata
+                .iter()
+                .map(|(key, value)| key.len() + value.len())
+                .sum::<usize>()
+    }
+
+    //RETURN THE INFORMATION ABOUT THE TRANSACTION THAT CAN BE SHOWN TO THE USERS
+    pub fn details(&self) -> TransactionDetails {
+        TransactionDetails {
+            id: self.id.clone(),
+            from_address: self.from_wallet.address.clone(),
+            to_address: self.to_wallet.address.clone(),
+            amount: self.amount,
+            timestamp: self.timestamp,
+            fee: self.fee,
+            expiry: self.expiry,
+            nonce: self.nonce,
+            memo: self.memo.clone(),
+            metadata: self.metadata.clone(),
+            lock: self.lock,
+            hash_lock: self.hash_lock.clone(),
+            settlement: self.settlement.clone(),
+        }
+    }
+
+    //CHECK IF THE TRANSACTION SENDS OR RECEIVES COINS FROM THE GIVEN ADDRESS
+    pub fn touches(&self, address: &str) -> bool {
+        self.from_wallet.address == address || self.to_wallet.address == address
+    }
+}
+
+//REBUILD A TRANSACTION FROM ITS DETAILS, THE WALLETS ONLY HAVE THEIR ADDRESSES
+//THIS IS ENOUGH TO CALCULATE THE HASH OF THE BLOCK THAT CONTAINS THE TRANSACTION
+impl From<&TransactionDetails> for Transaction {
+    fn from(details: &TransactionDetails) -> Self {
+        Transaction {
+            id: details.id.clone(),
+            from_wallet: Wallet::new(details.from_address.clone(), 0, String::new()),
+            to_wallet: Wallet::new(details.to_address.clone(), 0, String::new()),
+            amount: details.amount,
+            timestamp: details.timestamp,
+            fee: details.fee,
+            expiry: details.expiry,
+            nonce: details.nonce,
+            memo: details.memo.clone(),
+            metadata: details.metadata.clone(),
+            lock: details.lock,
+            hash_lock: details.hash_lock.clone(),
+            settlement: details.settlement.clone(),
+        }
+    }
+}
+
+impl LockTime {
+    //CHECK IF THE COINS CAN BE SPENT AT THE GIVEN TIME BY A TRANSACTION THAT GOES INTO THE BLOCK AT THE GIVEN HEIGHT
+    pub fn is_unlocked(&self, now: u64, height: usize) -> bool {
+        match *self {
+            LockTime::Timestamp(timestamp) => now >= timestamp,
+            LockTime::Height(first_height) => height >= first_height,
         }
     }
 }
 
 impl TransactionInfo {
     //CHECK IF THE TRANSACTION IS VALID
+    #[tracing::instrument(level = "debug", skip_all, err(level = "debug", Display))]
     pub fn check_transaction_info(&self) -> Result<(), BlockChainError> {
         if self.from_address.is_empty() {
             return Err(BlockChainError::EmptyFromAddress);
@@ -40,10 +276,56 @@ impl TransactionInfo {
             return Err(BlockChainError::EmptyToAddress);
         }
 
-        if self.amount <= 0 {
+        if self.amount == 0 || self.amount.checked_add(self.fee).is_none() {
             return Err(BlockChainError::InvalidAmount);
         }
 
+        if self
+            .memo
+            .as_ref()
+            .is_some_and(|memo| memo.len() > MAX_MEMO_LENGTH)
+        {
+            return Err(BlockChainError::MemoTooLong);
+        }
+
+        //THE KEYS CAN'T BE EMPTY, SO A SEARCH BY KEY ALWAYS MEANS SOMETHING
+        let invalid_entry = |(key, value): (&String, &String)| {
+            key.is_empty()
+                || key.len() > MAX_METADATA_KEY_LENGTH
+                || value.len() > MAX_METADATA_VALUE_LENGTH
+        };
+        if self.metadata.len() > MAX_METADATA_ENTRIES || self.metadata.iter().any(invalid_entry) {
+            return Err(BlockChainError::InvalidMetadata);
+        }
+
+        //THE COINS OF AN ESCROW ARE HELD UNTIL IT IS SETTLED, SO THEY CAN'T BE LOCKED TOO
+        if self
+            .hash_lock
+            .as_ref()
+            .is_some_and(|hash_lock| !hash_lock.is_valid() || self.lock.is_some())
+        {
+            return Err(BlockChainError::InvalidHashLock);
+        }
+
         Ok(())
     }
 }
+
+//THE PASSWORD IS NEVER SHOWN WHEN THE TRANSACTION IS PRINTED OR LOGGED
+impl fmt::Debug for TransactionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransactionInfo")
+            .field("from_address", &self.from_address)
+            .field("from_password", &REDACTED)
+            .field("to_address", &self.to_address)
+            .field("amount", &self.amount)
+            .field("fee", &self.fee)
+            .field("expiry", &self.expiry)
+            .field("nonce", &self.nonce)
+            .field("memo", &self.memo)
+            .field("metadata", &self.metadata)
+            .field("lock", &self.lock)
+            .field("hash_lock", &self.hash_lock)
+            .finish()
+    }
+}
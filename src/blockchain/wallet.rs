@@ -1,17 +1,38 @@
+use super::{
+    block_chain::MINING_ADDRESS, block_chain_errors::BlockChainError, escrow::ESCROW_ADDRESS,
+    multisig::Multisig, transaction::LockTime,
+};
+use crate::logging::REDACTED;
 use serde::{Deserialize, Serialize};
-use super::{transaction::{TransactionInfo}, block_chain::MINING_ADDRESS, block_chain_errors::BlockChainError};
+use std::fmt;
+use utoipa::ToSchema;
 
-//CONTAINS THE INFORMATION ABOUT A WALLET
-#[derive(Debug, Clone, Deserialize, Serialize)]
+//CONTAINS THE INFORMATION ABOUT A WALLET. 'nonce' IS THE NONCE THE NEXT TRANSACTION SENT FROM THE WALLET GETS
+//A MULTISIGNATURE WALLET HAS THE OWNERS THAT MUST APPROVE ITS TRANSFERS
+//THE COINS RECEIVED WITH A LOCK ARE NOT IN 'balance', THEY ARE MOVED THERE WHEN THE WALLET SENDS A TRANSACTION AFTER THE LOCK HAS PASSED
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Wallet {
     pub address: String,
     pub balance: u32,
     pub password: String,
-    pub transactions: Vec<TransactionInfo>,
+    #[serde(default)]
+    pub nonce: u64,
+    #[serde(default)]
+    pub multisig: Option<Multisig>,
+    #[serde(default)]
+    pub locked: Vec<LockedCoins>,
+}
+
+//COINS RECEIVED WITH A MINED TRANSACTION THAT HAS A LOCK, THEY CAN'T BE SPENT UNTIL THE LOCK HAS PASSED
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct LockedCoins {
+    pub transaction_id: String,
+    pub amount: u32,
+    pub lock: LockTime,
 }
 
 //CONTAINS THE INFORMATION THAT ARE REQUIRED WHEN CREATING A WALLET
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
 pub struct WalletInfo {
     pub address: String,
     pub balance: i32,
@@ -19,15 +40,25 @@ pub struct WalletInfo {
 }
 
 //CONTAINS THE INFORMATION THAT ARE REQUIRED WHEN ADDING COINS TO A WALLET
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, ToSchema)]
 pub struct WalletCoins {
     pub address: String,
     pub password: String,
     pub coins: u32,
 }
 
+//THE ADDRESS AND THE BALANCE OF A WALLET, WITHOUT ITS PASSWORD
+//'balance' IS WHAT THE WALLET CAN SPEND AND 'locked' WHAT IT RECEIVED WITH A LOCK THAT HAS NOT PASSED YET
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct WalletBalance {
+    pub address: String,
+    pub balance: u32,
+    #[serde(default)]
+    pub locked: u32,
+}
+
 //THE ADDRESS WHERE THE MINING REWARD WILL GO
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSchema)]
 pub struct MineRewardAddress {
     pub mining_reward_address: String,
 }
@@ -37,7 +68,7 @@ pub enum AddressType {
     TO,
     FROM,
     REWARD,
-    GENERIC
+    GENERIC,
 }
 
 impl Wallet {
@@ -47,22 +78,71 @@ impl Wallet {
             address,
             balance,
             password,
-            transactions: vec![],
+            nonce: 0,
+            multisig: None,
+            locked: vec![],
+        }
+    }
+
+    //RETURN THE ADDRESS OF THE WALLET, THE COINS IT CAN SPEND AND THE ONES THAT ARE STILL LOCKED
+    //FOR A TRANSACTION MADE AT THE GIVEN TIME THAT GOES INTO THE BLOCK AT THE GIVEN HEIGHT
+    //THE SUMS CAN'T OVERFLOW, A WALLET NEVER HOLDS MORE THAN u32::MAX COINS. SEE 'check_credit'
+    pub fn balance(&self, now: u64, height: usize) -> WalletBalance {
+        let (unlocked, locked): (Vec<&LockedCoins>, Vec<&LockedCoins>) = self
+            .locked
+            .iter()
+            .partition(|coins| coins.lock.is_unlocked(now, height));
+        let total = |coins: Vec<&LockedCoins>| coins.iter().map(|coins| coins.amount).sum::<u32>();
+
+        WalletBalance {
+            address: self.address.clone(),
+            balance: self.balance + total(unlocked),
+            locked: total(locked),
+        }
+    }
+
+    //MOVE THE LOCKED COINS WHOSE LOCK HAS PASSED TO THE BALANCE, SO THEY CAN BE SPENT
+    pub fn release_locked(&mut self, now: u64, height: usize) {
+        let (unlocked, locked): (Vec<LockedCoins>, Vec<LockedCoins>) =
+            std::mem::take(&mut self.locked)
+                .into_iter()
+                .partition(|coins| coins.lock.is_unlocked(now, height));
+        self.locked = locked;
+        self.balance += unlocked.iter().map(|coins| coins.amount).sum::<u32>();
+    }
+
+    //RETURN ALL THE COINS OF THE WALLET: ITS BALANCE, ITS LOCKED COINS AND THE ONES TAKEN FOR ITS PENDING TRANSACTIONS, WHICH CAN COME BACK TO IT
+    pub fn holdings(&self, pending_spend: u64) -> u64 {
+        let locked: u64 = self.locked.iter().map(|coins| coins.amount as u64).sum();
+        self.balance as u64 + locked + pending_spend
+    }
+
+    //RETURN AN ERROR IF THE WALLET WOULD HOLD MORE THAN u32::MAX COINS WITH THE GIVEN ONES
+    //EVERY CREDIT IS CHECKED BEFORE ANYTHING CHANGES, SO THE BALANCE, THE LOCKED COINS AND THE REFUNDS OF THE WALLET ALWAYS FIT IN A u32
+    pub fn check_credit(&self, pending_spend: u64, coins: u64) -> Result<(), BlockChainError> {
+        match self.holdings(pending_spend) + coins > u32::MAX as u64 {
+            true => Err(BlockChainError::BalanceOverflow),
+            false => Ok(()),
         }
     }
 }
 
 impl WalletInfo {
     //CHECK IF THE WALLET INFORMATION ARE VALID
+    #[tracing::instrument(level = "debug", skip_all, err(level = "debug", Display))]
     pub fn check_wallet_info(&self) -> Result<(), BlockChainError> {
         if self.address.is_empty() {
             return Err(BlockChainError::EmptyAddress);
         }
 
-        if self.address == MINING_ADDRESS.to_string() {
+        if self.address == MINING_ADDRESS {
             return Err(BlockChainError::MiningAddress);
         }
 
+        if self.address == ESCROW_ADDRESS {
+            return Err(BlockChainError::EscrowAddress);
+        }
+
         if self.balance < 0 {
             return Err(BlockChainError::NegativeBalance);
         }
@@ -73,4 +153,38 @@ impl WalletInfo {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+//THE PASSWORDS ARE NEVER SHOWN WHEN THE WALLETS ARE PRINTED OR LOGGED
+impl fmt::Debug for Wallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Wallet")
+            .field("address", &self.address)
+            .field("balance", &self.balance)
+            .field("password", &REDACTED)
+            .field("nonce", &self.nonce)
+            .field("multisig", &self.multisig)
+            .field("locked", &self.locked)
+            .finish()
+    }
+}
+
+impl fmt::Debug for WalletInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalletInfo")
+            .field("address", &self.address)
+            .field("balance", &self.balance)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
+
+impl fmt::Debug for WalletCoins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalletCoins")
+            .field("address", &self.address)
+            .field("password", &REDACTED)
+            .field("coins", &self.coins)
+            .finish()
+    }
+}
@@ -0,0 +1,443 @@
+use super::block_chain_errors::BlockChainError;
+use super::escrow::HashLock;
+use super::mempool::Eviction;
+use super::multisig::{MultisigWalletInfo, ProposalInfo};
+use super::snapshot::ChainSnapshot;
+use super::standing_order::StandingOrderInfo;
+use super::transaction::{Expiry, LockTime, Metadata, TransactionInfo};
+use super::wallet::WalletInfo;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::error;
+
+//THE COMMANDS THAT CHANGE THE STATE OF THE BLOCKCHAIN, TOGETHER WITH THE VALUES THAT WERE GENERATED WHEN THEY WERE APPLIED
+//WITH THESE VALUES A REPLAY BUILDS EXACTLY THE SAME BLOCKS, IDS AND BALANCES
+//A COMMAND IS AUTHORISED BEFORE IT IS WRITTEN, SO ONLY THE PASSWORDS THAT ARE PART OF A NEW WALLET ARE KEPT AND A REPLAY DOESN'T CHECK THEM AGAIN
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Command {
+    //ALWAYS THE FIRST COMMAND, THE GENESIS BLOCK IS CREATED WITH THE TIME THE NODE STARTED
+    Genesis {
+        timestamp: u64,
+        hash: String,
+    },
+    CreateWallet(WalletInfo),
+    AddCoins {
+        address: String,
+        coins: u32,
+    },
+    SubmitTransaction {
+        transaction: AuthorisedTransaction,
+        id: String,
+        timestamp: u64,
+    },
+    //THE ACCEPTED TRANSACTIONS OF A BATCH, THEY ARE ADDED TOGETHER
+    SubmitBatch {
+        transactions: Vec<RecordedTransaction>,
+    },
+    CreateMultisigWallet(MultisigWalletInfo),
+    //'transaction_id' IS THE ID OF THE TRANSACTION WHEN THE PROPOSAL OR THE APPROVAL REACHED THE THRESHOLD
+    ProposeTransfer {
+        wallet: String,
+        proposal: AuthorisedProposal,
+        id: String,
+        timestamp: u64,
+        transaction_id: Option<String>,
+    },
+    ApproveProposal {
+        id: String,
+        owner: String,
+        timestamp: u64,
+        transaction_id: Option<String>,
+    },
+    //THE CLAIM OF AN ESCROW HAS THE PREIMAGE, ITS REFUND DOESN'T. 'id' IS THE ID OF THE TRANSACTION THAT SETTLES IT
+    SettleEscrow {
+        escrow: String,
+        preimage: Option<String>,
+        id: String,
+        timestamp: u64,
+    },
+    CreateStandingOrder {
+        order: AuthorisedStandingOrder,
+        id: String,
+        timestamp: u64,
+    },
+    RevokeStandingOrder {
+        id: String,
+        timestamp: u64,
+    },
+    //THE PAYMENTS OF THE STANDING ORDERS THAT WERE DUE AT 'timestamp', IN THE ORDER THEY WERE MADE
+    PayStandingOrders {
+        timestamp: u64,
+        payments: Vec<ScheduledPayment>,
+    },
+    MineBlock(MinedBlock),
+    //PENDING TRANSACTIONS THAT WERE FOUND INVALID WHEN A BLOCK WAS BUILT
+    EvictTransactions {
+        evictions: Vec<Eviction>,
+    },
+    ImportSnapshot {
+        snapshot: ChainSnapshot,
+    },
+}
+
+//A TRANSACTION TOGETHER WITH THE ID AND THE TIMESTAMP IT GOT
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RecordedTransaction {
+    pub transaction: AuthorisedTransaction,
+    pub id: String,
+    pub timestamp: u64,
+}
+
+//A TRANSACTION WITHOUT THE PASSWORD OF ITS SENDER
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AuthorisedTransaction {
+    pub from_address: String,
+    pub to_address: String,
+    pub amount: u32,
+    #[serde(default)]
+    pub fee: u32,
+    #[serde(default)]
+    pub expiry: Option<Expiry>,
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub lock: Option<LockTime>,
+    #[serde(default)]
+    pub hash_lock: Option<HashLock>,
+}
+
+//A PROPOSAL WITHOUT THE PASSWORD OF THE OWNER THAT MADE IT
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AuthorisedProposal {
+    pub owner: String,
+    pub to_address: String,
+    pub amount: u32,
+    #[serde(default)]
+    pub fee: u32,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+}
+
+//A STANDING ORDER WITHOUT THE PASSWORD OF ITS PAYER
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AuthorisedStandingOrder {
+    pub from_address: String,
+    pub to_address: String,
+    pub amount: u32,
+    #[serde(default)]
+    pub fee: u32,
+    pub interval: u64,
+    #[serde(default)]
+    pub start_at: Option<u64>,
+    #[serde(default)]
+    pub end_at: Option<u64>,
+    #[serde(default)]
+    pub memo: Option<String>,
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+//A PAYMENT OF A STANDING ORDER THAT WAS DUE AT 'due_at'. 'transaction_id' IS THE ID OF ITS TRANSACTION, NOTHING WHEN THE PAYER COULDN'T PAY IT
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ScheduledPayment {
+    pub order: String,
+    pub due_at: u64,
+    pub transaction_id: Option<String>,
+}
+
+//A MINED BLOCK. 'transactions' IS HOW MANY OF THE PENDING TRANSACTIONS WENT INTO THE BLOCK, THE ONES CREATED WHILE MINING STAY PENDING
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct MinedBlock {
+    pub reward_address: String,
+    pub transactions: usize,
+    pub timestamp: u64,
+    pub nonce: usize,
+    pub hash: String,
+    pub reward_id: String,
+    pub reward_timestamp: u64,
+}
+
+//A LINE OF THE LOG, THE SEQUENCE STARTS AT 0 AND HAS NO GAPS
+#[derive(Deserialize, Serialize, Clone)]
+pub struct LogEntry {
+    pub sequence: u64,
+    pub command: Command,
+}
+
+//THE FILE WHERE THE COMMANDS ARE APPENDED, ONE JSON LINE EACH
+//THE LOG CONTAINS THE PASSWORDS OF THE NEW WALLETS, SO IT IS ONLY READABLE BY ITS OWNER
+//A LOG IS POISONED WHEN A LINE THAT FAILED COULD NOT BE REMOVED, NOTHING IS WRITTEN TO IT AFTER THAT
+pub struct WriteAheadLog {
+    path: PathBuf,
+    file: File,
+    next_sequence: u64,
+    poisoned: bool,
+}
+
+impl From<&TransactionInfo> for AuthorisedTransaction {
+    fn from(info: &TransactionInfo) -> Self {
+        AuthorisedTransaction {
+            from_address: info.from_address.clone(),
+            to_address: info.to_address.clone(),
+            amount: info.amount,
+            fee: info.fee,
+            expiry: info.expiry,
+            nonce: info.nonce,
+            memo: info.memo.clone(),
+            metadata: info.metadata.clone(),
+            lock: info.lock,
+            hash_lock: info.hash_lock.clone(),
+        }
+    }
+}
+
+//THE PASSWORD OF A REPLAYED TRANSACTION IS EMPTY, IT IS NOT CHECKED AGAIN
+impl From<AuthorisedTransaction> for TransactionInfo {
+    fn from(transaction: AuthorisedTransaction) -> Self {
+        TransactionInfo {
+            from_address: transaction.from_address,
+            from_password: String::new(),
+            to_address: transaction.to_address,
+            amount: transaction.amount,
+            fee: transaction.fee,
+            expiry: transaction.expiry,
+            nonce: transaction.nonce,
+            memo: transaction.memo,
+            metadata: transaction.metadata,
+            lock: transaction.lock,
+            hash_lock: transaction.hash_lock,
+        }
+    }
+}
+
+impl From<&ProposalInfo> for AuthorisedProposal {
+    fn from(info: &ProposalInfo) -> Self {
+        AuthorisedProposal {
+            owner: info.owner.clone(),
+            to_address: info.to_address.clone(),
+            amount: info.amount,
+            fee: info.fee,
+            memo: info.memo.clone(),
+            metadata: info.metadata.clone(),
+            expires_in: info.expires_in,
+        }
+    }
+}
+
+//THE PASSWORD OF A REPLAYED PROPOSAL IS EMPTY, IT IS NOT CHECKED AGAIN
+impl From<AuthorisedProposal> for ProposalInfo {
+    fn from(proposal: AuthorisedProposal) -> Self {
+        ProposalInfo {
+            owner: proposal.owner,
+            password: String::new(),
+            to_address: proposal.to_address,
+            amount: proposal.amount,
+            fee: proposal.fee,
+            memo: proposal.memo,
+            metadata: proposal.metadata,
+            expires_in: proposal.expires_in,
+        }
+    }
+}
+
+impl From<&StandingOrderInfo> for AuthorisedStandingOrder {
+    fn from(info: &StandingOrderInfo) -> Self {
+        AuthorisedStandingOrder {
+            from_address: info.from_address.clone(),
+            to_address: info.to_address.clone(),
+            amount: info.amount,
+            fee: info.fee,
+            interval: info.interval,
+            start_at: info.start_at,
+            end_at: info.end_at,
+            memo: info.memo.clone(),
+            metadata: info.metadata.clone(),
+        }
+    }
+}
+
+//THE PASSWORD OF A REPLAYED STANDING ORDER IS EMPTY, IT IS NOT CHECKED AGAIN
+impl From<AuthorisedStandingOrder> for StandingOrderInfo {
+    fn from(order: AuthorisedStandingOrder) -> Self {
+        StandingOrderInfo {
+            from_address: order.from_address,
+            password: String::new(),
+            to_address: order.to_address,
+            amount: order.amount,
+            fee: order.fee,
+            interval: order.interval,
+            start_at: order.start_at,
+            end_at: order.end_at,
+            memo: order.memo,
+            metadata: order.metadata,
+        }
+    }
+}
+
+impl WriteAheadLog {
+    //OPEN THE LOG OR CREATE IT IF IT DOESN'T EXIST AND RETURN THE ENTRIES ALREADY IN IT
+    //A LAST LINE THAT WAS NOT WRITTEN COMPLETELY IS REMOVED, ITS COMMAND WAS NEVER APPLIED
+    pub fn open(path: &Path) -> Result<(Self, Vec<LogEntry>), BlockChainError> {
+        let mut file = open_options()
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(log_failed)?;
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes).map_err(log_failed)?;
+
+        let entries = read_entries(&bytes)?;
+        let complete = complete_length(&bytes);
+        if complete < bytes.len() {
+            file.set_len(complete as u64).map_err(log_failed)?;
+        }
+
+        let log = WriteAheadLog {
+            path: path.to_path_buf(),
+            file,
+            next_sequence: entries.len() as u64,
+            poisoned: false,
+        };
+        Ok((log, entries))
+    }
+
+    //WRITE THE COMMAND AT THE END OF THE LOG AND WAIT UNTIL IT IS ON THE DISK
+    //IF THAT FAILS THE LOG IS CUT BACK TO WHERE IT WAS, SO THE REJECTED COMMAND IS NOT REPLAYED AND THE NEXT ONE GETS ITS SEQUENCE
+    pub fn append(&mut self, command: Command) -> Result<(), BlockChainError> {
+        if self.poisoned {
+            return Err(BlockChainError::LogFailed);
+        }
+
+        let entry = LogEntry {
+            sequence: self.next_sequence,
+            command,
+        };
+        let mut line = serde_json::to_vec(&entry).unwrap();
+        line.push(b'\n');
+
+        let length = self.file.metadata().map_err(log_failed)?.len();
+        let written = self
+            .file
+            .write_all(&line)
+            .and_then(|_| self.file.sync_data());
+        if let Err(error) = written {
+            if let Err(error) = self.file.set_len(length) {
+                error!(%error, "write-ahead log could not be cut back, it is poisoned");
+                self.poisoned = true;
+            }
+            return Err(log_failed(error));
+        }
+        self.next_sequence += 1;
+
+        Ok(())
+    }
+
+    //REPLACE THE LOG WITH THE GIVEN COMMANDS, WHICH REBUILD THE SAME STATE AS ALL THE COMMANDS IN IT, AND WAIT UNTIL IT IS ON THE DISK
+    //THE NEW LOG IS WRITTEN NEXT TO THE OLD ONE AND RENAMED OVER IT, SO A CRASH LEAVES ONE OF THE TWO
+    pub fn checkpoint(&mut self, commands: Vec<Command>) -> Result<(), BlockChainError> {
+        if self.poisoned {
+            return Err(BlockChainError::LogFailed);
+        }
+
+        let mut bytes = vec![];
+        for (sequence, command) in commands.into_iter().enumerate() {
+            let entry = LogEntry {
+                sequence: sequence as u64,
+                command,
+            };
+            serde_json::to_writer(&mut bytes, &entry).unwrap();
+            bytes.push(b'\n');
+        }
+        let next_sequence = read_entries(&bytes)?.len() as u64;
+
+        let checkpoint = self.path.with_extension("checkpoint");
+        let mut file = open_options()
+            .write(true)
+            .truncate(true)
+            .open(&checkpoint)
+            .map_err(log_failed)?;
+        file.write_all(&bytes).map_err(log_failed)?;
+        file.sync_all().map_err(log_failed)?;
+        fs::rename(&checkpoint, &self.path).map_err(log_failed)?;
+
+        //THE RENAME IS ONLY ON THE DISK ONCE THE DIRECTORY IS
+        #[cfg(unix)]
+        if let Some(directory) = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            File::open(directory)
+                .and_then(|directory| directory.sync_all())
+                .map_err(log_failed)?;
+        }
+
+        self.file = open_options()
+            .append(true)
+            .open(&self.path)
+            .map_err(log_failed)?;
+        self.next_sequence = next_sequence;
+
+        Ok(())
+    }
+}
+
+//THE OPTIONS THE FILES OF THE LOG ARE OPENED WITH, THEY ARE CREATED READABLE BY THEIR OWNER ONLY
+fn open_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.create(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+}
+
+//READ THE ENTRIES OF A LOG AND CHECK THAT NONE IS MISSING
+//A LAST LINE WITHOUT A NEW LINE AT THE END IS IGNORED, IT WAS STILL BEING WRITTEN
+pub fn read_entries(bytes: &[u8]) -> Result<Vec<LogEntry>, BlockChainError> {
+    let entries = bytes[..complete_length(bytes)]
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.trim_ascii().is_empty())
+        .map(|line| serde_json::from_slice(line).map_err(|_| BlockChainError::InvalidLog))
+        .collect::<Result<Vec<LogEntry>, BlockChainError>>()?;
+
+    for (sequence, entry) in entries.iter().enumerate() {
+        if entry.sequence != sequence as u64 {
+            return Err(BlockChainError::InvalidLog);
+        }
+    }
+
+    Ok(entries)
+}
+
+//THE LOG IS WRITTEN TO THE FILE FROM 'WAL_PATH', THERE IS NO LOG IF IT IS NOT SET
+pub fn path() -> Option<PathBuf> {
+    env::var_os("WAL_PATH")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+//THE LENGTH OF THE LINES THAT WERE WRITTEN COMPLETELY
+fn complete_length(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |end| end + 1)
+}
+
+//LOG THE ERROR FROM THE FILE AND RETURN THE ONE SHOWN TO THE USERS
+fn log_failed(error: std::io::Error) -> BlockChainError {
+    error!(%error, "write-ahead log failed");
+    BlockChainError::LogFailed
+}
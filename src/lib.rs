@@ -1,24 +1,190 @@
+#![allow(non_snake_case)]
 pub mod api;
 pub mod blockchain;
-use actix_web::{App, HttpServer};
+pub mod logging;
+pub mod metrics;
+pub mod scheduler;
+pub mod shutdown;
+pub mod sweeper;
+use actix_web::{
+    middleware::from_fn,
+    web::{scope, Data, PayloadConfig, ServiceConfig},
+    App, HttpServer,
+};
+use api::admin::{export_chain, import_chain, require_admin_token, AdminToken, MAX_SNAPSHOT_SIZE};
+use api::config::ApiConfig;
+use api::events::{subscribe_events, subscribe_events_ws};
+use api::idempotency::{honour_idempotency_key, IdempotencyKeys};
+use api::legacy::deprecate;
+use api::metrics::{record_request_metrics, show_metrics};
+use api::node::{health, info, ready};
+use api::openapi::swagger_ui;
+use api::request_id::trace_request;
 use api::requests::{
-    create_transaction, create_wallet, get_wallet_balance, get_wallet_transactions,
-    mine_pending_transactions, show_blockchain, add_coins,
+    add_coins, create_transaction, create_wallet, get_block, get_block_by_hash, get_blocks,
+    get_latest_block, get_transaction, get_wallet_balance, get_wallet_history,
+    get_wallet_transactions, mine_pending_transactions, show_blockchain,
 };
+use api::v1::{self, V1_PREFIX};
+use blockchain::block_chain::BLOCKCHAIN;
+use blockchain::mempool::MempoolLimits;
+use std::{env, time::Duration};
+use tracing::warn;
+
+//REGISTER ALL THE ENDPOINTS OF THE API, INCLUDING THE LEGACY ROUTES
+pub fn routes(cfg: &mut ServiceConfig) {
+    configure(ApiConfig::default())(cfg)
+}
+
+//REGISTER THE '/v1' ENDPOINTS AND THE ENDPOINTS OF THE NODE
+//THE LEGACY ROUTES AND THE ADMIN ENDPOINTS ARE ONLY REGISTERED IF ENABLED IN THE CONFIG
+pub fn configure(config: ApiConfig) -> impl Fn(&mut ServiceConfig) + Clone {
+    //THE RESPONSES KEPT FOR THE 'Idempotency-Key' HEADER ARE SHARED BY ALL THE WORKERS
+    let idempotency_keys = Data::new(IdempotencyKeys::new(config.idempotency_window));
+    //THE NODE INFO SHOWS THE FEATURES THAT ARE ENABLED BY THE CONFIG
+    let api_config = Data::new(config.clone());
+
+    move |cfg| {
+        let mut versioned = scope(V1_PREFIX);
+
+        //THE ADMIN ENDPOINTS CAN REPLACE THE WHOLE CHAIN, SO THEY ARE ONLY SERVED WITH A TOKEN
+        if let Some(token) = &config.admin_token {
+            versioned = versioned.service(
+                scope("/admin")
+                    .app_data(Data::new(AdminToken(token.clone())))
+                    .app_data(PayloadConfig::new(MAX_SNAPSHOT_SIZE))
+                    .wrap(from_fn(require_admin_token))
+                    .service(export_chain)
+                    .service(import_chain),
+            );
+        }
+
+        //THE OTHER '/v1' ENDPOINTS HONOUR THE 'Idempotency-Key' HEADER
+        //THIS SCOPE MATCHES EVERY PATH UNDER '/v1', SO IT HAS TO BE REGISTERED AFTER THE ADMIN ENDPOINTS
+        versioned = versioned.service(
+            scope("")
+                .wrap(from_fn(honour_idempotency_key))
+                .service(v1::submit_transaction)
+                .service(v1::cancel_transaction)
+                .service(v1::submit_batch)
+                .service(v1::claim_escrow)
+                .service(v1::refund_escrow)
+                .service(v1::mine_block)
+                .service(v1::create_wallet)
+                .service(v1::add_coins)
+                .service(v1::get_wallet_balance)
+                .service(v1::get_wallet_history)
+                .service(v1::create_multisig_wallet)
+                .service(v1::propose_transfer)
+                .service(v1::get_proposals)
+                .service(v1::get_proposal)
+                .service(v1::approve_proposal)
+                .service(v1::create_standing_order)
+                .service(v1::get_standing_orders)
+                .service(v1::revoke_standing_order)
+                .service(get_latest_block)
+                .service(get_block_by_hash)
+                .service(get_block)
+                .service(get_blocks)
+                .service(get_transaction)
+                .service(subscribe_events_ws)
+                .service(subscribe_events),
+        );
+
+        cfg.app_data(idempotency_keys.clone())
+            .app_data(api_config.clone())
+            .service(versioned)
+            .service(show_metrics)
+            .service(health)
+            .service(ready)
+            .service(info)
+            .service(swagger_ui());
+
+        //THE ROUTES FROM BEFORE '/v1' ARE KEPT FOR THE OLD CLIENTS, THEY ANSWER WITH A 'Deprecation' HEADER
+        //THIS SCOPE MATCHES EVERY PATH, SO IT HAS TO BE REGISTERED LAST
+        if config.legacy_routes {
+            cfg.service(
+                scope("")
+                    .wrap(from_fn(honour_idempotency_key))
+                    .wrap(from_fn(deprecate))
+                    .service(create_transaction)
+                    .service(mine_pending_transactions)
+                    .service(show_blockchain)
+                    .service(get_wallet_balance)
+                    .service(create_wallet)
+                    .service(get_wallet_transactions)
+                    .service(add_coins)
+                    .service(get_latest_block)
+                    .service(get_block_by_hash)
+                    .service(get_block)
+                    .service(get_blocks)
+                    .service(get_transaction)
+                    .service(get_wallet_history)
+                    .service(subscribe_events_ws)
+                    .service(subscribe_events),
+            );
+        }
+    }
+}
 
 #[actix_web::main]
 pub async fn run() -> std::io::Result<()> {
-    HttpServer::new(|| {
+    logging::init();
+
+    //REBUILD THE STATE FROM THE WRITE-AHEAD LOG, IF THERE IS ONE, BEFORE ACCEPTING ANY REQUEST
+    if let Some(path) = blockchain::write_ahead_log::path() {
+        BLOCKCHAIN
+            .open_log(&path)
+            .map_err(|error| std::io::Error::other(error.to_string()))?;
+    }
+
+    //THE MEMPOOL IS KEPT UNDER ITS LIMITS AND FREE OF EXPIRED TRANSACTIONS IN THE BACKGROUND
+    BLOCKCHAIN.set_mempool_limits(MempoolLimits::from_env());
+    actix_web::rt::spawn(sweeper::sweep_mempool_periodically(sweeper::interval()));
+
+    //THE PAYMENTS OF THE STANDING ORDERS ARE ADDED TO PENDING TRANSACTIONS IN THE BACKGROUND WHEN THEY ARE DUE
+    actix_web::rt::spawn(scheduler::pay_standing_orders_periodically(
+        scheduler::interval(),
+    ));
+
+    //THE SIGNALS ARE HANDLED BY 'shutdown' SO THE BLOCKCHAIN CAN STOP BEFORE THE SERVER
+    let routes = configure(ApiConfig::from_env());
+    let server = HttpServer::new(move || {
         App::new()
-            .service(create_transaction)
-            .service(mine_pending_transactions)
-            .service(show_blockchain)
-            .service(get_wallet_balance)
-            .service(create_wallet)
-            .service(get_wallet_transactions)
-            .service(add_coins)
+            .wrap(from_fn(record_request_metrics))
+            .wrap(from_fn(trace_request))
+            .configure(routes.clone())
     })
+    .disable_signals()
+    .shutdown_timeout(shutdown::timeout().as_secs())
     .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+    .run();
+
+    let shutdown = actix_web::rt::spawn(shutdown::shutdown_on_signal(server.handle()));
+    server.await?;
+
+    //LET THE SHUTDOWN FINISH IF IT WAS STARTED BY A SIGNAL, THE NODE FAILS IF IT DIDN'T FINISH IN TIME
+    if BLOCKCHAIN.is_shutting_down() {
+        shutdown.await.map_err(std::io::Error::other)??;
+    }
+    Ok(())
+}
+
+//READ A DURATION FROM THE ENVIRONMENT VARIABLE, IN SECONDS. THE DEFAULT IS USED WHEN IT IS NOT SET, NOT A NUMBER OR 0
+pub fn seconds_from_env(variable: &str, default: u64) -> Duration {
+    let seconds = match env::var(variable) {
+        Ok(seconds) => seconds
+            .parse()
+            .ok()
+            .filter(|seconds| *seconds > 0)
+            .unwrap_or_else(|| {
+                warn!(
+                    variable,
+                    seconds, "invalid number of seconds, using the default one"
+                );
+                default
+            }),
+        Err(_) => default,
+    };
+    Duration::from_secs(seconds)
 }
@@ -0,0 +1,22 @@
+use std::env;
+use tracing_subscriber::EnvFilter;
+
+//THE TEXT THAT IS LOGGED INSTEAD OF PASSWORDS AND OTHER SECRETS
+pub const REDACTED: &str = "<redacted>";
+
+//START WRITING THE LOGS TO THE STANDARD OUTPUT
+//THE LEVEL IS TAKEN FROM 'RUST_LOG' (DEFAULT 'info') AND 'LOG_FORMAT=json' SWITCHES FROM TEXT TO JSON LINES
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+
+    //A SUBSCRIBER MIGHT ALREADY BE SET, FOR EXAMPLE BY THE TESTS
+    let _ = match env::var("LOG_FORMAT").as_deref() {
+        Ok("json") => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .try_init(),
+        _ => builder.try_init(),
+    };
+}
@@ -1,6 +1,6 @@
-use blockchainApi;
+#![allow(non_snake_case)]
 
-#[allow(unused_must_use)]
-fn main () {
-    blockchainApi::run();
-}
\ No newline at end of file
+//THE NODE EXITS WITH A FAILURE STATUS WHEN IT CAN'T START OR STOPS BECAUSE OF AN ERROR
+fn main() -> std::io::Result<()> {
+    blockchainApi::run()
+}
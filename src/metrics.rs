@@ -0,0 +1,183 @@
+use crate::blockchain::block_chain::BlockChain;
+use crate::blockchain::mempool::EvictionReason;
+use lazy_static::lazy_static;
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+use std::time::Duration;
+
+//THE METRICS OF THE NODE, SHARED BY ALL THE PROGRAM
+lazy_static! {
+    pub static ref METRICS: Metrics = Metrics::new();
+}
+
+//HOLDS ALL THE METRICS THAT ARE EXPOSED IN THE PROMETHEUS TEXT FORMAT
+pub struct Metrics {
+    registry: Registry,
+    pub chain_height: IntGauge,
+    pub difficulty: IntGauge,
+    pub mempool_size: IntGauge,
+    pub mempool_bytes: IntGauge,
+    pub wallets: IntGauge,
+    pub mining_duration: Histogram,
+    pub hash_rate: Histogram,
+    pub requests: IntCounterVec,
+    pub request_duration: HistogramVec,
+    pub errors: IntCounterVec,
+    pub evictions: IntCounterVec,
+}
+
+impl Metrics {
+    //CREATE AND REGISTER ALL THE METRICS
+    fn new() -> Self {
+        let metrics = Metrics {
+            registry: Registry::new(),
+            chain_height: IntGauge::new("blockchain_height", "Number of blocks in the chain")
+                .unwrap(),
+            difficulty: IntGauge::new("blockchain_difficulty", "Current mining difficulty")
+                .unwrap(),
+            mempool_size: IntGauge::new(
+                "blockchain_mempool_size",
+                "Number of pending transactions",
+            )
+            .unwrap(),
+            mempool_bytes: IntGauge::new(
+                "blockchain_mempool_bytes",
+                "Estimated memory used by the pending transactions",
+            )
+            .unwrap(),
+            wallets: IntGauge::new("blockchain_wallets", "Number of wallets").unwrap(),
+            mining_duration: Histogram::with_opts(
+                HistogramOpts::new(
+                    "blockchain_mining_duration_seconds",
+                    "Time spent on the proof of work of a block",
+                )
+                .buckets(exponential_buckets(0.001, 4.0, 10).unwrap()),
+            )
+            .unwrap(),
+            hash_rate: Histogram::with_opts(
+                HistogramOpts::new(
+                    "blockchain_hash_rate",
+                    "Hashes per second computed while mining a block",
+                )
+                .buckets(exponential_buckets(1000.0, 4.0, 10).unwrap()),
+            )
+            .unwrap(),
+            requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "Number of HTTP requests"),
+                &["method", "endpoint", "status"],
+            )
+            .unwrap(),
+            request_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "Time spent handling HTTP requests",
+                ),
+                &["method", "endpoint"],
+            )
+            .unwrap(),
+            errors: IntCounterVec::new(
+                Opts::new(
+                    "blockchain_errors_total",
+                    "Number of errors returned by the API",
+                ),
+                &["error"],
+            )
+            .unwrap(),
+            evictions: IntCounterVec::new(
+                Opts::new(
+                    "blockchain_evicted_transactions_total",
+                    "Number of pending transactions evicted without being mined",
+                ),
+                &["reason"],
+            )
+            .unwrap(),
+        };
+
+        let registry = &metrics.registry;
+        registry
+            .register(Box::new(metrics.chain_height.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.difficulty.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.mempool_size.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.mempool_bytes.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.wallets.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.mining_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.hash_rate.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.requests.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(metrics.request_duration.clone()))
+            .unwrap();
+        registry.register(Box::new(metrics.errors.clone())).unwrap();
+        registry
+            .register(Box::new(metrics.evictions.clone()))
+            .unwrap();
+
+        metrics
+    }
+
+    //RECORD HOW LONG THE PROOF OF WORK OF A BLOCK TOOK AND HOW MANY HASHES WERE COMPUTED
+    pub fn record_mining(&self, duration: Duration, hashes: usize) {
+        let seconds = duration.as_secs_f64();
+        self.mining_duration.observe(seconds);
+        if seconds > 0.0 {
+            self.hash_rate.observe(hashes as f64 / seconds);
+        }
+    }
+
+    //RECORD A HANDLED HTTP REQUEST
+    pub fn record_request(&self, method: &str, endpoint: &str, status: u16, duration: Duration) {
+        self.requests
+            .with_label_values(&[method, endpoint, &status.to_string()])
+            .inc();
+        self.request_duration
+            .with_label_values(&[method, endpoint])
+            .observe(duration.as_secs_f64());
+    }
+
+    //RECORD AN ERROR RETURNED BY THE API
+    pub fn record_error(&self, error: &str) {
+        self.errors.with_label_values(&[error]).inc();
+    }
+
+    //RECORD A PENDING TRANSACTION THAT WAS EVICTED
+    pub fn record_eviction(&self, reason: EvictionReason) {
+        self.evictions
+            .with_label_values(&[&format!("{:?}", reason)])
+            .inc();
+    }
+
+    //UPDATE THE STATE OF THE BLOCKCHAIN AND RETURN ALL THE METRICS IN THE PROMETHEUS TEXT FORMAT
+    pub fn render(&self, blockchain: &BlockChain) -> String {
+        self.chain_height
+            .set(blockchain.chain.read().unwrap().len() as i64);
+        self.difficulty.set(blockchain.difficulty as i64);
+        let pending_transactions = blockchain.pending_transactions.lock().unwrap();
+        self.mempool_size.set(pending_transactions.len() as i64);
+        self.mempool_bytes.set(pending_transactions.bytes() as i64);
+        drop(pending_transactions);
+        self.wallets
+            .set(blockchain.wallets.read().unwrap().len() as i64);
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
@@ -0,0 +1,28 @@
+use crate::blockchain::block_chain::BLOCKCHAIN;
+use crate::blockchain::block_chain_errors::BlockChainError;
+use crate::seconds_from_env;
+use std::time::Duration;
+use tracing::{info, warn};
+
+//THE NUMBER OF SECONDS BETWEEN TWO CHECKS OF THE STANDING ORDERS WHEN NO INTERVAL IS GIVEN
+pub const DEFAULT_SCHEDULER_INTERVAL: u64 = 10;
+
+//THE TIME BETWEEN TWO CHECKS OF THE STANDING ORDERS, TAKEN FROM 'STANDING_ORDERS_INTERVAL' IN SECONDS
+pub fn interval() -> Duration {
+    seconds_from_env("STANDING_ORDERS_INTERVAL", DEFAULT_SCHEDULER_INTERVAL)
+}
+
+//ADD THE PAYMENTS OF THE STANDING ORDERS THAT ARE DUE TO PENDING TRANSACTIONS AT EVERY INTERVAL, UNTIL THE NODE SHUTS DOWN
+pub async fn pay_standing_orders_periodically(interval: Duration) {
+    let mut ticks = actix_web::rt::time::interval(interval);
+
+    loop {
+        ticks.tick().await;
+        match BLOCKCHAIN.pay_standing_orders() {
+            Ok(0) => {}
+            Ok(payments) => info!(payments, "standing orders paid"),
+            Err(BlockChainError::ShuttingDown) => return,
+            Err(error) => warn!(%error, "standing orders payment failed"),
+        }
+    }
+}
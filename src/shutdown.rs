@@ -0,0 +1,77 @@
+use crate::blockchain::block_chain::BLOCKCHAIN;
+use actix_web::dev::ServerHandle;
+use std::{env, time::Duration};
+use tracing::{error, info, warn};
+
+//THE NUMBER OF SECONDS THE SHUTDOWN CAN TAKE WHEN NO TIMEOUT IS GIVEN
+pub const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
+
+//THE TIME THE WHOLE SHUTDOWN CAN TAKE, FROM THE SIGNAL UNTIL THE STATE IS ON THE DISK, TAKEN FROM 'SHUTDOWN_TIMEOUT' IN SECONDS
+//THE REQUESTS THAT ARE STILL RUNNING ALSO HAVE THIS LONG TO FINISH
+pub fn timeout() -> Duration {
+    let seconds = match env::var("SHUTDOWN_TIMEOUT") {
+        Ok(seconds) => seconds.parse().unwrap_or_else(|_| {
+            warn!(seconds, "invalid shutdown timeout, using the default one");
+            DEFAULT_SHUTDOWN_TIMEOUT
+        }),
+        Err(_) => DEFAULT_SHUTDOWN_TIMEOUT,
+    };
+    Duration::from_secs(seconds)
+}
+
+//WAIT FOR CTRL+C OR, ON UNIX, FOR SIGTERM
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("cannot listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+//WAIT FOR A SIGNAL AND STOP THE NODE
+//THE BLOCKCHAIN STOPS ACCEPTING CHANGES AND CANCELS MINING FIRST, THEN THE SERVER STOPS TAKING NEW CONNECTIONS AND WAITS FOR THE RUNNING REQUESTS
+//AT THE END THE CHAIN, THE MEMPOOL AND THE WALLETS ARE WRITTEN TO THE WRITE-AHEAD LOG AS A CHECKPOINT. THE NODE HAS NO PEERS TO DISCONNECT
+//RETURN AN ERROR WHEN THE STATE COULD NOT BE WRITTEN OR THE SHUTDOWN TOOK LONGER THAN ITS TIMEOUT
+pub async fn shutdown_on_signal(server: ServerHandle) -> std::io::Result<()> {
+    wait_for_signal().await;
+    info!("shutdown signal received");
+
+    let timeout = timeout();
+    let shutdown = async {
+        BLOCKCHAIN.shutdown();
+        server.stop(true).await;
+
+        //THE CHECKPOINT WAITS FOR THE BLOCK THAT IS BEING MINED, SO IT IS DONE OUTSIDE OF THE ASYNC WORKERS
+        actix_web::rt::task::spawn_blocking(|| BLOCKCHAIN.checkpoint())
+            .await
+            .map_err(std::io::Error::other)?
+            .map_err(|error| std::io::Error::other(error.to_string()))
+    };
+    match actix_web::rt::time::timeout(timeout, shutdown).await {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => {
+            error!(%error, "the state could not be written on shutdown");
+            return Err(error);
+        }
+        Err(_) => {
+            error!(?timeout, "shutdown timed out");
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "shutdown timed out",
+            ));
+        }
+    }
+
+    let height = BLOCKCHAIN.chain.read().unwrap().len() - 1;
+    let pending_transactions = BLOCKCHAIN.pending_transactions.lock().unwrap().len();
+    info!(height, pending_transactions, "shutdown complete");
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use crate::blockchain::block_chain::BLOCKCHAIN;
+use crate::blockchain::block_chain_errors::BlockChainError;
+use crate::seconds_from_env;
+use std::time::Duration;
+use tracing::{info, warn};
+
+//THE NUMBER OF SECONDS BETWEEN TWO SWEEPS OF THE MEMPOOL WHEN NO INTERVAL IS GIVEN
+pub const DEFAULT_SWEEP_INTERVAL: u64 = 30;
+
+//THE TIME BETWEEN TWO SWEEPS OF THE MEMPOOL, TAKEN FROM 'MEMPOOL_SWEEP_INTERVAL' IN SECONDS
+pub fn interval() -> Duration {
+    seconds_from_env("MEMPOOL_SWEEP_INTERVAL", DEFAULT_SWEEP_INTERVAL)
+}
+
+//EVICT THE EXPIRED TRANSACTIONS AND THE ONES OVER THE LIMITS OF THE MEMPOOL AT EVERY INTERVAL, UNTIL THE NODE SHUTS DOWN
+pub async fn sweep_mempool_periodically(interval: Duration) {
+    let mut ticks = actix_web::rt::time::interval(interval);
+
+    loop {
+        ticks.tick().await;
+        match BLOCKCHAIN.sweep_mempool() {
+            Ok(0) => {}
+            Ok(evicted) => info!(evicted, "mempool swept"),
+            Err(BlockChainError::ShuttingDown) => return,
+            Err(error) => warn!(%error, "mempool sweep failed"),
+        }
+    }
+}
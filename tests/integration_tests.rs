@@ -1,45 +1,85 @@
+use actix_web::{
+    body::MessageBody,
+    http::StatusCode,
+    middleware::from_fn,
+    test::{call_and_read_body, call_and_read_body_json, call_service, init_service, TestRequest},
+    App,
+};
 use blockchainApi::{
     self,
+    api::{
+        config::ApiConfig,
+        idempotency::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER},
+        legacy::DEPRECATION_HEADER,
+        metrics::record_request_metrics,
+        node::{Health, NodeInfo, Readiness},
+        openapi::ApiDoc,
+        request_id::{trace_request, REQUEST_ID_HEADER},
+        v1::V1_PREFIX,
+    },
     blockchain::{
-        block::Block,
+        batch::{BatchItem, BatchItemStatus, BatchMode, MAX_BATCH_SIZE},
+        block::{Block, BlockDetails},
         block_chain::{BlockChain, BLOCKCHAIN},
-        transaction::TransactionInfo,
-        wallet::WalletInfo,
+        block_chain_errors::BlockChainError,
+        chain_index::ChainIndex,
+        escrow::{hash_of, ClaimInfo, HashLock, Settlement},
+        events::{Event, EventFilter, EventType},
+        mempool::{EvictionReason, MempoolLimits},
+        multisig::{MultisigWalletInfo, OwnerCredentials, ProposalInfo, ProposalStatus},
+        query::{BlockQuery, Direction, HistoryQuery, Page, SortOrder, TransactionState},
+        snapshot::{ChainSnapshot, SnapshotFormat, SNAPSHOT_VERSION},
+        standing_order::{StandingOrderInfo, StandingOrderStatus, MAX_STANDING_ORDER_INTERVAL},
+        transaction::{
+            Expiry, LockTime, Metadata, Transaction, TransactionDetails, TransactionInfo,
+            MAX_MEMO_LENGTH, MAX_METADATA_ENTRIES,
+        },
+        wallet::{AddressType, LockedCoins, Wallet, WalletCoins, WalletInfo},
+        write_ahead_log::{read_entries, Command},
     },
 };
+use std::{
+    fs,
+    future::poll_fn,
+    io::{self, Write},
+    pin::pin,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime},
+};
+use utoipa::OpenApi;
 
 #[test]
 fn test_global_blockchain() {
     //GET GLOBAL BLOCKCHAIN
-    let global_blockchain = BLOCKCHAIN.lock().unwrap();
+    let global_blockchain = &*BLOCKCHAIN;
 
     //CREATE A NEW BLOCKCHAIN WITH DEFAULT VALUES
-    let genesis_block = Block::new(0, &vec![]);
-    let blockchain = BlockChain {
-        chain: vec![genesis_block],
-        difficulty: 2,
-        pending_transactions: vec![],
-        mining_reward: 100,
-        name: "Blockchain".to_string(),
-        wallets: vec![],
-    };
+    let blockchain = BlockChain::default();
 
     //CHECK IF GLOBAL BLOCKCHAIN AND NEW CREATED BLOCKCHAIN ARE THE SAME
-    assert_eq!(global_blockchain.chain.len(), blockchain.chain.len());
+    assert_eq!(
+        global_blockchain.chain.read().unwrap().len(),
+        blockchain.chain.read().unwrap().len()
+    );
     assert_eq!(global_blockchain.difficulty, blockchain.difficulty);
     assert_eq!(
-        global_blockchain.pending_transactions.len(),
-        blockchain.pending_transactions.len()
+        global_blockchain.pending_transactions.lock().unwrap().len(),
+        blockchain.pending_transactions.lock().unwrap().len()
     );
     assert_eq!(global_blockchain.mining_reward, blockchain.mining_reward);
     assert_eq!(global_blockchain.name, blockchain.name);
-    assert_eq!(global_blockchain.wallets.len(), blockchain.wallets.len());
+    assert_eq!(
+        global_blockchain.wallets.read().unwrap().len(),
+        blockchain.wallets.read().unwrap().len()
+    );
+    assert_eq!(blockchain.chain.read().unwrap()[0].index, 0);
 }
 
 #[test]
 fn test_blockchain() {
-    //GET BLOCKCHAIN
-    let mut blockchain = BLOCKCHAIN.lock().unwrap();
+    //CREATE A BLOCKCHAIN WITH DEFAULT VALUES
+    let blockchain = BlockChain::default();
 
     //CREATE 'FROM' WALLET INFO
     let from_wallet = WalletInfo {
@@ -69,6 +109,13 @@ fn test_blockchain() {
         from_password: "pass".to_string(),
         to_address: "to_address".to_string(),
         amount: 50,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
     };
 
     //CREATE TRANSACTION
@@ -81,18 +128,17 @@ fn test_blockchain() {
     );
 
     //CHECK PENDING TRANSACTIONS LENGTH
-    assert_eq!(blockchain.pending_transactions.len(), 1);
+    assert_eq!(blockchain.pending_transactions.lock().unwrap().len(), 1);
 
     //CREATE NEW TRANSACTION
     blockchain.create_transaction(transaction).unwrap();
 
     //CHECK PENDING TRANSACTIONS LENGTH
-    assert_eq!(blockchain.pending_transactions.len(), 2);
+    assert_eq!(blockchain.pending_transactions.lock().unwrap().len(), 2);
 
     //MINE PENDING TRANSACTIONS
-    let mine_pending_transactions_resp = blockchain
-        .mine_pending_transactions(&"to_address".to_string())
-        .unwrap();
+    let mine_pending_transactions_resp =
+        blockchain.mine_pending_transactions("to_address").unwrap();
 
     //CHECK MINE PENDING TRANSACTIONS RESPONSE
     assert_eq!(
@@ -101,31 +147,33 @@ fn test_blockchain() {
     );
 
     //CHECK PENDING TRANSACTIONS LENGTH
-    assert_eq!(blockchain.pending_transactions.len(), 1);
+    assert_eq!(blockchain.pending_transactions.lock().unwrap().len(), 1);
 
     //GET 'FROM' WALLET BALLANCE
     let from_wallet_balance = blockchain
-        .get_balance_of_wallet(&"from_address".to_string(), &"pass".to_string())
-        .unwrap();
+        .get_balance_of_wallet("from_address", "pass")
+        .unwrap()
+        .balance;
 
     //CHECK 'FROM' WALLET BALLANCE
     assert_eq!(from_wallet_balance, 0);
 
     //GET 'TO' WALLET BALLANCE
     let to_wallet_balance = blockchain
-        .get_balance_of_wallet(&"to_address".to_string(), &"pass".to_string())
-        .unwrap();
+        .get_balance_of_wallet("to_address", "pass")
+        .unwrap()
+        .balance;
 
     //CHECK 'TO' WALLET BALLANCE
     assert_eq!(to_wallet_balance, 100);
     //CHECK BLOCKCHAIN LENGTH
-    assert_eq!(blockchain.chain.len(), 2);
+    assert_eq!(blockchain.chain.read().unwrap().len(), 2);
     //CHECK NR OF TRANSACTIONS FROM THE SECOND BLOCK
-    assert_eq!(blockchain.chain[1].transactions.len(), 2);
+    assert_eq!(blockchain.chain.read().unwrap()[1].transactions.len(), 2);
 
     //MINE PENDING TRANSACTIONS
     let mine_pending_transactions_resp = blockchain
-        .mine_pending_transactions(&"from_address".to_string())
+        .mine_pending_transactions("from_address")
         .unwrap();
 
     //CHECK MINE PENDING TRANSACTIONS RESPONSE
@@ -136,17 +184,3055 @@ fn test_blockchain() {
 
     //GET 'TO' WALLET BALLANCE
     let to_wallet_balance = blockchain
-        .get_balance_of_wallet(&"to_address".to_string(), &"pass".to_string())
-        .unwrap();
+        .get_balance_of_wallet("to_address", "pass")
+        .unwrap()
+        .balance;
 
     //CHECK 'TO' WALLET BALLANCE
     assert_eq!(to_wallet_balance, 200);
     //CHECK BLOCKCHAIN LENGTH
-    assert_eq!(blockchain.chain.len(), 3);
+    assert_eq!(blockchain.chain.read().unwrap().len(), 3);
     //CHECK NR OF TRANSACTIONS FROM THE THIRD BLOCK
-    assert_eq!(blockchain.chain[2].transactions.len(), 1);
+    assert_eq!(blockchain.chain.read().unwrap()[2].transactions.len(), 1);
+}
+
+#[test]
+fn test_reads_while_mining() {
+    //CREATE A BLOCKCHAIN WITH A HIGHER DIFFICULTY SO MINING TAKES LONGER
+    let mut blockchain = BlockChain::default();
+    blockchain.difficulty = 3;
+    let blockchain = Arc::new(blockchain);
+
+    //CREATE WALLETS
+    for address in ["miner", "reader"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 1000,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+
+    //MINE A FEW BLOCKS ON ANOTHER THREAD
+    let miner = {
+        let blockchain = Arc::clone(&blockchain);
+        thread::spawn(move || {
+            for _ in 0..5 {
+                blockchain
+                    .create_transaction(TransactionInfo {
+                        from_address: "miner".to_string(),
+                        from_password: "pass".to_string(),
+                        to_address: "reader".to_string(),
+                        amount: 1,
+                        fee: 0,
+                        expiry: None,
+                        nonce: None,
+                        memo: None,
+                        metadata: Metadata::new(),
+                        lock: None,
+                        hash_lock: None,
+                    })
+                    .unwrap();
+                blockchain.mine_pending_transactions("miner").unwrap();
+            }
+        })
+    };
+
+    //READ THE CHAIN AND THE BALANCES WHILE MINING, THE CHAIN CAN ONLY GROW
+    let mut last_length = 0;
+    while !miner.is_finished() {
+        let length = blockchain.chain.read().unwrap().len();
+        assert!(length >= last_length);
+        last_length = length;
+
+        blockchain.get_balance_of_wallet("reader", "pass").unwrap();
+    }
+    miner.join().unwrap();
+
+    //CHECK THE FINAL STATE
+    assert_eq!(blockchain.chain.read().unwrap().len(), 6);
+    assert_eq!(
+        blockchain
+            .get_balance_of_wallet("reader", "pass")
+            .unwrap()
+            .balance,
+        1005
+    );
+}
+
+#[test]
+fn test_block_and_transaction_lookup() {
+    //CREATE A BLOCKCHAIN WITH TWO WALLETS
+    let blockchain = BlockChain::default();
+    for address in ["from_address", "to_address"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+
+    //CREATE A TRANSACTION AND CHECK THAT IT IS PENDING
+    let transaction = blockchain
+        .submit_transaction(TransactionInfo {
+            from_address: "from_address".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "to_address".to_string(),
+            amount: 10,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        })
+        .unwrap();
+    let status = blockchain.get_transaction(&transaction.id).unwrap();
+    assert_eq!(status.status, "pending");
+    assert_eq!(status.block_height, None);
+    assert_eq!(status.transaction, transaction);
+
+    //MINE THE TRANSACTION AND CHECK THAT IT IS CONFIRMED
+    blockchain.mine_pending_transactions("to_address").unwrap();
+    let status = blockchain.get_transaction(&transaction.id).unwrap();
+    assert_eq!(status.status, "confirmed");
+    assert_eq!(status.block_height, Some(1));
+    assert_eq!(status.confirmations, 1);
+
+    //EVERY NEW BLOCK ADDS A CONFIRMATION
+    blockchain.mine_pending_transactions("to_address").unwrap();
+    assert_eq!(
+        blockchain
+            .get_transaction(&transaction.id)
+            .unwrap()
+            .confirmations,
+        2
+    );
+
+    //LOOK UP THE BLOCKS
+    let block = blockchain.get_block(1).unwrap();
+    assert_eq!(block.transactions, vec![transaction]);
+    assert_eq!(blockchain.get_block_by_hash(&block.hash).unwrap(), block);
+    assert_eq!(blockchain.get_latest_block().unwrap().index, 2);
+    let all_blocks = blockchain.list_blocks(&BlockQuery::default()).unwrap();
+    assert_eq!(all_blocks.items.len(), 3);
+    assert_eq!(all_blocks.next_cursor, None);
+    let range = BlockQuery {
+        from: Some(1),
+        to: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(blockchain.list_blocks(&range).unwrap().items, vec![block]);
+
+    //CHECK THE ERRORS
+    assert!(matches!(
+        blockchain.get_block(3),
+        Err(BlockChainError::BlockNotFound)
+    ));
+    assert!(matches!(
+        blockchain.get_block_by_hash("missing"),
+        Err(BlockChainError::BlockNotFound)
+    ));
+    let invalid_range = BlockQuery {
+        from: Some(2),
+        to: Some(1),
+        ..Default::default()
+    };
+    assert!(matches!(
+        blockchain.list_blocks(&invalid_range),
+        Err(BlockChainError::InvalidBlockRange)
+    ));
+    assert!(matches!(
+        blockchain.get_transaction("missing"),
+        Err(BlockChainError::TransactionNotFound)
+    ));
+}
+
+#[actix_web::test]
+async fn test_block_lookup_endpoints() {
+    let app = init_service(App::new().configure(blockchainApi::routes)).await;
+
+    //GET THE GENESIS BLOCK BY HEIGHT
+    let request = TestRequest::get().uri("/blocks/0").to_request();
+    let genesis: BlockDetails = call_and_read_body_json(&app, request).await;
+    assert_eq!(genesis.index, 0);
+
+    //GET THE GENESIS BLOCK BY HASH
+    let request = TestRequest::get()
+        .uri(&format!("/blocks/hash/{}", genesis.hash))
+        .to_request();
+    let block: BlockDetails = call_and_read_body_json(&app, request).await;
+    assert_eq!(block, genesis);
+
+    //GET A RANGE OF BLOCKS
+    let request = TestRequest::get().uri("/blocks?from=0&to=0").to_request();
+    let blocks: Page<BlockDetails> = call_and_read_body_json(&app, request).await;
+    assert_eq!(blocks.items, vec![genesis]);
+    assert_eq!(blocks.next_cursor, None);
+
+    //UNKNOWN BLOCKS AND TRANSACTIONS ARE NOT FOUND
+    let request = TestRequest::get().uri("/blocks/hash/missing").to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    let request = TestRequest::get().uri("/transactions/missing").to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[test]
+fn test_pagination_and_filters() {
+    //CREATE A BLOCKCHAIN WITH THREE WALLETS
+    let blockchain = BlockChain::default();
+    for address in ["a", "b", "c"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let transfer = |from: &str, to: &str, amount: u32| {
+        blockchain
+            .submit_transaction(TransactionInfo {
+                from_address: from.to_string(),
+                from_password: "pass".to_string(),
+                to_address: to.to_string(),
+                amount,
+                fee: 0,
+                expiry: None,
+                nonce: None,
+                memo: None,
+                metadata: Metadata::new(),
+                lock: None,
+                hash_lock: None,
+            })
+            .unwrap()
+    };
+
+    //THREE MINED TRANSACTIONS AND ONE PENDING TRANSACTION TOUCH 'a'
+    transfer("a", "b", 10);
+    transfer("a", "c", 20);
+    transfer("b", "a", 5);
+    blockchain.mine_pending_transactions("c").unwrap();
+    let pending = transfer("a", "b", 30);
+
+    //READ THE HISTORY OF 'a' IN PAGES OF TWO
+    let history = |query: HistoryQuery| blockchain.get_wallet_history("a", "pass", &query).unwrap();
+    let first_page = history(HistoryQuery {
+        limit: Some(2),
+        ..Default::default()
+    });
+    assert_eq!(first_page.items.len(), 2);
+    assert_eq!(first_page.items[0].transaction.amount, 10);
+    let second_page = history(HistoryQuery {
+        limit: Some(2),
+        cursor: first_page.next_cursor,
+        ..Default::default()
+    });
+    assert_eq!(second_page.items.len(), 2);
+    assert_eq!(second_page.items[1].transaction, pending);
+    assert_eq!(second_page.next_cursor, None);
+
+    //NEWEST FIRST
+    let newest = history(HistoryQuery {
+        limit: Some(1),
+        order: Some(SortOrder::Desc),
+        ..Default::default()
+    });
+    assert_eq!(newest.items[0].transaction, pending);
+    assert_eq!(newest.items[0].status, TransactionState::Pending);
+    assert_eq!(newest.items[0].block_height, None);
+
+    //FILTERS
+    let incoming = history(HistoryQuery {
+        direction: Some(Direction::In),
+        ..Default::default()
+    });
+    assert_eq!(incoming.items.len(), 1);
+    assert_eq!(incoming.items[0].transaction.amount, 5);
+    assert_eq!(incoming.items[0].block_height, Some(1));
+    let with_b = history(HistoryQuery {
+        counterparty: Some("b".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(with_b.items.len(), 3);
+    let large_confirmed = history(HistoryQuery {
+        min_amount: Some(15),
+        status: Some(TransactionState::Confirmed),
+        ..Default::default()
+    });
+    assert_eq!(large_confirmed.items.len(), 1);
+    assert_eq!(large_confirmed.items[0].transaction.amount, 20);
+
+    //LIST THE BLOCKS THAT TOUCH 'b', NEWEST FIRST
+    blockchain.mine_pending_transactions("c").unwrap();
+    let blocks = blockchain
+        .list_blocks(&BlockQuery {
+            address: Some("b".to_string()),
+            order: Some(SortOrder::Desc),
+            limit: Some(1),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(blocks.items[0].index, 2);
+    assert_eq!(blocks.next_cursor, Some("2".to_string()));
+    let blocks = blockchain
+        .list_blocks(&BlockQuery {
+            address: Some("b".to_string()),
+            order: Some(SortOrder::Desc),
+            cursor: blocks.next_cursor,
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(blocks.items.len(), 1);
+    assert_eq!(blocks.items[0].index, 1);
+
+    //INVALID CURSORS AND PAGE SIZES
+    let invalid_cursor = HistoryQuery {
+        cursor: Some("not a cursor".to_string()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        blockchain.get_wallet_history("a", "pass", &invalid_cursor),
+        Err(BlockChainError::InvalidCursor)
+    ));
+    let invalid_limit = BlockQuery {
+        limit: Some(0),
+        ..Default::default()
+    };
+    assert!(matches!(
+        blockchain.list_blocks(&invalid_limit),
+        Err(BlockChainError::InvalidPageSize)
+    ));
+
+    //A PENDING CURSOR STILL POINTS AT ITS TRANSACTION WHEN THE ONES BEFORE IT ARE CANCELLED OR IT IS MINED
+    let unrelated = transfer("b", "c", 1);
+    let first = transfer("a", "b", 1);
+    let second = transfer("c", "a", 1);
+    let pending_page = history(HistoryQuery {
+        limit: Some(1),
+        status: Some(TransactionState::Pending),
+        ..Default::default()
+    });
+    assert_eq!(pending_page.items[0].transaction, first);
+    blockchain
+        .cancel_transaction(&unrelated.id, "pass")
+        .unwrap();
+    let next_page = history(HistoryQuery {
+        cursor: pending_page.next_cursor.clone(),
+        status: Some(TransactionState::Pending),
+        ..Default::default()
+    });
+    assert_eq!(next_page.items.len(), 1);
+    assert_eq!(next_page.items[0].transaction, second);
+    blockchain.mine_pending_transactions("c").unwrap();
+    let next_page = history(HistoryQuery {
+        cursor: pending_page.next_cursor,
+        ..Default::default()
+    });
+    assert_eq!(next_page.items.len(), 1);
+    assert_eq!(next_page.items[0].transaction.id, second.id);
+    assert_eq!(next_page.items[0].status, TransactionState::Confirmed);
+}
+
+#[test]
+fn test_address_index() {
+    //CREATE A BLOCKCHAIN WITH TWO WALLETS AND MINE A FEW TRANSACTIONS BETWEEN THEM
+    let blockchain = BlockChain::default();
+    for address in ["a", "b"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    for amount in 1..=3 {
+        blockchain
+            .create_transaction(TransactionInfo {
+                from_address: "a".to_string(),
+                from_password: "pass".to_string(),
+                to_address: "b".to_string(),
+                amount,
+                fee: 0,
+                expiry: None,
+                nonce: None,
+                memo: None,
+                metadata: Metadata::new(),
+                lock: None,
+                hash_lock: None,
+            })
+            .unwrap();
+        blockchain.mine_pending_transactions("b").unwrap();
+    }
+
+    //THE INDEX CONTAINS EVERY MINED TRANSACTION OF THE ADDRESS IN THE ORDER OF THE CHAIN
+    let positions = blockchain.index.read().unwrap().positions("a").to_vec();
+    assert_eq!(positions.len(), 3);
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(blockchain.index.read().unwrap().positions("b").len(), 5);
+    assert!(blockchain.index.read().unwrap().positions("c").is_empty());
+
+    //THE HISTORY IS THE SAME AFTER THE INDEX IS REBUILT FROM THE CHAIN
+    let history = blockchain
+        .get_wallet_history("b", "pass", &HistoryQuery::default())
+        .unwrap();
+    *blockchain.index.write().unwrap() = ChainIndex::default();
+    blockchain.rebuild_index();
+    assert_eq!(
+        blockchain
+            .get_wallet_history("b", "pass", &HistoryQuery::default())
+            .unwrap(),
+        history
+    );
+    assert_eq!(history.items.len(), 6);
+}
+
+#[test]
+fn test_events() {
+    //CREATE A BLOCKCHAIN WITH TWO WALLETS AND SUBSCRIBE TO ITS EVENTS
+    let blockchain = BlockChain::default();
+    for address in ["a", "b"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let mut receiver = blockchain.events.subscribe();
+
+    //CREATE A TRANSACTION, MINE IT AND ADD COINS
+    let transaction = blockchain
+        .submit_transaction(TransactionInfo {
+            from_address: "a".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "b".to_string(),
+            amount: 10,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        })
+        .unwrap();
+    blockchain.mine_pending_transactions("a").unwrap();
+    blockchain
+        .add_coins(WalletCoins {
+            address: "a".to_string(),
+            password: "pass".to_string(),
+            coins: 5,
+        })
+        .unwrap();
+
+    //CHECK THE EVENTS
+    let events: Vec<Event> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+    let balance = |address: &str, balance: u32| Event::BalanceChanged {
+        address: address.to_string(),
+        balance,
+    };
+    assert_eq!(events.len(), 6);
+    assert_eq!(
+        events[0],
+        Event::PendingTransaction {
+            transaction: transaction.clone()
+        }
+    );
+    assert_eq!(events[1], balance("a", 90));
+    assert!(
+        matches!(&events[2], Event::BlockMined { block } if block.transactions == vec![transaction.clone()])
+    );
+    assert_eq!(events[3], balance("b", 110));
+    assert!(
+        matches!(&events[4], Event::PendingTransaction { transaction } if transaction.to_address == "a")
+    );
+    assert_eq!(events[5], balance("a", 95));
+
+    //FILTER BY ADDRESS AND TYPE. BALANCES ARE ONLY SENT TO AUTHENTICATED SUBSCRIBERS
+    let only_b = EventFilter {
+        address: Some("b".to_string()),
+        ..Default::default()
+    };
+    let matching: Vec<&Event> = events
+        .iter()
+        .filter(|event| only_b.matches(event))
+        .collect();
+    assert_eq!(matching, vec![&events[0], &events[2]]);
+    let b_balances = EventFilter {
+        address: Some("b".to_string()),
+        authenticated: true,
+        types: Some(vec![EventType::BalanceChanged]),
+    };
+    let matching: Vec<&Event> = events
+        .iter()
+        .filter(|event| b_balances.matches(event))
+        .collect();
+    assert_eq!(matching, vec![&events[3]]);
+    assert!(!EventFilter::default().matches(&events[1]));
+}
+
+#[actix_web::test]
+async fn test_event_stream_endpoint() {
+    let app = init_service(App::new().configure(blockchainApi::routes)).await;
+
+    //UNKNOWN TYPES AND WALLETS ARE REJECTED
+    let request = TestRequest::get().uri("/events?types=unknown").to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::FAILED_DEPENDENCY
+    );
+    let request = TestRequest::get()
+        .uri("/events?address=missing&password=pass")
+        .to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    //SUBSCRIBE TO THE MINED BLOCKS AND RECEIVE ONE
+    let request = TestRequest::get()
+        .uri("/events?types=block_mined")
+        .to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+
+    let block = BLOCKCHAIN.get_block(0).unwrap();
+    BLOCKCHAIN.events.publish(Event::BlockMined {
+        block: block.clone(),
+    });
+    let mut body = pin!(response.into_body());
+    let chunk = poll_fn(|cx| body.as_mut().poll_next(cx))
+        .await
+        .unwrap()
+        .unwrap();
+    let expected = format!(
+        "event: block_mined\ndata: {}\n\n",
+        serde_json::to_string(&Event::BlockMined { block }).unwrap()
+    );
+    assert_eq!(chunk, expected.as_bytes());
+}
+
+#[actix_web::test]
+async fn test_metrics_endpoint() {
+    let app = init_service(
+        App::new()
+            .wrap(from_fn(record_request_metrics))
+            .configure(blockchainApi::routes),
+    )
+    .await;
+
+    //MAKE A SUCCESSFUL REQUEST AND A REQUEST THAT FAILS
+    let request = TestRequest::get().uri("/blocks/0").to_request();
+    assert_eq!(call_service(&app, request).await.status(), StatusCode::OK);
+    let request = TestRequest::get().uri("/blocks/hash/missing").to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    //CHECK THE METRICS
+    let request = TestRequest::get().uri("/metrics").to_request();
+    let body = String::from_utf8(call_and_read_body(&app, request).await.to_vec()).unwrap();
+    for expected in [
+        "blockchain_height 1",
+        "blockchain_difficulty 2",
+        "blockchain_mempool_size 0",
+        "blockchain_wallets ",
+        "blockchain_mining_duration_seconds_bucket",
+        "blockchain_hash_rate_bucket",
+        "http_requests_total{endpoint=\"/blocks/{height}\",method=\"GET\",status=\"200\"}",
+        "http_request_duration_seconds_bucket{endpoint=\"/blocks/hash/{hash}\",method=\"GET\"",
+        "blockchain_errors_total{error=\"BlockNotFound\"}",
+    ] {
+        assert!(
+            body.contains(expected),
+            "missing '{}' in:\n{}",
+            expected,
+            body
+        );
+    }
+}
+
+//COLLECTS THE LOGS WRITTEN DURING A TEST
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_logs_are_redacted() {
+    //PASSWORDS ARE NOT SHOWN WHEN THE REQUESTS ARE PRINTED
+    let transaction = TransactionInfo {
+        from_address: "a".to_string(),
+        from_password: "secret-password".to_string(),
+        to_address: "b".to_string(),
+        amount: 10,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
+    };
+    let wallet = WalletInfo {
+        address: "a".to_string(),
+        balance: 100,
+        password: "secret-password".to_string(),
+    };
+    assert!(!format!("{:?}", transaction).contains("secret-password"));
+    assert!(!format!("{:?}", wallet).contains("secret-password"));
+
+    //COLLECT THE JSON LOGS OF A FEW OPERATIONS
+    let buffer = LogBuffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_max_level(tracing::Level::DEBUG)
+        .with_writer(move || writer.clone())
+        .finish();
+
+    tracing::subscriber::with_default(subscriber, || {
+        let blockchain = BlockChain::default();
+        blockchain.create_wallet(wallet).unwrap();
+        blockchain
+            .create_wallet(WalletInfo {
+                address: "b".to_string(),
+                balance: 0,
+                password: "secret-password".to_string(),
+            })
+            .unwrap();
+        blockchain.create_transaction(transaction.clone()).unwrap();
+        blockchain.mine_pending_transactions("a").unwrap();
+        blockchain
+            .create_transaction(TransactionInfo {
+                amount: 0,
+                ..transaction
+            })
+            .unwrap_err();
+    });
+
+    //THE SPANS AND THE EVENTS ARE LOGGED, THE PASSWORDS ARE NOT
+    let logs = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    for expected in [
+        "\"name\":\"submit_transaction\"",
+        "\"name\":\"mine_block\"",
+        "\"name\":\"check_transaction_info\"",
+        "transaction added to pending transactions",
+        "block mined",
+        "Amount can't be 0 or less!",
+    ] {
+        assert!(
+            logs.contains(expected),
+            "missing '{}' in:\n{}",
+            expected,
+            logs
+        );
+    }
+    assert!(!logs.contains("secret-password"));
+}
+
+#[actix_web::test]
+async fn test_request_id() {
+    let app = init_service(
+        App::new()
+            .wrap(from_fn(trace_request))
+            .configure(blockchainApi::routes),
+    )
+    .await;
+
+    //A NEW ID IS GENERATED WHEN THE REQUEST DOESN'T HAVE ONE
+    let request = TestRequest::get().uri("/blocks/0").to_request();
+    let response = call_service(&app, request).await;
+    let request_id = response.headers().get(REQUEST_ID_HEADER).unwrap();
+    assert_eq!(request_id.len(), 36);
+
+    //THE ID SENT BY THE CLIENT IS KEPT, EVEN WHEN THE REQUEST FAILS
+    let request = TestRequest::get()
+        .uri("/blocks/hash/missing")
+        .insert_header((REQUEST_ID_HEADER, "client-id-1"))
+        .to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers().get(REQUEST_ID_HEADER).unwrap(),
+        "client-id-1"
+    );
+}
+
+#[test]
+fn test_chain_validation() {
+    let blockchain = BlockChain::default();
+    for address in ["miner", "receiver"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    blockchain
+        .create_transaction(TransactionInfo {
+            from_address: "miner".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "receiver".to_string(),
+            amount: 10,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        })
+        .unwrap();
+    blockchain.mine_pending_transactions("miner").unwrap();
+    blockchain.mine_pending_transactions("miner").unwrap();
+    assert!(blockchain.validate_chain().is_ok());
+
+    //A BLOCK LINKED TO THE BLOCK BEFORE IT HAS THE HASH OF ITS CONTENT, EVEN BEFORE IT IS MINED
+    let mut linked = Block::new(3, &[]);
+    linked.set_previous_hash("previous");
+    assert!(linked.is_valid(0));
+
+    //A CHANGED TRANSACTION NO LONGER MATCHES THE HASH OF ITS BLOCK
+    let original = blockchain.chain.read().unwrap()[2].clone();
+    blockchain.chain.write().unwrap()[2].transactions[0].amount += 1;
+    assert!(matches!(
+        blockchain.validate_chain(),
+        Err(BlockChainError::InvalidChain)
+    ));
+
+    //SO DOES A TRANSACTION WITH A CHANGED TIMESTAMP OR EXPIRY
+    for tamper in [
+        |transaction: &mut Transaction| transaction.timestamp += 1,
+        |transaction: &mut Transaction| transaction.expiry = Some(Expiry::Height(99)),
+        |transaction: &mut Transaction| transaction.expiry = Some(Expiry::Timestamp(99)),
+    ] {
+        let mut tampered = original.clone();
+        tamper(&mut tampered.transactions[0]);
+        blockchain.chain.write().unwrap()[2] = tampered;
+        assert!(matches!(
+            blockchain.validate_chain(),
+            Err(BlockChainError::InvalidChain)
+        ));
+    }
+
+    //A BLOCK THAT DOES NOT POINT TO THE BLOCK BEFORE IT
+    let mut unlinked = original.clone();
+    unlinked.previous_hash = "missing".to_string();
+    unlinked.hash = unlinked.calculate_hash();
+    blockchain.chain.write().unwrap()[2] = unlinked;
+    assert!(matches!(
+        blockchain.validate_chain(),
+        Err(BlockChainError::InvalidChain)
+    ));
+
+    //THE RESULT OF THE LAST CHECK IS KEPT, AN IMPORTED CHAIN WAS CHECKED BEFORE IT REPLACED THE CHAIN
+    assert!(!blockchain.is_chain_valid());
+    blockchain.chain.write().unwrap()[2] = original;
+    assert!(!blockchain.is_chain_valid());
+    assert!(blockchain.validate_chain().is_ok());
+    assert!(blockchain.is_chain_valid());
+    blockchain.chain.write().unwrap()[2].transactions[0].amount += 1;
+    assert!(blockchain.validate_chain().is_err());
+    let imported = blockchain.export_snapshot();
+    assert!(matches!(
+        blockchain.import_snapshot(imported),
+        Err(BlockChainError::InvalidChain)
+    ));
+    blockchain.chain.write().unwrap()[2].transactions[0].amount -= 1;
+    blockchain
+        .import_snapshot(blockchain.export_snapshot())
+        .unwrap();
+    assert!(blockchain.is_chain_valid());
+}
+
+#[actix_web::test]
+async fn test_node_endpoints() {
+    let app = init_service(App::new().configure(blockchainApi::routes)).await;
+
+    let request = TestRequest::get().uri("/health").to_request();
+    let health: Health = call_and_read_body_json(&app, request).await;
+    assert_eq!(health.status, "ok");
+
+    let request = TestRequest::get().uri("/ready").to_request();
+    let readiness: Readiness = call_and_read_body_json(&app, request).await;
+    assert!(readiness.ready);
+    assert!(readiness.chain_valid);
+    assert!(readiness.storage_loaded);
+    assert!(!readiness.shutting_down);
+
+    let request = TestRequest::get().uri("/info").to_request();
+    let info: NodeInfo = call_and_read_body_json(&app, request).await;
+    let genesis_hash = BLOCKCHAIN.get_block(0).unwrap().hash;
+    assert_eq!(info.chain_name, BLOCKCHAIN.name);
+    assert_eq!(info.chain_id, BLOCKCHAIN.chain_id);
+    assert_eq!(info.genesis_hash, genesis_hash);
+    assert_eq!(info.tip_height, 0);
+    assert_eq!(info.tip_hash, genesis_hash);
+    assert_eq!(info.difficulty, BLOCKCHAIN.difficulty);
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert!(info.features.contains(&"event_stream".to_string()));
+    assert!(info.features.contains(&"standing_orders".to_string()));
+    assert!(info.features.contains(&"legacy_routes".to_string()));
+    assert!(!info.features.contains(&"admin".to_string()));
+
+    //THE FEATURES FOLLOW THE CONFIG OF THE NODE
+    let app = init_service(App::new().configure(blockchainApi::configure(ApiConfig {
+        legacy_routes: false,
+        admin_token: Some("secret".to_string()),
+        ..ApiConfig::default()
+    })))
+    .await;
+    let request = TestRequest::get().uri("/info").to_request();
+    let info: NodeInfo = call_and_read_body_json(&app, request).await;
+    assert!(!info.features.contains(&"legacy_routes".to_string()));
+    assert!(info.features.contains(&"admin".to_string()));
+}
+
+#[test]
+fn test_shutdown() {
+    let mut blockchain = BlockChain::default();
+    blockchain.difficulty = 64;
+    let blockchain = Arc::new(blockchain);
+
+    for address in ["miner", "receiver"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let transaction = TransactionInfo {
+        from_address: "miner".to_string(),
+        from_password: "pass".to_string(),
+        to_address: "receiver".to_string(),
+        amount: 10,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
+    };
+    blockchain.create_transaction(transaction.clone()).unwrap();
+
+    //START MINING A BLOCK THAT CANNOT BE FOUND AND SHUT DOWN WHILE IT IS MINED
+    let miner = {
+        let blockchain = Arc::clone(&blockchain);
+        thread::spawn(move || blockchain.mine_pending_transactions("miner"))
+    };
+    thread::sleep(Duration::from_millis(50));
+    blockchain.shutdown();
+
+    //THE MINING IS CANCELLED AND THE TRANSACTION STAYS PENDING
+    assert!(matches!(
+        miner.join().unwrap(),
+        Err(BlockChainError::ShuttingDown)
+    ));
+    assert!(blockchain.is_shutting_down());
+    assert_eq!(blockchain.chain.read().unwrap().len(), 1);
+    assert_eq!(blockchain.pending_transactions.lock().unwrap().len(), 1);
+
+    //NO MORE CHANGES ARE ACCEPTED
+    assert!(matches!(
+        blockchain.create_transaction(transaction),
+        Err(BlockChainError::ShuttingDown)
+    ));
+    assert!(matches!(
+        blockchain.add_coins(WalletCoins {
+            address: "miner".to_string(),
+            password: "pass".to_string(),
+            coins: 10,
+        }),
+        Err(BlockChainError::ShuttingDown)
+    ));
+    assert!(matches!(
+        blockchain.mine_pending_transactions("miner"),
+        Err(BlockChainError::ShuttingDown)
+    ));
+}
+
+#[actix_web::test]
+async fn test_openapi() {
+    let app = init_service(App::new().configure(blockchainApi::routes)).await;
+
+    //THE DOCUMENT AND THE SWAGGER UI ARE SERVED
+    let request = TestRequest::get().uri("/openapi.json").to_request();
+    let document: serde_json::Value = call_and_read_body_json(&app, request).await;
+    assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+    assert!(document["components"]["schemas"]["TransactionInfo"].is_object());
+    let request = TestRequest::get().uri("/swagger-ui/").to_request();
+    assert_eq!(call_service(&app, request).await.status(), StatusCode::OK);
+
+    //EVERY HANDLER OF THE API IS DOCUMENTED
+    let documented = serde_json::to_value(ApiDoc::openapi().paths.paths).unwrap();
+    let methods = ["get", "post", "put", "patch", "delete"];
+    let mut handlers = 0;
+    for file in fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src/api")).unwrap() {
+        let source = fs::read_to_string(file.unwrap().path()).unwrap();
+
+        for line in source.lines().map(str::trim) {
+            let route = methods.into_iter().find_map(|method| {
+                let path = line.strip_prefix(&format!("#[{}(\"", method))?;
+                Some((method, path.split('"').next()?))
+            });
+
+            if let Some((method, path)) = route {
+                handlers += 1;
+                let prefixes = ["", V1_PREFIX, "/v1/admin"];
+                assert!(
+                    prefixes.iter().any(|prefix| documented
+                        [format!("{}{}", prefix, path).as_str()][method]
+                        .is_object()),
+                    "{} {} is not documented",
+                    method.to_uppercase(),
+                    path
+                );
+            }
+        }
+    }
+
+    //AND NOTHING ELSE IS DOCUMENTED
+    let operations: usize = documented
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|item| {
+            methods
+                .iter()
+                .filter(|method| item[**method].is_object())
+                .count()
+        })
+        .sum();
+    assert_eq!(handlers, operations);
+}
+
+#[actix_web::test]
+async fn test_versioned_api() {
+    //THE WRITE METHODS USED BY '/v1' RETURN WHAT THEY CREATED
+    let blockchain = BlockChain::default();
+    let wallet = blockchain
+        .add_wallet(WalletInfo {
+            address: "miner".to_string(),
+            balance: 100,
+            password: "pass".to_string(),
+        })
+        .unwrap();
+    assert_eq!(wallet.balance, 100);
+    let wallet = blockchain
+        .deposit_coins(WalletCoins {
+            address: "miner".to_string(),
+            password: "pass".to_string(),
+            coins: 5,
+        })
+        .unwrap();
+    assert_eq!(wallet.balance, 105);
+    let transaction = blockchain
+        .submit_transaction(TransactionInfo {
+            from_address: "miner".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "miner".to_string(),
+            amount: 10,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        })
+        .unwrap();
+    let block = blockchain.mine_block("miner").unwrap();
+    assert_eq!(block.index, 1);
+    assert_eq!(block.transactions, vec![transaction]);
+
+    //THE '/v1' ROUTES ARE NOT DEPRECATED, THE LEGACY ONES ARE
+    let app = init_service(App::new().configure(blockchainApi::routes)).await;
+    let request = TestRequest::get().uri("/v1/blocks/0").to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(DEPRECATION_HEADER).is_none());
+
+    let request = TestRequest::get().uri("/blocks/0").to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get(DEPRECATION_HEADER).unwrap(), "true");
+
+    let request = TestRequest::get().uri("/missing").to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.headers().get(DEPRECATION_HEADER).is_none());
+
+    //THE '/v1' ROUTES CHECK THEIR INPUT LIKE THE LEGACY ONES
+    let request = TestRequest::post()
+        .uri("/v1/wallets")
+        .set_json(WalletInfo {
+            address: "new".to_string(),
+            balance: -1,
+            password: "pass".to_string(),
+        })
+        .to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::FAILED_DEPENDENCY
+    );
+    let request = TestRequest::get()
+        .uri("/v1/wallets/missing/balance?password=pass")
+        .to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    //THE LEGACY ROUTES CAN BE DISABLED
+    let app = init_service(App::new().configure(blockchainApi::configure(ApiConfig {
+        legacy_routes: false,
+        ..ApiConfig::default()
+    })))
+    .await;
+    let request = TestRequest::get().uri("/blocks/0").to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    let request = TestRequest::get().uri("/v1/blocks/latest").to_request();
+    let latest: BlockDetails = call_and_read_body_json(&app, request).await;
+    assert_eq!(latest.index, 0);
+
+    //THE LEGACY ROUTES ARE DEPRECATED IN THE DOCUMENTATION
+    let document = serde_json::to_value(ApiDoc::openapi()).unwrap();
+    assert_eq!(
+        document["paths"]["/transaction/new"]["post"]["deprecated"],
+        true
+    );
+    assert!(document["paths"]["/v1/transactions"]["post"]["deprecated"].is_null());
+}
+
+#[actix_web::test]
+async fn test_idempotency_key() {
+    let app = init_service(App::new().configure(blockchainApi::configure(ApiConfig {
+        idempotency_window: Duration::from_millis(200),
+        ..ApiConfig::default()
+    })))
+    .await;
+    let wallet = |address: &str| WalletInfo {
+        address: address.to_string(),
+        balance: 100,
+        password: "pass".to_string(),
+    };
+    let create_wallet = |path: &str, key: &str, address: &str| {
+        TestRequest::post()
+            .uri(path)
+            .insert_header((IDEMPOTENCY_KEY_HEADER, key))
+            .set_json(wallet(address))
+            .to_request()
+    };
+
+    //THE FIRST RESPONSE IS RETURNED AGAIN WHEN THE REQUEST IS RETRIED WITH THE SAME KEY
+    let response = call_service(&app, create_wallet("/v1/wallets", "key-1", "")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+    let response = call_service(&app, create_wallet("/v1/wallets", "key-1", "")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers().get(IDEMPOTENT_REPLAYED_HEADER).unwrap(),
+        "true"
+    );
+    assert_eq!(
+        response.into_body().try_into_bytes().unwrap(),
+        "Address is empty!"
+    );
+
+    //THE SAME KEY CAN'T BE USED FOR ANOTHER BODY OR ANOTHER ENDPOINT
+    let response = call_service(&app, create_wallet("/v1/wallets", "key-1", "MINING")).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = call_service(&app, create_wallet("/wallet/new", "key-1", "")).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    //THE LEGACY ROUTES HONOUR THE KEY TOO, AND STAY DEPRECATED
+    for _ in 0..2 {
+        let response = call_service(&app, create_wallet("/wallet/new", "key-2", "")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers().get(DEPRECATION_HEADER).unwrap(), "true");
+    }
+
+    //THE KEYS MUST BE SHORT AND PRINTABLE
+    let key = "k".repeat(256);
+    let response = call_service(&app, create_wallet("/v1/wallets", &key, "")).await;
+    assert_eq!(response.status(), StatusCode::FAILED_DEPENDENCY);
+
+    //THE RESPONSES ARE FORGOTTEN AFTER THE WINDOW
+    thread::sleep(Duration::from_millis(250));
+    let response = call_service(&app, create_wallet("/v1/wallets", "key-1", "MINING")).await;
+    assert_eq!(response.status(), StatusCode::FAILED_DEPENDENCY);
+    assert!(response.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+
+    //THE HEADER AND THE CONFLICT ARE DOCUMENTED FOR THE 'POST' OPERATIONS, EXCEPT THE ADMIN ONES
+    let document = serde_json::to_value(ApiDoc::openapi()).unwrap();
+    let operation = &document["paths"]["/v1/transactions"]["post"];
+    assert_eq!(operation["parameters"][0]["name"], "Idempotency-Key");
+    assert!(operation["responses"]["409"].is_object());
+    assert!(document["paths"]["/v1/admin/import"]["post"]["parameters"].is_null());
+}
+
+#[test]
+fn test_snapshot() {
+    let blockchain = BlockChain::default();
+    for address in ["miner", "receiver"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let transaction = TransactionInfo {
+        from_address: "miner".to_string(),
+        from_password: "pass".to_string(),
+        to_address: "receiver".to_string(),
+        amount: 10,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
+    };
+    blockchain.create_transaction(transaction.clone()).unwrap();
+    blockchain.mine_pending_transactions("miner").unwrap();
+    blockchain.create_transaction(transaction).unwrap();
+    let snapshot = blockchain.export_snapshot();
+    assert_eq!(snapshot.header.blocks, 2);
+    assert_eq!(snapshot.pending_transactions.len(), 2);
+
+    //BOTH FORMATS CAN BE IMPORTED INTO A NEW NODE AND GIVE THE SAME STATE
+    for format in [SnapshotFormat::Jsonl, SnapshotFormat::Binary] {
+        let bytes = snapshot.encode(format);
+        let imported = BlockChain::default();
+        let height = imported
+            .import_snapshot(ChainSnapshot::decode(&bytes).unwrap())
+            .unwrap();
+        assert_eq!(height, 1);
+        assert!(imported.validate_chain().is_ok());
+        assert_eq!(
+            imported.get_latest_block().unwrap(),
+            blockchain.get_latest_block().unwrap()
+        );
+        assert_eq!(
+            imported
+                .get_balance_of_wallet("receiver", "pass")
+                .unwrap()
+                .balance,
+            110
+        );
+        assert_eq!(imported.export_snapshot().encode(format), bytes);
+
+        //THE IMPORTED CHAIN IS INDEXED AND CAN BE MINED ON
+        let id = &snapshot.blocks[1].transactions[0].id;
+        assert_eq!(imported.get_transaction(id).unwrap().block_height, Some(1));
+        imported.mine_pending_transactions("miner").unwrap();
+        assert_eq!(
+            imported
+                .get_balance_of_wallet("receiver", "pass")
+                .unwrap()
+                .balance,
+            120
+        );
+    }
+
+    //EVERY LINE OF A 'jsonl' SNAPSHOT IS A RECORD, THE HEADER IS FIRST
+    let bytes = snapshot.encode(SnapshotFormat::Jsonl);
+    let lines: Vec<serde_json::Value> = bytes
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1 + 2 + 2 + 2);
+    assert_eq!(lines[0]["type"], "header");
+    assert_eq!(lines[0]["version"], SNAPSHOT_VERSION);
+    assert_eq!(lines[1]["type"], "block");
+    assert!(snapshot.encode(SnapshotFormat::Binary).len() < bytes.len());
+
+    //A CHANGED BLOCK IS NOT IMPORTED AND THE CHAIN IS NOT REPLACED
+    let imported = BlockChain::default();
+    let mut tampered = snapshot.clone();
+    tampered.blocks[1].transactions[0].amount = 1000;
+    assert!(matches!(
+        imported.import_snapshot(tampered),
+        Err(BlockChainError::InvalidChain)
+    ));
+    assert_eq!(imported.get_latest_block().unwrap().index, 0);
+
+    //A SNAPSHOT OF ANOTHER CHAIN IS NOT IMPORTED
+    let mut other_chain = snapshot.clone();
+    other_chain.header.chain_id += 1;
+    assert!(matches!(
+        imported.import_snapshot(other_chain),
+        Err(BlockChainError::ChainIdMismatch)
+    ));
+
+    //NEWER VERSIONS AND FILES THAT WERE CUT SHORT ARE NOT READ
+    let mut newer = snapshot.clone();
+    newer.header.version = SNAPSHOT_VERSION + 1;
+    for format in [SnapshotFormat::Jsonl, SnapshotFormat::Binary] {
+        assert!(matches!(
+            ChainSnapshot::decode(&newer.encode(format)),
+            Err(BlockChainError::UnsupportedSnapshotVersion)
+        ));
+    }
+    let cut = lines[..lines.len() - 5]
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    assert!(matches!(
+        ChainSnapshot::decode(cut.as_bytes()),
+        Err(BlockChainError::InvalidSnapshot)
+    ));
+    let binary = snapshot.encode(SnapshotFormat::Binary);
+    assert!(matches!(
+        ChainSnapshot::decode(&binary[..binary.len() / 2]),
+        Err(BlockChainError::InvalidSnapshot)
+    ));
+}
+
+#[actix_web::test]
+async fn test_admin_endpoints() {
+    //WITHOUT A TOKEN THE ADMIN ENDPOINTS ARE NOT SERVED
+    let app = init_service(App::new().configure(blockchainApi::routes)).await;
+    let request = TestRequest::get().uri("/v1/admin/export").to_request();
+    assert_eq!(
+        call_service(&app, request).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let app = init_service(App::new().configure(blockchainApi::configure(ApiConfig {
+        admin_token: Some("secret".to_string()),
+        ..ApiConfig::default()
+    })))
+    .await;
+
+    //THE TOKEN IS REQUIRED
+    for token in [None, Some("Bearer wrong")] {
+        let mut request = TestRequest::get().uri("/v1/admin/export");
+        if let Some(token) = token {
+            request = request.insert_header(("authorization", token));
+        }
+        let response = call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    //THE CHAIN IS EXPORTED IN THE ASKED FORMAT
+    let request = TestRequest::get()
+        .uri("/v1/admin/export?format=binary")
+        .insert_header(("authorization", "Bearer secret"))
+        .to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/octet-stream"
+    );
+    assert_eq!(
+        response.headers().get("content-disposition").unwrap(),
+        "attachment; filename=\"chain-1-0.bin\""
+    );
+    let bytes = response.into_body().try_into_bytes().unwrap();
+    let snapshot = ChainSnapshot::decode(&bytes).unwrap();
+    assert_eq!(snapshot.header.chain_id, BLOCKCHAIN.chain_id);
+
+    //AN INVALID SNAPSHOT IS REJECTED
+    let mut tampered = snapshot.clone();
+    tampered.blocks[0].nonce += 1;
+    for body in [
+        b"not a snapshot".to_vec(),
+        tampered.encode(SnapshotFormat::Jsonl),
+    ] {
+        let request = TestRequest::post()
+            .uri("/v1/admin/import")
+            .insert_header(("authorization", "Bearer secret"))
+            .set_payload(body)
+            .to_request();
+        assert_eq!(
+            call_service(&app, request).await.status(),
+            StatusCode::FAILED_DEPENDENCY
+        );
+    }
+
+    //A VALID SNAPSHOT REPLACES THE CHAIN AND THE SUBSCRIBERS ARE TOLD ABOUT ITS NEW LAST BLOCK
+    let mut receiver = BLOCKCHAIN.events.subscribe();
+    let request = TestRequest::post()
+        .uri("/v1/admin/import")
+        .insert_header(("authorization", "Bearer secret"))
+        .set_payload(snapshot.encode(SnapshotFormat::Jsonl))
+        .to_request();
+    assert_eq!(call_service(&app, request).await.status(), StatusCode::OK);
+    let replaced = Event::ChainReplaced {
+        block: snapshot.blocks.last().unwrap().clone(),
+    };
+    let events: Vec<Event> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+    assert!(events.contains(&replaced));
+    assert!(EventFilter {
+        address: Some("anyone".to_string()),
+        ..Default::default()
+    }
+    .matches(&replaced));
+}
+
+#[test]
+fn test_write_ahead_log() {
+    let path = std::env::temp_dir().join(format!("write-ahead-log-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    //EVERY ACCEPTED COMMAND IS WRITTEN TO THE LOG, AFTER THE GENESIS BLOCK
+    let blockchain = BlockChain::default();
+    assert_eq!(blockchain.open_log(&path).unwrap(), 0);
+    for address in ["miner", "receiver"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    blockchain
+        .add_coins(WalletCoins {
+            address: "receiver".to_string(),
+            password: "pass".to_string(),
+            coins: 50,
+        })
+        .unwrap();
+    let transaction = TransactionInfo {
+        from_address: "miner".to_string(),
+        from_password: "pass".to_string(),
+        to_address: "receiver".to_string(),
+        amount: 10,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
+    };
+    blockchain.create_transaction(transaction.clone()).unwrap();
+    blockchain.mine_pending_transactions("miner").unwrap();
+    blockchain.create_transaction(transaction.clone()).unwrap();
+    blockchain.mine_pending_transactions("receiver").unwrap();
+    blockchain.create_transaction(transaction.clone()).unwrap();
+
+    //THE COMMANDS THAT FAIL ARE NOT WRITTEN
+    let mut wrong_password = transaction.clone();
+    wrong_password.from_password = "wrong".to_string();
+    assert!(blockchain.create_transaction(wrong_password).is_err());
+
+    let entries = read_entries(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!(entries.len(), 9);
+    assert!(matches!(entries[0].command, Command::Genesis { .. }));
+    assert!(matches!(entries[3].command, Command::AddCoins { .. }));
+    assert!(matches!(entries[5].command, Command::MineBlock(_)));
+    assert!(matches!(
+        entries[8].command,
+        Command::SubmitTransaction { .. }
+    ));
+
+    //ONLY THE NEW WALLETS KEEP THEIR PASSWORDS IN THE LOG, THE DEPOSITS AND THE TRANSACTIONS WERE AUTHORISED BEFORE THEY WERE WRITTEN
+    let log = fs::read_to_string(&path).unwrap();
+    assert!(log
+        .lines()
+        .filter(|line| line.contains("password"))
+        .all(|line| line.contains("\"create_wallet\"")));
 
-    blockchain.chain = vec![Block::new(0, &vec![])];
-    blockchain.pending_transactions = vec![];
-    blockchain.wallets = vec![];
+    //THE REPLAY BUILDS THE SAME CHAIN, WALLETS AND PENDING TRANSACTIONS
+    let replayed = BlockChain::default();
+    replayed.replay(&entries).unwrap();
+    assert_eq!(
+        replayed.get_latest_block().unwrap().hash,
+        blockchain.get_latest_block().unwrap().hash
+    );
+    assert_eq!(
+        replayed.export_snapshot().encode(SnapshotFormat::Jsonl),
+        blockchain.export_snapshot().encode(SnapshotFormat::Jsonl)
+    );
+
+    //A NODE THAT OPENS THE LOG AGAIN GETS ITS STATE BACK AND KEEPS WRITING AFTER THE LAST ENTRY
+    let reopened = BlockChain::default();
+    assert_eq!(reopened.open_log(&path).unwrap(), 9);
+    assert!(reopened.is_storage_loaded());
+    assert!(reopened.is_chain_valid());
+    assert_eq!(
+        reopened
+            .get_balance_of_wallet("receiver", "pass")
+            .unwrap()
+            .balance,
+        170
+    );
+    reopened.mine_pending_transactions("miner").unwrap();
+    let entries = read_entries(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!(entries.len(), 10);
+    assert_eq!(entries[9].sequence, 9);
+
+    //A LINE THAT WAS NOT WRITTEN COMPLETELY IS IGNORED
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"{\"sequence\":10,")
+        .unwrap();
+    assert_eq!(read_entries(&fs::read(&path).unwrap()).unwrap().len(), 10);
+
+    //A CHANGED BLOCK OR A MISSING ENTRY IS FOUND
+    let mut changed = entries.clone();
+    if let Command::MineBlock(block) = &mut changed[5].command {
+        block.nonce += 1;
+    }
+    assert!(matches!(
+        BlockChain::default().replay(&changed),
+        Err(BlockChainError::ReplayMismatch)
+    ));
+    let mut bytes = fs::read(&path).unwrap();
+    let second_line = bytes.iter().position(|byte| *byte == b'\n').unwrap() + 1;
+    let third_line = second_line
+        + bytes[second_line..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .unwrap()
+        + 1;
+    bytes.drain(second_line..third_line);
+    assert!(matches!(
+        read_entries(&bytes),
+        Err(BlockChainError::InvalidLog)
+    ));
+
+    //A NODE WHOSE LOG CAN'T BE REPLAYED HAS NOT LOADED ITS STATE
+    let broken_path = path.with_extension("broken");
+    fs::write(
+        &broken_path,
+        b"{\"sequence\":0,\"command\":{\"type\":\"mine_block\"}}\n",
+    )
+    .unwrap();
+    let broken = BlockChain::default();
+    assert!(broken.open_log(&broken_path).is_err());
+    assert!(!broken.is_storage_loaded());
+    fs::remove_file(&broken_path).unwrap();
+
+    //A CHECKPOINT REPLACES THE LOG WITH THE GENESIS BLOCK AND A SNAPSHOT THAT BUILD THE SAME STATE
+    reopened.checkpoint().unwrap();
+    let entries = read_entries(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(matches!(entries[0].command, Command::Genesis { .. }));
+    assert!(matches!(entries[1].command, Command::ImportSnapshot { .. }));
+    reopened
+        .create_wallet(WalletInfo {
+            address: "late".to_string(),
+            balance: 5,
+            password: "pass".to_string(),
+        })
+        .unwrap();
+    let checkpointed = BlockChain::default();
+    assert_eq!(checkpointed.open_log(&path).unwrap(), 3);
+    assert_eq!(
+        checkpointed.export_snapshot().encode(SnapshotFormat::Jsonl),
+        reopened.export_snapshot().encode(SnapshotFormat::Jsonl)
+    );
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_mempool() {
+    let blockchain = BlockChain::default();
+    for address in ["a", "b"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let send = |amount: u32| TransactionInfo {
+        from_address: "a".to_string(),
+        from_password: "pass".to_string(),
+        to_address: "b".to_string(),
+        amount,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
+    };
+
+    //THE COINS OF THE PENDING TRANSACTIONS ARE TRACKED FOR EVERY SENDER
+    let mined = blockchain.submit_transaction(send(60)).unwrap();
+    let pending_spend = |address: &str| {
+        blockchain
+            .pending_transactions
+            .lock()
+            .unwrap()
+            .pending_spend(address)
+    };
+    assert_eq!(pending_spend("a"), 60);
+    assert_eq!(pending_spend("b"), 0);
+
+    //A TRANSACTION THAT NEEDS THE COINS OF A PENDING ONE CONFLICTS WITH IT, ONE THAT NEEDS MORE THAN THE WALLET EVER HAD DOESN'T
+    assert!(matches!(
+        blockchain.submit_transaction(send(50)),
+        Err(BlockChainError::PendingSpendConflict)
+    ));
+    assert!(matches!(
+        blockchain.submit_transaction(send(500)),
+        Err(BlockChainError::NotEnoughCoins)
+    ));
+    assert_eq!(
+        blockchain
+            .get_balance_of_wallet("a", "pass")
+            .unwrap()
+            .balance,
+        40
+    );
+
+    //THE MINED COINS ARE NO LONGER PENDING
+    blockchain.mine_pending_transactions("a").unwrap();
+    assert_eq!(pending_spend("a"), 0);
+
+    //A NODE IMPORTS A SNAPSHOT WITH PENDING TRANSACTIONS THAT CAN'T BE MINED, AND WRITES A LOG
+    let mut snapshot = blockchain.export_snapshot();
+    let reward = snapshot.pending_transactions[0].clone();
+    let invalid = |from: &str, to: &str, amount: u32| TransactionDetails {
+        id: format!("{}-{}-{}", from, to, amount),
+        from_address: from.to_string(),
+        to_address: to.to_string(),
+        amount,
+        timestamp: reward.timestamp,
+        fee: 0,
+        expiry: None,
+        nonce: 0,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
+        settlement: None,
+    };
+    snapshot.pending_transactions.extend([
+        mined.clone(),
+        invalid("a", "missing", 10),
+        invalid("missing", "b", 10),
+        invalid("a", "b", 0),
+        invalid("MINING", "a", 5),
+    ]);
+
+    let path = std::env::temp_dir().join(format!("mempool-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let node = BlockChain::default();
+    node.open_log(&path).unwrap();
+    node.import_snapshot(snapshot).unwrap();
+    assert_eq!(
+        node.pending_transactions.lock().unwrap().pending_spend("a"),
+        70
+    );
+    let mut receiver = node.events.subscribe();
+
+    //ONLY THE VALID TRANSACTIONS ARE MINED, THE OTHERS ARE EVICTED AND THEIR COINS GO BACK TO THE SENDERS
+    let block = node.mine_block("a").unwrap();
+    assert_eq!(block.transactions, vec![reward]);
+    assert_eq!(node.pending_transactions.lock().unwrap().len(), 1);
+    assert_eq!(
+        node.get_balance_of_wallet("a", "pass").unwrap().balance,
+        210
+    );
+
+    let reasons = [
+        (mined.id.clone(), EvictionReason::Duplicate),
+        ("a-missing-10".to_string(), EvictionReason::UnknownRecipient),
+        ("missing-b-10".to_string(), EvictionReason::UnknownSender),
+        ("a-b-0".to_string(), EvictionReason::InvalidAmount),
+        ("MINING-a-5".to_string(), EvictionReason::InvalidReward),
+    ];
+    let evicted: Vec<(String, EvictionReason)> = std::iter::from_fn(|| receiver.try_recv().ok())
+        .filter_map(|event| match event {
+            Event::TransactionEvicted {
+                transaction,
+                reason,
+            } => Some((transaction.id, reason)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(evicted, reasons);
+
+    //THE REASON IS SHOWN WITH THE TRANSACTION
+    let status = node.get_transaction("a-missing-10").unwrap();
+    assert_eq!(status.status, "evicted");
+    assert_eq!(
+        status.eviction_reason,
+        Some(EvictionReason::UnknownRecipient)
+    );
+    assert_eq!(node.get_transaction(&mined.id).unwrap().status, "confirmed");
+
+    //THE EVICTIONS ARE IN THE LOG AND ARE REPLAYED
+    let entries = read_entries(&fs::read(&path).unwrap()).unwrap();
+    assert!(matches!(
+        &entries[2].command,
+        Command::EvictTransactions { evictions } if evictions.len() == 5
+    ));
+    let replayed = BlockChain::default();
+    replayed.replay(&entries).unwrap();
+    assert_eq!(
+        replayed.export_snapshot().encode(SnapshotFormat::Jsonl),
+        node.export_snapshot().encode(SnapshotFormat::Jsonl)
+    );
+    fs::remove_file(&path).unwrap();
+
+    //A PENDING TRANSACTION THE WALLET CAN NO LONGER PAY, BECAUSE IT CHANGED AFTER THE TRANSACTION WAS ADDED, IS EVICTED
+    //THE WALLET WAS NOT DEBITED FOR IT, SO NO COINS GO BACK TO IT, IT ONLY GETS THE REWARD OF THE PREVIOUS BLOCK
+    let overdraft = blockchain.submit_transaction(send(30)).unwrap();
+    let covered = blockchain
+        .submit_transaction(TransactionInfo {
+            from_address: "b".to_string(),
+            ..send(10)
+        })
+        .unwrap();
+    blockchain
+        .update_wallet(Wallet::new("a".to_string(), 10, "pass".to_string()))
+        .unwrap();
+    let block = blockchain.mine_block("b").unwrap();
+    assert!(block.transactions.contains(&covered));
+    assert!(!block.transactions.contains(&overdraft));
+    assert_eq!(
+        blockchain
+            .get_transaction(&overdraft.id)
+            .unwrap()
+            .eviction_reason,
+        Some(EvictionReason::Overdraft)
+    );
+    assert_eq!(
+        blockchain
+            .get_balance_of_wallet("a", "pass")
+            .unwrap()
+            .balance,
+        10 + blockchain.mining_reward
+    );
+}
+
+#[test]
+fn test_mempool_eviction_policy() {
+    let blockchain = BlockChain::default();
+    for (address, balance) in [("a", 1000), ("b", 0)] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let send = |amount: u32, fee: u32, expiry: Option<Expiry>| {
+        blockchain.submit_transaction(TransactionInfo {
+            from_address: "a".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "b".to_string(),
+            amount,
+            fee,
+            expiry,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        })
+    };
+    let balance = |address: &str| {
+        blockchain
+            .get_balance_of_wallet(address, "pass")
+            .unwrap()
+            .balance
+    };
+
+    //THE SENDER PAYS THE FEE TOGETHER WITH THE AMOUNT AND THE MINER GETS IT WITH THE REWARD
+    let transaction = send(10, 5, None).unwrap();
+    assert_eq!(transaction.fee, 5);
+    assert_eq!(balance("a"), 985);
+    assert_eq!(
+        blockchain
+            .pending_transactions
+            .lock()
+            .unwrap()
+            .pending_spend("a"),
+        15
+    );
+    blockchain.mine_pending_transactions("b").unwrap();
+    assert_eq!(balance("b"), 10);
+    let reward = blockchain
+        .pending_transactions
+        .lock()
+        .unwrap()
+        .iter()
+        .next()
+        .unwrap()
+        .details();
+    assert_eq!(reward.amount, blockchain.mining_reward + 5);
+    blockchain.mine_pending_transactions("b").unwrap();
+    assert_eq!(balance("b"), 10 + blockchain.mining_reward + 5);
+    assert!(blockchain.validate_chain().is_ok());
+
+    //A TRANSACTION CAN'T BE CREATED ALREADY EXPIRED, THE NEXT BLOCK IS AT HEIGHT 3
+    assert!(matches!(
+        send(10, 0, Some(Expiry::Height(2))),
+        Err(BlockChainError::TransactionExpired)
+    ));
+    assert!(matches!(
+        send(10, 0, Some(Expiry::Timestamp(1))),
+        Err(BlockChainError::TransactionExpired)
+    ));
+    assert!(matches!(
+        send(u32::MAX, 1, None),
+        Err(BlockChainError::InvalidAmount)
+    ));
+    let expiring = send(10, 0, Some(Expiry::Height(3))).unwrap();
+    assert_eq!(
+        blockchain
+            .get_transaction(&expiring.id)
+            .unwrap()
+            .transaction
+            .expiry,
+        Some(Expiry::Height(3))
+    );
+
+    //A SWEEP EVICTS THE TRANSACTIONS THAT EXPIRED WHILE PENDING AND GIVES THEIR COINS BACK
+    let mut snapshot = blockchain.export_snapshot();
+    snapshot.pending_transactions.push(TransactionDetails {
+        id: "expired".to_string(),
+        from_address: "a".to_string(),
+        to_address: "b".to_string(),
+        amount: 20,
+        timestamp: expiring.timestamp,
+        fee: 0,
+        expiry: Some(Expiry::Height(2)),
+        nonce: 0,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
+        settlement: None,
+    });
+    blockchain.import_snapshot(snapshot).unwrap();
+    let before = balance("a");
+    assert_eq!(blockchain.sweep_mempool().unwrap(), 1);
+    assert_eq!(balance("a"), before + 20);
+    assert_eq!(
+        blockchain
+            .get_transaction("expired")
+            .unwrap()
+            .eviction_reason,
+        Some(EvictionReason::Expired)
+    );
+    assert_eq!(blockchain.sweep_mempool().unwrap(), 0);
+
+    //OVER THE LIMIT, THE LOWEST FEES ARE EVICTED FIRST AND, FOR THE SAME FEE, THE NEWEST
+    blockchain.mine_pending_transactions("b").unwrap();
+    let low = send(10, 1, None).unwrap();
+    let high = send(10, 3, None).unwrap();
+    let newest_low = send(10, 1, None).unwrap();
+    let middle = send(10, 2, None).unwrap();
+    blockchain.set_mempool_limits(MempoolLimits {
+        max_transactions: 3,
+        ..MempoolLimits::default()
+    });
+    let before = balance("a");
+    assert_eq!(blockchain.sweep_mempool().unwrap(), 2);
+    assert_eq!(balance("a"), before + 22);
+    for evicted in [&low, &newest_low] {
+        assert_eq!(
+            blockchain
+                .get_transaction(&evicted.id)
+                .unwrap()
+                .eviction_reason,
+            Some(EvictionReason::LowFee)
+        );
+    }
+    let pending: Vec<String> = blockchain
+        .pending_transactions
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|transaction| transaction.id.clone())
+        .collect();
+    assert_eq!(pending[1..], [high.id.clone(), middle.id.clone()]);
+
+    //THE MEMORY BUDGET IS A LIMIT TOO, THE MINER REWARD IS NEVER EVICTED
+    let bytes = blockchain.pending_transactions.lock().unwrap().bytes();
+    blockchain.set_mempool_limits(MempoolLimits {
+        max_transactions: 3,
+        max_bytes: bytes - 1,
+    });
+    assert_eq!(blockchain.sweep_mempool().unwrap(), 1);
+    assert_eq!(
+        blockchain.get_transaction(&middle.id).unwrap().status,
+        "evicted"
+    );
+    blockchain.set_mempool_limits(MempoolLimits {
+        max_transactions: 0,
+        max_bytes: 0,
+    });
+    assert_eq!(blockchain.sweep_mempool().unwrap(), 1);
+    assert_eq!(blockchain.pending_transactions.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_replace_and_cancel() {
+    let path = std::env::temp_dir().join(format!("replace-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let blockchain = BlockChain::default();
+    blockchain.open_log(&path).unwrap();
+    for (address, balance) in [("a", 100), ("b", 0)] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let send = |amount: u32, fee: u32, nonce: Option<u64>| {
+        blockchain.submit_transaction(TransactionInfo {
+            from_address: "a".to_string(),
+            from_password: "pass".to_string(),
+            to_address: "b".to_string(),
+            amount,
+            fee,
+            expiry: None,
+            nonce,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        })
+    };
+    let balance = |address: &str| {
+        blockchain
+            .get_balance_of_wallet(address, "pass")
+            .unwrap()
+            .balance
+    };
+    let mut receiver = blockchain.events.subscribe();
+
+    //EVERY NEW TRANSACTION GETS THE NEXT NONCE OF THE SENDER
+    let first = send(50, 1, None).unwrap();
+    let second = send(10, 0, Some(1)).unwrap();
+    assert_eq!((first.nonce, second.nonce), (0, 1));
+    assert_eq!(balance("a"), 39);
+    assert!(matches!(
+        send(10, 0, Some(5)),
+        Err(BlockChainError::InvalidNonce)
+    ));
+
+    //A TRANSACTION WITH THE SAME NONCE AND A HIGHER FEE TAKES THE PLACE OF THE PENDING ONE
+    assert!(matches!(
+        send(60, 1, Some(0)),
+        Err(BlockChainError::ReplacementFeeTooLow)
+    ));
+    let replacement = send(60, 2, Some(0)).unwrap();
+    assert_eq!(replacement.nonce, 0);
+    assert_eq!(balance("a"), 28);
+    let replaced = blockchain.get_transaction(&first.id).unwrap();
+    assert_eq!(replaced.eviction_reason, Some(EvictionReason::Replaced));
+    let pending: Vec<String> = blockchain
+        .pending_transactions
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|transaction| transaction.id.clone())
+        .collect();
+    assert_eq!(pending, [replacement.id.clone(), second.id.clone()]);
+    assert!(std::iter::from_fn(|| receiver.try_recv().ok()).any(|event| matches!(
+        event,
+        Event::TransactionEvicted { transaction, reason: EvictionReason::Replaced } if transaction.id == first.id
+    )));
+
+    //THE COINS OF THE REPLACED TRANSACTION CAN BE SPENT BY THE REPLACEMENT, NOT THE ONES OF THE OTHER PENDING TRANSACTIONS
+    assert!(matches!(
+        send(80, 1, Some(1)),
+        Err(BlockChainError::PendingSpendConflict)
+    ));
+    assert!(matches!(
+        send(100, 1, Some(1)),
+        Err(BlockChainError::NotEnoughCoins)
+    ));
+
+    //ONLY THE SENDER CAN CANCEL A PENDING TRANSACTION AND THE COINS GO BACK TO IT
+    assert!(matches!(
+        blockchain.cancel_transaction(&second.id, "wrong"),
+        Err(BlockChainError::WrongPassword)
+    ));
+    let cancelled = blockchain.cancel_transaction(&second.id, "pass").unwrap();
+    assert_eq!(cancelled.status, "evicted");
+    assert_eq!(cancelled.eviction_reason, Some(EvictionReason::Cancelled));
+    assert_eq!(balance("a"), 38);
+    assert!(matches!(
+        blockchain.cancel_transaction(&second.id, "pass"),
+        Err(BlockChainError::TransactionNotPending)
+    ));
+    assert!(matches!(
+        blockchain.cancel_transaction("missing", "pass"),
+        Err(BlockChainError::TransactionNotFound)
+    ));
+
+    //A MINED TRANSACTION AND A MINER REWARD CAN'T BE CANCELLED
+    blockchain.mine_pending_transactions("b").unwrap();
+    assert_eq!(balance("b"), 60);
+    assert!(matches!(
+        blockchain.cancel_transaction(&replacement.id, "pass"),
+        Err(BlockChainError::TransactionNotPending)
+    ));
+    let reward = blockchain
+        .pending_transactions
+        .lock()
+        .unwrap()
+        .iter()
+        .next()
+        .unwrap()
+        .id
+        .clone();
+    assert!(matches!(
+        blockchain.cancel_transaction(&reward, "pass"),
+        Err(BlockChainError::InvalidFromAddress)
+    ));
+    let last = send(5, 0, None).unwrap();
+    assert_eq!(last.nonce, 2);
+
+    //THE COINS TAKEN FOR THE PENDING TRANSACTIONS STILL COUNT AS THE SENDER'S, SO THEY ALWAYS FIT WHEN THEY COME BACK
+    let deposit = |coins: u32| {
+        blockchain.deposit_coins(WalletCoins {
+            address: "a".to_string(),
+            password: "pass".to_string(),
+            coins,
+        })
+    };
+    assert!(matches!(
+        deposit(u32::MAX - balance("a")),
+        Err(BlockChainError::BalanceOverflow)
+    ));
+    deposit(u32::MAX - balance("a") - 5).unwrap();
+    let replacement = send(1, 1, Some(2)).unwrap();
+    assert_eq!(balance("a"), u32::MAX - 2);
+    blockchain
+        .cancel_transaction(&replacement.id, "pass")
+        .unwrap();
+    assert_eq!(balance("a"), u32::MAX);
+
+    //A TRANSACTION THE 'TO' WALLET CAN'T HOLD IS REJECTED, OR EVICTED WHEN THE ONES BEFORE IT FILL THE WALLET
+    assert!(matches!(
+        send(u32::MAX - 50, 0, None),
+        Err(BlockChainError::BalanceOverflow)
+    ));
+    let filling = send(u32::MAX - 1000, 0, None).unwrap();
+    let overflowing = send(900, 0, None).unwrap();
+    blockchain.mine_pending_transactions("a").unwrap();
+    assert_eq!(balance("b"), u32::MAX - 838);
+    assert_eq!(
+        blockchain.get_transaction(&filling.id).unwrap().status,
+        "confirmed"
+    );
+    assert_eq!(
+        blockchain
+            .get_transaction(&overflowing.id)
+            .unwrap()
+            .eviction_reason,
+        Some(EvictionReason::BalanceOverflow)
+    );
+    assert_eq!(balance("a"), 1000);
+
+    //THE REPLACEMENTS AND THE CANCELLATIONS ARE REPLAYED FROM THE LOG
+    let replayed = BlockChain::default();
+    replayed
+        .replay(&read_entries(&fs::read(&path).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(
+        replayed.export_snapshot().encode(SnapshotFormat::Jsonl),
+        blockchain.export_snapshot().encode(SnapshotFormat::Jsonl)
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_batch() {
+    let path = std::env::temp_dir().join(format!("batch-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let blockchain = BlockChain::default();
+    blockchain.open_log(&path).unwrap();
+    for (address, balance) in [("a", 100), ("b", 100), ("c", 0)] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let transfer = |from: &str, to: &str, amount: u32| TransactionInfo {
+        from_address: from.to_string(),
+        from_password: "pass".to_string(),
+        to_address: to.to_string(),
+        amount,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: None,
+    };
+    let balance = |address: &str| {
+        blockchain
+            .get_balance_of_wallet(address, "pass")
+            .unwrap()
+            .balance
+    };
+    let statuses = |items: &[BatchItem]| -> Vec<BatchItemStatus> {
+        items.iter().map(|item| item.status).collect()
+    };
+
+    //ALL THE TRANSACTIONS OF A VALID BATCH ARE ADDED IN ORDER
+    let items = blockchain
+        .submit_batch(
+            vec![
+                transfer("a", "c", 30),
+                transfer("b", "c", 20),
+                transfer("a", "c", 40),
+            ],
+            BatchMode::Atomic,
+        )
+        .unwrap();
+    assert_eq!(statuses(&items), [BatchItemStatus::Accepted; 3]);
+    let first = items[0].transaction.clone().unwrap();
+    assert_eq!(items[2].transaction.as_ref().unwrap().nonce, 1);
+    assert_eq!((balance("a"), balance("b")), (30, 80));
+    assert_eq!(blockchain.pending_transactions.lock().unwrap().len(), 3);
+
+    //IN ATOMIC MODE THE TOTAL OF A SENDER IS CHECKED ONCE, AND ONE INVALID TRANSACTION STOPS THE WHOLE BATCH
+    let items = blockchain
+        .submit_batch(
+            vec![
+                transfer("b", "c", 10),
+                transfer("a", "c", 20),
+                transfer("a", "c", 20),
+            ],
+            BatchMode::Atomic,
+        )
+        .unwrap();
+    assert_eq!(
+        statuses(&items),
+        [
+            BatchItemStatus::NotApplied,
+            BatchItemStatus::Rejected,
+            BatchItemStatus::Rejected
+        ]
+    );
+    assert_eq!(items[1].error.as_deref(), Some("Not enough coins!"));
+    let items = blockchain
+        .submit_batch(
+            vec![transfer("b", "c", 10), transfer("b", "missing", 10)],
+            BatchMode::Atomic,
+        )
+        .unwrap();
+    assert_eq!(
+        statuses(&items),
+        [BatchItemStatus::NotApplied, BatchItemStatus::Rejected]
+    );
+    assert_eq!((balance("a"), balance("b")), (30, 80));
+    assert_eq!(blockchain.pending_transactions.lock().unwrap().len(), 3);
+
+    //IN BEST-EFFORT MODE THE VALID TRANSACTIONS ARE ADDED, A TRANSACTION SEES THE ONES BEFORE IT
+    let replacement = TransactionInfo {
+        fee: 1,
+        nonce: Some(first.nonce),
+        ..transfer("a", "c", 50)
+    };
+    let items = blockchain
+        .submit_batch(
+            vec![
+                transfer("b", "missing", 10),
+                transfer("b", "c", 10),
+                replacement,
+                transfer("a", "c", 20),
+            ],
+            BatchMode::BestEffort,
+        )
+        .unwrap();
+    assert_eq!(
+        statuses(&items),
+        [
+            BatchItemStatus::Rejected,
+            BatchItemStatus::Accepted,
+            BatchItemStatus::Accepted,
+            BatchItemStatus::Rejected
+        ]
+    );
+    assert_eq!(
+        items[3].error.as_deref(),
+        Some("The coins are already spent by pending transactions!")
+    );
+    assert_eq!((balance("a"), balance("b")), (9, 70));
+    assert_eq!(
+        blockchain
+            .get_transaction(&first.id)
+            .unwrap()
+            .eviction_reason,
+        Some(EvictionReason::Replaced)
+    );
+
+    //A BATCH MUST HAVE TRANSACTIONS, BUT NOT TOO MANY
+    assert!(matches!(
+        blockchain.submit_batch(vec![], BatchMode::Atomic),
+        Err(BlockChainError::EmptyBatch)
+    ));
+    assert!(matches!(
+        blockchain.submit_batch(
+            vec![transfer("a", "c", 1); MAX_BATCH_SIZE + 1],
+            BatchMode::BestEffort
+        ),
+        Err(BlockChainError::BatchTooLarge)
+    ));
+
+    //THE BATCHES ARE REPLAYED FROM THE LOG
+    blockchain.mine_pending_transactions("c").unwrap();
+    assert_eq!(balance("c"), 120);
+    let replayed = BlockChain::default();
+    replayed
+        .replay(&read_entries(&fs::read(&path).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(
+        replayed.export_snapshot().encode(SnapshotFormat::Jsonl),
+        blockchain.export_snapshot().encode(SnapshotFormat::Jsonl)
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_memo_and_metadata() {
+    let blockchain = BlockChain::default();
+    for address in ["a", "b"] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 100,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let transfer = |memo: Option<&str>, metadata: &[(&str, &str)]| TransactionInfo {
+        from_address: "a".to_string(),
+        from_password: "pass".to_string(),
+        to_address: "b".to_string(),
+        amount: 10,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: memo.map(str::to_string),
+        metadata: metadata
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        lock: None,
+        hash_lock: None,
+    };
+
+    //THE MEMO AND THE METADATA ARE BOUNDED
+    let memo = "m".repeat(MAX_MEMO_LENGTH + 1);
+    assert!(matches!(
+        blockchain.submit_transaction(transfer(Some(&memo), &[])),
+        Err(BlockChainError::MemoTooLong)
+    ));
+    assert!(matches!(
+        blockchain.submit_transaction(transfer(None, &[("", "42")])),
+        Err(BlockChainError::InvalidMetadata)
+    ));
+    let keys: Vec<String> = (0..=MAX_METADATA_ENTRIES).map(|i| i.to_string()).collect();
+    let entries: Vec<(&str, &str)> = keys.iter().map(|key| (key.as_str(), "")).collect();
+    assert!(matches!(
+        blockchain.submit_transaction(transfer(None, &entries)),
+        Err(BlockChainError::InvalidMetadata)
+    ));
+
+    //THEY ARE RETURNED WITH THE TRANSACTION AND IN THE BLOCK
+    let invoice = blockchain
+        .submit_transaction(transfer(
+            Some("Invoice 42 for March"),
+            &[("invoice", "42"), ("team", "sales")],
+        ))
+        .unwrap();
+    assert_eq!(invoice.memo.as_deref(), Some("Invoice 42 for March"));
+    assert_eq!(invoice.metadata["invoice"], "42");
+    let plain = blockchain.submit_transaction(transfer(None, &[])).unwrap();
+    let block = blockchain.mine_block("a").unwrap();
+    assert_eq!(block.transactions[..2], [invoice.clone(), plain]);
+
+    //THE BLOCKS AND THE HISTORY CAN BE SEARCHED BY MEMO AND METADATA
+    let blocks = |memo: Option<&str>, key: Option<&str>, value: Option<&str>| {
+        let query = BlockQuery {
+            memo: memo.map(str::to_string),
+            metadata_key: key.map(str::to_string),
+            metadata_value: value.map(str::to_string),
+            ..Default::default()
+        };
+        let page = blockchain.list_blocks(&query).unwrap();
+        page.items
+            .iter()
+            .map(|block| block.index)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(blocks(Some("Invoice 42"), None, None), [1]);
+    assert_eq!(blocks(None, Some("invoice"), Some("42")), [1]);
+    assert!(blocks(None, Some("invoice"), Some("43")).is_empty());
+    assert_eq!(blocks(None, None, Some("sales")), [1]);
+    assert!(blocks(Some("April"), None, None).is_empty());
+
+    let history = |query: HistoryQuery| {
+        let page = blockchain.get_wallet_history("b", "pass", &query).unwrap();
+        page.items
+            .into_iter()
+            .map(|entry| entry.transaction)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        history(HistoryQuery {
+            metadata_key: Some("team".to_string()),
+            ..Default::default()
+        }),
+        vec![invoice.clone()]
+    );
+    assert_eq!(
+        history(HistoryQuery {
+            memo: Some("March".to_string()),
+            direction: Some(Direction::Out),
+            ..Default::default()
+        }),
+        []
+    );
+
+    //THEY ARE PART OF THE HASH AND ARE KEPT IN THE SNAPSHOTS
+    for format in [SnapshotFormat::Jsonl, SnapshotFormat::Binary] {
+        let snapshot = ChainSnapshot::decode(&blockchain.export_snapshot().encode(format)).unwrap();
+        assert_eq!(snapshot.blocks[1].transactions[0], invoice);
+    }
+    let mut snapshot = blockchain.export_snapshot();
+    snapshot.blocks[1].transactions[0]
+        .metadata
+        .insert("invoice".to_string(), "43".to_string());
+    assert!(matches!(
+        BlockChain::default().import_snapshot(snapshot),
+        Err(BlockChainError::InvalidChain)
+    ));
+}
+
+#[test]
+fn test_multisig() {
+    let path = std::env::temp_dir().join(format!("multisig-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let blockchain = BlockChain::default();
+    blockchain.open_log(&path).unwrap();
+    for (address, password) in [("alice", "a"), ("bob", "b"), ("carol", "c"), ("shop", "s")] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance: 0,
+                password: password.to_string(),
+            })
+            .unwrap();
+    }
+    let multisig = |address: &str, owners: &[&str], threshold: usize| MultisigWalletInfo {
+        address: address.to_string(),
+        password: "vault".to_string(),
+        owners: owners.iter().map(|owner| owner.to_string()).collect(),
+        threshold,
+    };
+    let owner = |owner: &str, password: &str| OwnerCredentials {
+        owner: owner.to_string(),
+        password: password.to_string(),
+    };
+    let proposal = |owner: &str, password: &str, amount: u32| ProposalInfo {
+        owner: owner.to_string(),
+        password: password.to_string(),
+        to_address: "shop".to_string(),
+        amount,
+        fee: 0,
+        memo: Some("Rent".to_string()),
+        metadata: Metadata::new(),
+        expires_in: None,
+    };
+
+    //THE OWNERS MUST BE DIFFERENT EXISTING WALLETS AND THE THRESHOLD BETWEEN 1 AND THEIR NUMBER
+    for invalid in [
+        multisig("vault", &["alice", "alice"], 1),
+        multisig("vault", &["alice", "bob"], 3),
+        multisig("vault", &["alice", "bob"], 0),
+        multisig("vault", &["alice", "dave"], 1),
+        multisig("vault", &[], 0),
+    ] {
+        assert!(matches!(
+            blockchain.create_multisig_wallet(invalid),
+            Err(BlockChainError::InvalidMultisig)
+        ));
+    }
+    let vault = blockchain
+        .create_multisig_wallet(multisig("vault", &["alice", "bob", "carol"], 2))
+        .unwrap();
+    assert_eq!((vault.balance, vault.threshold), (0, 2));
+    assert!(matches!(
+        blockchain.create_multisig_wallet(multisig("other", &["vault"], 1)),
+        Err(BlockChainError::InvalidMultisig)
+    ));
+    blockchain
+        .add_coins(WalletCoins {
+            address: "vault".to_string(),
+            password: "vault".to_string(),
+            coins: 100,
+        })
+        .unwrap();
+
+    //THE PASSWORD OF THE WALLET IS NOT ENOUGH TO SEND COINS FROM IT
+    assert!(matches!(
+        blockchain.submit_transaction(TransactionInfo {
+            from_address: "vault".to_string(),
+            from_password: "vault".to_string(),
+            to_address: "shop".to_string(),
+            amount: 10,
+            fee: 0,
+            expiry: None,
+            nonce: None,
+            memo: None,
+            metadata: Metadata::new(),
+            lock: None,
+            hash_lock: None,
+        }),
+        Err(BlockChainError::MultisigApprovalRequired)
+    ));
+
+    //ONLY THE OWNERS CAN PROPOSE, WITH THE PASSWORDS OF THEIR OWN WALLETS
+    assert!(matches!(
+        blockchain.propose_transfer("vault", proposal("shop", "s", 10)),
+        Err(BlockChainError::NotAnOwner)
+    ));
+    assert!(matches!(
+        blockchain.propose_transfer("vault", proposal("alice", "b", 10)),
+        Err(BlockChainError::WrongPassword)
+    ));
+    assert!(matches!(
+        blockchain.propose_transfer("alice", proposal("alice", "a", 10)),
+        Err(BlockChainError::NotMultisigWallet)
+    ));
+    assert!(matches!(
+        blockchain.propose_transfer(
+            "vault",
+            ProposalInfo {
+                expires_in: Some(0),
+                ..proposal("alice", "a", 10)
+            }
+        ),
+        Err(BlockChainError::InvalidProposalLifetime)
+    ));
+
+    //THE PROPOSAL IS THE FIRST APPROVAL, THE SECOND ONE ADDS THE TRANSFER TO PENDING TRANSACTIONS
+    let rent = blockchain
+        .propose_transfer("vault", proposal("alice", "a", 30))
+        .unwrap();
+    assert_eq!(rent.status, ProposalStatus::Pending);
+    assert_eq!(rent.approvals.len(), 1);
+    assert!(blockchain.pending_transactions.lock().unwrap().is_empty());
+    assert!(matches!(
+        blockchain.approve_proposal(&rent.id, owner("alice", "a")),
+        Err(BlockChainError::AlreadyApproved)
+    ));
+    assert!(matches!(
+        blockchain.approve_proposal(&rent.id, owner("shop", "s")),
+        Err(BlockChainError::NotAnOwner)
+    ));
+    assert!(matches!(
+        blockchain.approve_proposal("missing", owner("bob", "b")),
+        Err(BlockChainError::ProposalNotFound)
+    ));
+    let rent = blockchain
+        .approve_proposal(&rent.id, owner("carol", "c"))
+        .unwrap();
+    assert_eq!(rent.status, ProposalStatus::Executed);
+    let transaction_id = rent.transaction_id.clone().unwrap();
+    let pending = blockchain.get_transaction(&transaction_id).unwrap();
+    assert_eq!(pending.transaction.from_address, "vault");
+    assert_eq!(pending.transaction.memo.as_deref(), Some("Rent"));
+    assert_eq!(
+        blockchain
+            .get_balance_of_wallet("vault", "vault")
+            .unwrap()
+            .balance,
+        70
+    );
+    assert!(matches!(
+        blockchain.approve_proposal(&rent.id, owner("bob", "b")),
+        Err(BlockChainError::ProposalNotPending)
+    ));
+    assert!(matches!(
+        blockchain.cancel_transaction(&transaction_id, "vault"),
+        Err(BlockChainError::MultisigApprovalRequired)
+    ));
+
+    //THE APPROVALS ARE KEPT TO SEE WHO APPROVED WHAT
+    let approvers: Vec<&str> = rent
+        .approvals
+        .iter()
+        .map(|approval| approval.owner.as_str())
+        .collect();
+    assert_eq!(approvers, ["alice", "carol"]);
+    assert_eq!(
+        blockchain
+            .get_proposal(&rent.id, &owner("bob", "b"))
+            .unwrap(),
+        rent
+    );
+    assert!(matches!(
+        blockchain.get_proposal(&rent.id, &owner("shop", "s")),
+        Err(BlockChainError::NotAnOwner)
+    ));
+
+    //AN APPROVAL THAT CAN'T ADD THE TRANSFER IS NOT RECORDED
+    let too_much = blockchain
+        .propose_transfer("vault", proposal("bob", "b", 200))
+        .unwrap();
+    assert!(matches!(
+        blockchain.approve_proposal(&too_much.id, owner("alice", "a")),
+        Err(BlockChainError::NotEnoughCoins)
+    ));
+    let proposals = blockchain
+        .get_proposals("vault", &owner("carol", "c"))
+        .unwrap();
+    assert_eq!(proposals, [rent.clone(), too_much.clone()]);
+
+    //THE PROPOSALS ARE REPLAYED FROM THE LOG, IT DOESN'T KEEP THE PASSWORDS OF THE OWNERS THAT PROPOSED OR APPROVED THEM
+    blockchain.mine_pending_transactions("shop").unwrap();
+    let log = fs::read_to_string(&path).unwrap();
+    assert!(log
+        .lines()
+        .filter(
+            |line| line.contains("\"propose_transfer\"") || line.contains("\"approve_proposal\"")
+        )
+        .all(|line| !line.contains("password")));
+    let replayed = BlockChain::default();
+    replayed
+        .replay(&read_entries(&fs::read(&path).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(
+        replayed.export_snapshot().encode(SnapshotFormat::Jsonl),
+        blockchain.export_snapshot().encode(SnapshotFormat::Jsonl)
+    );
+    fs::remove_file(&path).unwrap();
+
+    //A PROPOSAL THAT WAS NOT APPROVED IN TIME CAN NO LONGER BE APPROVED
+    let mut snapshot =
+        ChainSnapshot::decode(&blockchain.export_snapshot().encode(SnapshotFormat::Binary))
+            .unwrap();
+    snapshot.proposals[1].expires_at = snapshot.proposals[1].created_at;
+    let imported = BlockChain::default();
+    imported.import_snapshot(snapshot).unwrap();
+    assert!(matches!(
+        imported.approve_proposal(&too_much.id, owner("carol", "c")),
+        Err(BlockChainError::ProposalExpired)
+    ));
+    assert_eq!(
+        imported
+            .get_proposal(&too_much.id, &owner("carol", "c"))
+            .unwrap()
+            .status,
+        ProposalStatus::Expired
+    );
+}
+
+#[test]
+fn test_lock_time() {
+    let path = std::env::temp_dir().join(format!("lock-time-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let blockchain = BlockChain::default();
+    blockchain.open_log(&path).unwrap();
+    for (address, balance) in [("payroll", 100), ("alice", 0), ("miner", 0)] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance,
+                password: "pass".to_string(),
+            })
+            .unwrap();
+    }
+    let transfer = |from: &str, to: &str, amount: u32, lock: Option<LockTime>| TransactionInfo {
+        from_address: from.to_string(),
+        from_password: "pass".to_string(),
+        to_address: to.to_string(),
+        amount,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock,
+        hash_lock: None,
+    };
+    let balance = |address: &str| {
+        let balance = blockchain.get_balance_of_wallet(address, "pass").unwrap();
+        (balance.balance, balance.locked)
+    };
+
+    //THE COINS OF A LOCKED TRANSACTION ARE MINED BUT CAN'T BE SPENT BEFORE THE LOCK
+    let payout = blockchain
+        .submit_transaction(transfer("payroll", "alice", 30, Some(LockTime::Height(3))))
+        .unwrap();
+    assert_eq!(payout.lock, Some(LockTime::Height(3)));
+    blockchain
+        .submit_transaction(transfer(
+            "payroll",
+            "alice",
+            20,
+            Some(LockTime::Timestamp(u64::MAX)),
+        ))
+        .unwrap();
+    blockchain
+        .submit_transaction(transfer(
+            "payroll",
+            "alice",
+            5,
+            Some(LockTime::Timestamp(1)),
+        ))
+        .unwrap();
+    let mut receiver = blockchain.events.subscribe();
+    blockchain.mine_pending_transactions("miner").unwrap();
+    assert_eq!(balance("alice"), (5, 50));
+
+    //THE EVENTS REPORT WHAT THE WALLET CAN SPEND, WITH THE COINS WHOSE LOCK HAS PASSED
+    let events: Vec<Event> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+    assert!(events.contains(&Event::BalanceChanged {
+        address: "alice".to_string(),
+        balance: 5,
+    }));
+    assert_eq!(balance("payroll"), (45, 0));
+    assert!(matches!(
+        blockchain.submit_transaction(transfer("alice", "payroll", 10, None)),
+        Err(BlockChainError::NotEnoughCoins)
+    ));
+
+    //A HEIGHT LOCK PASSES FOR THE TRANSACTIONS THAT GO INTO THE BLOCK AT THAT HEIGHT
+    blockchain.mine_pending_transactions("miner").unwrap();
+    assert_eq!(balance("alice"), (35, 20));
+    blockchain
+        .submit_transaction(transfer("alice", "payroll", 33, None))
+        .unwrap();
+    assert_eq!(balance("alice"), (2, 20));
+    assert_eq!(
+        blockchain
+            .get_wallet("alice", AddressType::GENERIC)
+            .unwrap()
+            .locked
+            .len(),
+        1
+    );
+
+    //THE LOCK IS PART OF THE HASH AND THE LOCKED COINS ARE KEPT IN THE SNAPSHOTS AND REPLAYED FROM THE LOG
+    let mut snapshot = blockchain.export_snapshot();
+    snapshot.blocks[1].transactions[0].lock = Some(LockTime::Height(1));
+    assert!(matches!(
+        BlockChain::default().import_snapshot(snapshot),
+        Err(BlockChainError::InvalidChain)
+    ));
+    let imported = BlockChain::default();
+    imported
+        .import_snapshot(
+            ChainSnapshot::decode(&blockchain.export_snapshot().encode(SnapshotFormat::Binary))
+                .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        imported
+            .get_balance_of_wallet("alice", "pass")
+            .unwrap()
+            .locked,
+        20
+    );
+    let replayed = BlockChain::default();
+    replayed
+        .replay(&read_entries(&fs::read(&path).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(
+        replayed.export_snapshot().encode(SnapshotFormat::Jsonl),
+        blockchain.export_snapshot().encode(SnapshotFormat::Jsonl)
+    );
+    fs::remove_file(&path).unwrap();
+
+    //A WALLET CAN'T HOLD MORE THAN u32::MAX COINS, COUNTING ITS LOCKED ONES AND THE ONES TAKEN FOR ITS PENDING TRANSACTIONS
+    let mut whale = Wallet::new("whale".to_string(), u32::MAX - 10, "pass".to_string());
+    whale.locked.push(LockedCoins {
+        transaction_id: "a".to_string(),
+        amount: 10,
+        lock: LockTime::Height(9),
+    });
+    assert!(whale.check_credit(0, 0).is_ok());
+    assert!(matches!(
+        whale.check_credit(0, 1),
+        Err(BlockChainError::BalanceOverflow)
+    ));
+    assert!(matches!(
+        whale.check_credit(1, 0),
+        Err(BlockChainError::BalanceOverflow)
+    ));
+    assert_eq!(whale.balance(0, 9).balance, u32::MAX);
+    whale.release_locked(0, 9);
+    assert_eq!((whale.balance, whale.locked.len()), (u32::MAX, 0));
+    whale.locked.push(LockedCoins {
+        transaction_id: "b".to_string(),
+        amount: 1,
+        lock: LockTime::Height(9),
+    });
+    assert!(matches!(
+        BlockChain::default().update_wallet(whale),
+        Err(BlockChainError::BalanceOverflow)
+    ));
+}
+
+#[test]
+fn test_escrow() {
+    let path = std::env::temp_dir().join(format!("escrow-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let blockchain = BlockChain::default();
+    blockchain.open_log(&path).unwrap();
+    for (address, balance) in [("alice", 100), ("bob", 0), ("miner", 0)] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance,
+                password: address.to_string(),
+            })
+            .unwrap();
+    }
+    let escrow = |amount: u32, hash: &str, timeout: LockTime| TransactionInfo {
+        from_address: "alice".to_string(),
+        from_password: "alice".to_string(),
+        to_address: "bob".to_string(),
+        amount,
+        fee: 0,
+        expiry: None,
+        nonce: None,
+        memo: None,
+        metadata: Metadata::new(),
+        lock: None,
+        hash_lock: Some(HashLock {
+            hash: hash.to_string(),
+            timeout,
+        }),
+    };
+    let claim = |password: &str, preimage: &str| ClaimInfo {
+        password: password.to_string(),
+        preimage: preimage.to_string(),
+    };
+    let balance = |address: &str| {
+        blockchain
+            .get_balance_of_wallet(address, address)
+            .unwrap()
+            .balance
+    };
+    let hash = hash_of("secret");
+
+    //THE HASH MUST BE A HEX SHA-256 DIGEST AND THE TIMEOUT MUST NOT HAVE PASSED
+    assert!(matches!(
+        blockchain.submit_transaction(escrow(30, "secret", LockTime::Height(3))),
+        Err(BlockChainError::InvalidHashLock)
+    ));
+    let mut locked = escrow(30, &hash, LockTime::Height(3));
+    locked.lock = Some(LockTime::Height(3));
+    assert!(matches!(
+        blockchain.submit_transaction(locked),
+        Err(BlockChainError::InvalidHashLock)
+    ));
+    assert!(matches!(
+        blockchain.submit_transaction(escrow(30, &hash, LockTime::Timestamp(1))),
+        Err(BlockChainError::HashLockTimedOut)
+    ));
+    assert!(matches!(
+        blockchain.create_wallet(WalletInfo {
+            address: "ESCROW".to_string(),
+            balance: 0,
+            password: "pass".to_string(),
+        }),
+        Err(BlockChainError::EscrowAddress)
+    ));
+
+    //THE COINS OF A MINED ESCROW ARE TAKEN FROM THE SENDER BUT NOT GIVEN TO THE RECIPIENT
+    let claimed = blockchain
+        .submit_transaction(escrow(30, &hash, LockTime::Height(3)))
+        .unwrap();
+    assert!(matches!(
+        blockchain.claim_escrow(&claimed.id, claim("bob", "secret")),
+        Err(BlockChainError::EscrowNotMined)
+    ));
+    blockchain.mine_pending_transactions("miner").unwrap();
+    assert_eq!((balance("alice"), balance("bob")), (70, 0));
+    assert!(matches!(
+        blockchain.claim_escrow("unknown", claim("bob", "secret")),
+        Err(BlockChainError::EscrowNotFound)
+    ));
+
+    //ONLY THE RECIPIENT CAN CLAIM IT WITH THE PREIMAGE, AND THE SENDER CAN'T TAKE IT BACK BEFORE THE TIMEOUT
+    assert!(matches!(
+        blockchain.claim_escrow(&claimed.id, claim("alice", "secret")),
+        Err(BlockChainError::WrongPassword)
+    ));
+    assert!(matches!(
+        blockchain.claim_escrow(&claimed.id, claim("bob", "guess")),
+        Err(BlockChainError::WrongPreimage)
+    ));
+    assert!(matches!(
+        blockchain.refund_escrow(&claimed.id, "alice"),
+        Err(BlockChainError::EscrowNotTimedOut)
+    ));
+    let claim_transaction = blockchain
+        .claim_escrow(&claimed.id, claim("bob", "secret"))
+        .unwrap();
+    assert_eq!(claim_transaction.from_address, "ESCROW");
+    assert_eq!(claim_transaction.to_address, "bob");
+    assert_eq!(claim_transaction.amount, 30);
+    assert_eq!(
+        claim_transaction.settlement,
+        Some(Settlement {
+            escrow: claimed.id.clone(),
+            preimage: Some("secret".to_string()),
+        })
+    );
+    assert!(matches!(
+        blockchain.claim_escrow(&claimed.id, claim("bob", "secret")),
+        Err(BlockChainError::EscrowSettled)
+    ));
+    blockchain.mine_pending_transactions("miner").unwrap();
+    assert_eq!(balance("bob"), 30);
+    assert!(matches!(
+        blockchain.refund_escrow(&claimed.id, "alice"),
+        Err(BlockChainError::EscrowSettled)
+    ));
+
+    //AFTER THE TIMEOUT THE SENDER CAN TAKE BACK AN ESCROW THAT WAS NOT CLAIMED
+    let refunded = blockchain
+        .submit_transaction(escrow(20, &hash, LockTime::Height(4)))
+        .unwrap();
+    blockchain.mine_pending_transactions("miner").unwrap();
+    assert_eq!(balance("alice"), 50);
+    let refund = blockchain.refund_escrow(&refunded.id, "alice").unwrap();
+    assert_eq!(refund.to_address, "alice");
+    assert!(matches!(
+        blockchain.claim_escrow(&refunded.id, claim("bob", "secret")),
+        Err(BlockChainError::EscrowSettled)
+    ));
+    blockchain.mine_pending_transactions("miner").unwrap();
+    assert_eq!((balance("alice"), balance("bob")), (70, 30));
+    blockchain.validate_chain().unwrap();
+
+    //A CHAIN WHERE A CLAIM PAYS SOMEONE ELSE IS NOT VALID, EVEN WITH THE RIGHT HASHES AND PROOF OF WORK
+    let mut snapshot = blockchain.export_snapshot();
+    let claim_block = snapshot
+        .blocks
+        .iter()
+        .position(|block| block.transactions.contains(&claim_transaction))
+        .unwrap();
+    let position = snapshot.blocks[claim_block]
+        .transactions
+        .iter()
+        .position(|transaction| transaction.id == claim_transaction.id)
+        .unwrap();
+    snapshot.blocks[claim_block].transactions[position].to_address = "alice".to_string();
+    for height in claim_block..snapshot.blocks.len() {
+        let mut block = Block::from(&snapshot.blocks[height]);
+        block.set_previous_hash(&snapshot.blocks[height - 1].hash);
+        block.set_hash();
+        block.mine_block(blockchain.difficulty, &AtomicBool::new(false));
+        snapshot.blocks[height] = block.details();
+    }
+    assert!(matches!(
+        BlockChain::default().import_snapshot(snapshot),
+        Err(BlockChainError::InvalidChain)
+    ));
+
+    //THE REPLAY OF THE LOG SETTLES THE SAME ESCROWS, THE LOG DOESN'T KEEP THE PASSWORDS OF THE SETTLEMENTS
+    let log = fs::read_to_string(&path).unwrap();
+    let settlements: Vec<&str> = log
+        .lines()
+        .filter(|line| line.contains("\"settle_escrow\""))
+        .collect();
+    assert_eq!(settlements.len(), 2);
+    assert!(settlements.iter().all(|line| !line.contains("password")));
+    let replayed = BlockChain::default();
+    replayed
+        .replay(&read_entries(&fs::read(&path).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(
+        replayed.export_snapshot().encode(SnapshotFormat::Jsonl),
+        blockchain.export_snapshot().encode(SnapshotFormat::Jsonl)
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_standing_orders() {
+    let path = std::env::temp_dir().join(format!("standing-orders-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let blockchain = BlockChain::default();
+    blockchain.open_log(&path).unwrap();
+    for (address, balance) in [("tenant", 250), ("landlord", 0), ("miner", 0)] {
+        blockchain
+            .create_wallet(WalletInfo {
+                address: address.to_string(),
+                balance,
+                password: address.to_string(),
+            })
+            .unwrap();
+    }
+    blockchain
+        .create_multisig_wallet(MultisigWalletInfo {
+            address: "vault".to_string(),
+            password: "vault".to_string(),
+            owners: vec!["tenant".to_string(), "landlord".to_string()],
+            threshold: 1,
+        })
+        .unwrap();
+    let order =
+        |from: &str, password: &str, interval: u64, start_at: Option<u64>, end_at: Option<u64>| {
+            StandingOrderInfo {
+                from_address: from.to_string(),
+                password: password.to_string(),
+                to_address: "landlord".to_string(),
+                amount: 100,
+                fee: 0,
+                interval,
+                start_at,
+                end_at,
+                memo: Some("rent".to_string()),
+                metadata: Metadata::new(),
+            }
+        };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    //THE PAYER MUST AUTHORISE THE ORDER AND THE SCHEDULE MUST BE VALID
+    for (info, error) in [
+        (
+            order("tenant", "landlord", 3600, None, None),
+            BlockChainError::WrongPassword,
+        ),
+        (
+            order("nobody", "nobody", 3600, None, None),
+            BlockChainError::InvalidFromAddress,
+        ),
+        (
+            order("vault", "vault", 3600, None, None),
+            BlockChainError::MultisigApprovalRequired,
+        ),
+        (
+            order("tenant", "tenant", 59, None, None),
+            BlockChainError::InvalidStandingOrder,
+        ),
+        (
+            order(
+                "tenant",
+                "tenant",
+                MAX_STANDING_ORDER_INTERVAL + 1,
+                None,
+                None,
+            ),
+            BlockChainError::InvalidStandingOrder,
+        ),
+        (
+            order("tenant", "tenant", 3600, Some(1), None),
+            BlockChainError::InvalidStandingOrder,
+        ),
+        (
+            order("tenant", "tenant", 3600, Some(now + 100), Some(now + 50)),
+            BlockChainError::InvalidStandingOrder,
+        ),
+    ] {
+        let result = blockchain.create_standing_order(info);
+        assert_eq!(result.unwrap_err().to_string(), error.to_string());
+    }
+    let mut unknown_recipient = order("tenant", "tenant", 3600, None, None);
+    unknown_recipient.to_address = "nobody".to_string();
+    assert!(matches!(
+        blockchain.create_standing_order(unknown_recipient),
+        Err(BlockChainError::InvalidToAddress)
+    ));
+
+    //THE FIRST PAYMENT OF AN ORDER WITHOUT A START IS DUE RIGHT AWAY, THE OTHER ONE ONLY TOMORROW
+    let rent = blockchain
+        .create_standing_order(order("tenant", "tenant", 3600, None, Some(now + 9000)))
+        .unwrap();
+    let later = blockchain
+        .create_standing_order(order("tenant", "tenant", 3600, Some(now + 86400), None))
+        .unwrap();
+    assert_eq!(rent.status, StandingOrderStatus::Active);
+
+    //AN ORDER IS COMPLETED WHEN ITS NEXT PAYMENT WOULD BE AFTER THE LAST TIME THERE IS
+    let mut last = rent.clone();
+    last.next_payment_at = u64::MAX - 10;
+    last.end_at = None;
+    last.advance();
+    assert_eq!(last.status, StandingOrderStatus::Completed);
+    assert_eq!(last.next_payment_at, u64::MAX - 10);
+    assert_eq!(blockchain.pay_standing_orders().unwrap(), 1);
+    assert_eq!(blockchain.pay_standing_orders().unwrap(), 0);
+    assert_eq!(
+        blockchain
+            .get_balance_of_wallet("tenant", "tenant")
+            .unwrap()
+            .balance,
+        150
+    );
+    let orders = blockchain.get_standing_orders("tenant", "tenant").unwrap();
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[0].payments, 1);
+    assert_eq!(orders[0].next_payment_at, rent.created_at + 3600);
+    let payment = orders[0].transaction_id.clone().unwrap();
+    let pending = blockchain.get_transaction(&payment).unwrap().transaction;
+    assert_eq!(pending.to_address, "landlord");
+    assert_eq!(pending.memo.as_deref(), Some("rent"));
+    assert_eq!(orders[1], later);
+    assert!(blockchain
+        .get_standing_orders("landlord", "landlord")
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        blockchain.get_standing_orders("tenant", "landlord"),
+        Err(BlockChainError::WrongPassword)
+    ));
+
+    //AFTER A WEEK DOWN ONLY THE LATEST RUN IS PAID, THE OLDER ONES ARE SKIPPED AND COUNTED AS MISSED
+    blockchain.mine_pending_transactions("miner").unwrap();
+    let mut snapshot =
+        ChainSnapshot::decode(&blockchain.export_snapshot().encode(SnapshotFormat::Binary))
+            .unwrap();
+    snapshot.standing_orders[0].next_payment_at = rent.created_at - 7 * 24 * 3600;
+    snapshot.standing_orders[0].end_at = Some(rent.created_at + 3600);
+    blockchain.import_snapshot(snapshot).unwrap();
+    assert_eq!(blockchain.pay_standing_orders().unwrap(), 1);
+    assert_eq!(blockchain.pay_standing_orders().unwrap(), 0);
+    let orders = blockchain.get_standing_orders("tenant", "tenant").unwrap();
+    assert_eq!(orders[0].payments, 2);
+    assert_eq!(orders[0].missed, 7 * 24);
+    assert_eq!(orders[0].next_payment_at, rent.created_at + 3600);
+    assert_eq!(orders[0].status, StandingOrderStatus::Active);
+    assert_eq!(
+        blockchain
+            .get_balance_of_wallet("tenant", "tenant")
+            .unwrap()
+            .balance,
+        50
+    );
+
+    //A RUN THE PAYER CAN'T PAY IS MISSED, THE ORDER IS COMPLETED WHEN ITS NEXT PAYMENT WOULD BE AFTER ITS END
+    let mut snapshot = blockchain.export_snapshot();
+    snapshot.standing_orders[0].next_payment_at = rent.created_at;
+    snapshot.standing_orders[0].end_at = Some(rent.created_at);
+    blockchain.import_snapshot(snapshot).unwrap();
+    assert_eq!(blockchain.pay_standing_orders().unwrap(), 0);
+    let orders = blockchain.get_standing_orders("tenant", "tenant").unwrap();
+    assert_eq!(orders[0].payments, 2);
+    assert_eq!(orders[0].missed, 7 * 24 + 1);
+    assert_eq!(orders[0].status, StandingOrderStatus::Completed);
+
+    //ONLY THE PAYER CAN REVOKE AN ACTIVE ORDER
+    assert!(matches!(
+        blockchain.revoke_standing_order(&later.id, "landlord"),
+        Err(BlockChainError::WrongPassword)
+    ));
+    assert!(matches!(
+        blockchain.revoke_standing_order("unknown", "tenant"),
+        Err(BlockChainError::StandingOrderNotFound)
+    ));
+    assert!(matches!(
+        blockchain.revoke_standing_order(&rent.id, "tenant"),
+        Err(BlockChainError::StandingOrderNotActive)
+    ));
+    let revoked = blockchain
+        .revoke_standing_order(&later.id, "tenant")
+        .unwrap();
+    assert_eq!(revoked.status, StandingOrderStatus::Revoked);
+    assert!(matches!(
+        blockchain.revoke_standing_order(&later.id, "tenant"),
+        Err(BlockChainError::StandingOrderNotActive)
+    ));
+
+    //A REVOKED ORDER IS NOT PAID EVEN WHEN IT IS DUE
+    let mut snapshot = blockchain.export_snapshot();
+    snapshot.standing_orders[1].next_payment_at = now;
+    let imported = BlockChain::default();
+    imported.import_snapshot(snapshot.clone()).unwrap();
+    assert_eq!(imported.pay_standing_orders().unwrap(), 0);
+
+    //EVERY ORDER OF A SNAPSHOT IS PAID FROM ONE OF ITS WALLETS
+    snapshot.standing_orders[1].from_address = "nobody".to_string();
+    assert!(matches!(
+        BlockChain::default().import_snapshot(snapshot),
+        Err(BlockChainError::InvalidSnapshot)
+    ));
+
+    //THE REPLAY OF THE LOG MAKES THE SAME PAYMENTS, THE LOG DOESN'T KEEP THE PASSWORDS OF THE ORDERS AND OF THE REVOCATION
+    let log = fs::read_to_string(&path).unwrap();
+    let orders: Vec<&str> = log
+        .lines()
+        .filter(|line| {
+            line.contains("\"create_standing_order\"") || line.contains("\"revoke_standing_order\"")
+        })
+        .collect();
+    assert!(orders.len() > 2);
+    assert!(orders.iter().all(|line| !line.contains("password")));
+    let replayed = BlockChain::default();
+    replayed
+        .replay(&read_entries(&fs::read(&path).unwrap()).unwrap())
+        .unwrap();
+    assert_eq!(
+        replayed.export_snapshot().encode(SnapshotFormat::Jsonl),
+        blockchain.export_snapshot().encode(SnapshotFormat::Jsonl)
+    );
+    let entries = read_entries(&fs::read(&path).unwrap()).unwrap();
+    assert!(entries.iter().any(|entry| matches!(
+        &entry.command,
+        Command::PayStandingOrders { payments, .. } if payments.len() == 1 && payments[0].transaction_id.is_none()
+    )));
+    fs::remove_file(&path).unwrap();
 }